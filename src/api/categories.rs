@@ -0,0 +1,159 @@
+use anyhow::{Context, Result, bail};
+
+use crate::api::client::JamfClient;
+use crate::api::error::JamfError;
+use crate::models::category::{Category, CategoryCreateRequest, CategorySearchResponse};
+use crate::models::package::HrefResponse;
+
+const CATEGORY_PAGE_SIZE: usize = 100;
+/// Priority Jamf Pro gives new categories in its UI.
+const DEFAULT_CATEGORY_PRIORITY: i32 = 9;
+
+impl JamfClient {
+    /// Fetch every category, one page at a time.
+    pub async fn list_categories(&self) -> Result<Vec<Category>> {
+        let mut categories = Vec::new();
+        let mut page = 0;
+
+        loop {
+            let url = format!(
+                "{}/api/v1/categories?page={}&page-size={}&sort=name%3Aasc",
+                self.base_url, page, CATEGORY_PAGE_SIZE
+            );
+
+            let resp = self
+                .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+                .await
+                .context("Failed to list categories")?;
+
+            if !resp.status().is_success() {
+                return Err(JamfError::from_response("Failed to list categories", resp)
+                    .await
+                    .into());
+            }
+
+            let search: CategorySearchResponse = resp
+                .json()
+                .await
+                .map_err(|e| JamfError::schema("Failed to parse category list response", e))?;
+
+            let received = search.results.len();
+            categories.extend(search.results);
+            if received == 0 || categories.len() as i64 >= search.total_count {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(categories)
+    }
+
+    /// Resolve a category name to its record. Fails, listing the available
+    /// names, when no category matches exactly.
+    pub async fn resolve_category(&self, name: &str) -> Result<Category> {
+        let categories = self.list_categories().await?;
+        if let Some(category) = categories.iter().find(|c| c.name == name) {
+            return Ok(category.clone());
+        }
+        bail!(unknown_category_message(name, &categories));
+    }
+
+    /// Like [`resolve_category`](Self::resolve_category), but create the
+    /// category when none matches. The flag says whether it was created.
+    pub async fn resolve_or_create_category(&self, name: &str) -> Result<(Category, bool)> {
+        let categories = self.list_categories().await?;
+        if let Some(category) = categories.into_iter().find(|c| c.name == name) {
+            return Ok((category, false));
+        }
+        Ok((self.create_category(name).await?, true))
+    }
+
+    /// Create a category with Jamf's default priority.
+    pub async fn create_category(&self, name: &str) -> Result<Category> {
+        let url = format!("{}/api/v1/categories", self.base_url);
+        let body = CategoryCreateRequest {
+            name,
+            priority: DEFAULT_CATEGORY_PRIORITY,
+        };
+
+        let resp = self
+            .authed_request(|http| {
+                http.post(&url)
+                    .header("Accept", "application/json")
+                    .json(&body)
+            })
+            .await
+            .context("Failed to create category")?;
+
+        if !resp.status().is_success() {
+            return Err(JamfError::from_response(
+                format!("Failed to create category '{}'", name),
+                resp,
+            )
+            .await
+            .into());
+        }
+
+        let created: HrefResponse = resp
+            .json()
+            .await
+            .map_err(|e| JamfError::schema("Failed to parse category create response", e))?;
+        Ok(Category {
+            id: created.id,
+            name: name.to_string(),
+        })
+    }
+}
+
+/// The error for an unknown category name, listing the ones that exist.
+fn unknown_category_message(name: &str, categories: &[Category]) -> String {
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    if names.is_empty() {
+        return format!(
+            "Category '{}' not found; Jamf Pro has no categories (--create-category creates it)",
+            name
+        );
+    }
+    format!(
+        "Category '{}' not found (--create-category creates it). Available categories: {}",
+        name,
+        names.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+
+    use crate::api::mock::{self, MockState};
+
+    #[tokio::test]
+    async fn resolves_category_names_and_lists_the_available_ones() {
+        let state = Arc::new(MockState {
+            categories: vec!["Browsers", "Utilities"],
+            ..MockState::default()
+        });
+        let client = mock::connect(state.clone()).await;
+
+        assert_eq!(client.resolve_category("Utilities").await.unwrap().id, "2");
+
+        let err = client.resolve_category("Games").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Category 'Games' not found (--create-category creates it). \
+             Available categories: Browsers, Utilities"
+        );
+        assert_eq!(state.created_categories.load(Ordering::SeqCst), 0);
+
+        let (category, created) = client.resolve_or_create_category("Games").await.unwrap();
+        assert!(created);
+        assert_eq!(
+            (category.id.as_str(), category.name.as_str()),
+            ("3", "Games")
+        );
+        let (_, created) = client.resolve_or_create_category("Browsers").await.unwrap();
+        assert!(!created);
+        assert_eq!(state.created_categories.load(Ordering::SeqCst), 1);
+    }
+}
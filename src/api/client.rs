@@ -1,8 +1,16 @@
 use anyhow::{Context, Result, bail};
-use reqwest::Client;
-use serde::Deserialize;
-use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use reqwest::{Certificate, Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{OnceCell, RwLock};
+use tracing::debug;
+
+use crate::api::error::JamfError;
+use crate::api::token_cache::TokenCache;
+use crate::events::{outcome, status};
+use crate::redact;
 
 /// Refresh the token when it has less than this much time remaining.
 const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(30);
@@ -10,75 +18,517 @@ const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(30);
 /// Fallback token lifetime when the server doesn't provide `expires_in`.
 const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
 
+/// Upper bound on the backoff between retries of a failed request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Upper bound on a server-requested `Retry-After` wait, so a misbehaving
+/// proxy can't stall a run for hours.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// The token endpoint rejected the client credentials (HTTP 401).
+///
+/// Kept as a distinct type so callers can tell a rejected secret apart from
+/// other failures and decide whether reloading credentials is worthwhile.
+#[derive(Debug, thiserror::Error)]
+#[error("Authentication failed (HTTP {status}): {body}")]
+pub struct AuthRejected {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+/// Whether the error chain contains a transport-level failure (connection
+/// refused, DNS, timeout) rather than an HTTP error response from Jamf.
+pub fn is_network_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let reqwest_error = match cause.downcast_ref::<JamfError>() {
+            Some(JamfError::Transport(e)) => Some(e),
+            _ => cause.downcast_ref::<reqwest::Error>(),
+        };
+        reqwest_error.is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+    })
+}
+
 #[derive(Deserialize)]
 struct OAuthTokenResponse {
     access_token: String,
     expires_in: Option<u64>,
 }
 
+/// `POST /api/v1/auth/token` response; `expires` is an RFC 3339 UTC time.
+#[derive(Deserialize)]
+struct BasicTokenResponse {
+    token: String,
+    expires: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JamfProVersionResponse {
+    version: String,
+}
+
 struct TokenState {
     access_token: String,
     expires_at: Instant,
+    /// Jamf rejected `access_token` before its expiry; don't reuse it even if
+    /// the shared cache still lists it as valid.
+    rejected: bool,
+}
+
+/// Retries for transient API failures: connection errors and HTTP 408, 429,
+/// 500, 502, 503 or 504. Only idempotent requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further one.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (1-based), capped at 30 seconds.
+    fn delay_for(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+    }
+
+    /// [`delay_for`](Self::delay_for) with up to half of it replaced by
+    /// random jitter, so parallel workers that were throttled together don't
+    /// all come back at the same moment.
+    fn jittered_delay_for(&self, retry: u32) -> Duration {
+        let delay = self.delay_for(retry);
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let fraction = f64::from(seed % 1000) / 1000.0;
+        delay.mul_f64(0.5 + fraction / 2.0)
+    }
+}
+
+/// Time limits for requests to Jamf Pro and JCDS storage.
+///
+/// API calls are short and get a fixed limit. A transfer's limit depends on
+/// its size: an upload may take [`request`](Self::request) plus its size at
+/// [`min_upload_rate`](Self::min_upload_rate), and a download fails only
+/// once it stalls for [`request`](Self::request).
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    /// Establishing a connection, including TLS.
+    pub connect: Duration,
+    /// A whole API call, from sending it to reading its response.
+    pub request: Duration,
+    /// Slowest upload speed tolerated, in bytes per second.
+    pub min_upload_rate: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(15),
+            request: Duration::from_secs(60),
+            min_upload_rate: 256 << 10,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Time allowed to send `bytes` and read the response.
+    pub fn upload(&self, bytes: u64) -> Duration {
+        self.request
+            .saturating_add(Duration::from_secs(bytes / self.min_upload_rate.max(1)))
+    }
+}
+
+/// The wait requested by a `Retry-After` header, in delta-seconds. The
+/// HTTP-date form is ignored (Jamf Cloud sends seconds), falling back to the
+/// regular backoff.
+pub(crate) fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(reqwest::header::RETRY_AFTER)?;
+    let seconds: u64 = value.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// How the client obtains its bearer tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    /// OAuth client credentials (an API client ID and secret) exchanged at
+    /// `/api/oauth/token`.
+    #[default]
+    #[value(name = "oauth")]
+    OAuth,
+    /// A Jamf Pro username and password, sent with basic auth to
+    /// `/api/v1/auth/token`, for instances without API clients.
+    Basic,
+}
+
+/// How `JamfClient::connect` should set up the connection.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Share access tokens with other processes through an on-disk cache.
+    pub prefer_cached_token: bool,
+    /// Override for the JCDS inventory-refresh endpoint path.
+    pub inventory_refresh_path: Option<String>,
+    /// Extra trusted root certificate(s), PEM, e.g. a corporate proxy's CA.
+    pub ca_cert: Option<PathBuf>,
+    /// Retries for transient failures of idempotent API calls.
+    pub retry: RetryPolicy,
+    /// Whether `client_id`/`client_secret` are OAuth client credentials or a
+    /// username and password.
+    pub auth_mode: AuthMode,
+    /// Connect, API call and upload time limits.
+    pub timeouts: Timeouts,
 }
 
 pub struct JamfClient {
     pub base_url: String,
     client_id: String,
     client_secret: String,
+    auth_mode: AuthMode,
     token_state: RwLock<TokenState>,
+    token_cache: Option<TokenCache>,
+    pub(crate) inventory_refresh_path: Option<String>,
+    jamf_version: OnceCell<String>,
+    retry: RetryPolicy,
+    pub(crate) timeouts: Timeouts,
     pub http: Client,
 }
 
 impl JamfClient {
-    pub async fn connect(base_url: &str, client_id: &str, client_secret: &str) -> Result<Self> {
-        let http = Client::builder()
-            .timeout(Duration::from_secs(1800)) // 30 min for large uploads
-            .build()
-            .context("Failed to create HTTP client")?;
+    pub async fn connect(
+        base_url: &str,
+        client_id: &str,
+        client_secret: &str,
+        options: &ConnectOptions,
+    ) -> Result<Self> {
+        let http = Self::build_http(options)?;
 
-        let (access_token, expires_at) = Self::fetch_token(&http, base_url, client_id, client_secret).await?;
+        let token_cache = if options.prefer_cached_token {
+            Some(TokenCache::new(base_url, client_id)?)
+        } else {
+            None
+        };
 
-        Ok(Self {
+        let client = Self {
             base_url: base_url.to_string(),
             client_id: client_id.to_string(),
             client_secret: client_secret.to_string(),
-            token_state: RwLock::new(TokenState { access_token, expires_at }),
+            auth_mode: options.auth_mode,
+            // Already expired, so the first token() call logs in.
+            token_state: RwLock::new(TokenState {
+                access_token: String::new(),
+                expires_at: Instant::now(),
+                rejected: false,
+            }),
+            token_cache,
+            inventory_refresh_path: options.inventory_refresh_path.clone(),
+            jamf_version: OnceCell::new(),
+            retry: options.retry.clone(),
+            timeouts: options.timeouts,
             http,
-        })
+        };
+        client.token().await?;
+        Ok(client)
     }
 
-    async fn fetch_token(http: &Client, base_url: &str, client_id: &str, client_secret: &str) -> Result<(String, Instant)> {
-        let token_url = format!("{}/api/oauth/token", base_url);
+    /// Get a token, going through the cross-process cache when enabled.
+    ///
+    /// The cache file stays locked while we talk to the token endpoint, so
+    /// concurrent processes wait for one refresh instead of each doing their own.
+    async fn obtain_token(&self, rejected: Option<&str>) -> Result<(String, Instant)> {
+        redact::register(&self.client_secret);
 
-        let resp = http
-            .post(&token_url)
-            .form(&[
-                ("client_id", client_id),
-                ("client_secret", client_secret),
-                ("grant_type", "client_credentials"),
-            ])
-            .send()
-            .await
-            .context("Failed to reach Jamf Pro for authentication")?;
+        let Some(cache) = self.token_cache.as_ref() else {
+            return self.fetch_token().await;
+        };
+
+        let mut locked = cache.lock().await?;
+        if let Some(cached) = locked.read_valid(TOKEN_REFRESH_MARGIN)
+            && rejected != Some(cached.0.as_str())
+        {
+            redact::register(&cached.0);
+            debug!("Using access token from the shared token cache");
+            return Ok(cached);
+        }
+
+        let (access_token, expires_at) = self.fetch_token().await?;
+        if let Err(e) = locked.write(&access_token, expires_at) {
+            outcome!(
+                "Warning: could not update token cache: {}",
+                redact::scrub(&format!("{:#}", e))
+            );
+        }
+        Ok((access_token, expires_at))
+    }
+
+    async fn fetch_token(&self) -> Result<(String, Instant)> {
+        let (base_url, client_id, client_secret) =
+            (&self.base_url, &self.client_id, &self.client_secret);
+        let build = || match self.auth_mode {
+            AuthMode::OAuth => self
+                .http
+                .post(format!("{}/api/oauth/token", base_url))
+                .form(&[
+                    ("client_id", client_id.as_str()),
+                    ("client_secret", client_secret.as_str()),
+                    ("grant_type", "client_credentials"),
+                ]),
+            AuthMode::Basic => self
+                .http
+                .post(format!("{}/api/v1/auth/token", base_url))
+                .basic_auth(client_id, Some(client_secret))
+                .header("Accept", "application/json"),
+        };
+        debug!(
+            "Requesting access token with {}",
+            match self.auth_mode {
+                AuthMode::OAuth => "client credentials",
+                AuthMode::Basic => "basic auth",
+            }
+        );
+
+        // Issuing a token changes nothing, so a rate-limited or unavailable
+        // token endpoint is retried like any idempotent call.
+        let attempts = self.retry.max_attempts.max(1);
+        let mut attempt = 1;
+        let resp = loop {
+            let result = build()
+                .timeout(self.timeouts.request)
+                .send()
+                .await
+                .map_err(JamfError::Transport)
+                .context("Failed to reach Jamf Pro for authentication");
+            let (failure, requested) = match &result {
+                Ok(resp) if is_retryable_status(resp.status()) => {
+                    (format!("HTTP {}", resp.status()), retry_after(resp))
+                }
+                Err(e) if is_network_error(e) => (format!("{}", e.root_cause()), None),
+                _ => break result?,
+            };
+            if attempt >= attempts {
+                break result?;
+            }
+            let delay = requested
+                .unwrap_or_default()
+                .max(self.retry.jittered_delay_for(attempt));
+            status!(
+                "  Authentication request failed ({}); retrying in {:.1}s ({}/{})...",
+                failure,
+                delay.as_secs_f64(),
+                attempt,
+                attempts - 1
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
 
         if !resp.status().is_success() {
             let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Authentication failed (HTTP {}): {}", status, body);
+            let body = redact::scrub(&resp.text().await.unwrap_or_default());
+            if status == StatusCode::UNAUTHORIZED {
+                return Err(AuthRejected { status, body }.into());
+            }
+            // Jamf answers 400 `invalid_client` for a bad client ID or secret;
+            // a 429 is rate limiting, not a credential problem.
+            return Err(
+                if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    JamfError::AuthFailed {
+                        action: "Authentication failed".to_string(),
+                        status,
+                        body,
+                    }
+                } else {
+                    JamfError::from_status("Authentication failed", status, body)
+                }
+                .into(),
+            );
         }
 
-        let token_resp: OAuthTokenResponse = resp
-            .json()
+        let (access_token, lifetime) =
+            match self.auth_mode {
+                AuthMode::OAuth => {
+                    let token_resp: OAuthTokenResponse = resp.json().await.map_err(|e| {
+                        JamfError::schema("Failed to parse authentication response", e)
+                    })?;
+                    (
+                        token_resp.access_token,
+                        token_resp.expires_in.map(Duration::from_secs),
+                    )
+                }
+                AuthMode::Basic => {
+                    let token_resp: BasicTokenResponse = resp.json().await.map_err(|e| {
+                        JamfError::schema("Failed to parse authentication response", e)
+                    })?;
+                    let lifetime = token_resp
+                        .expires
+                        .as_deref()
+                        .and_then(parse_utc_timestamp)
+                        .map(|expires| {
+                            expires
+                                .duration_since(SystemTime::now())
+                                .unwrap_or_default()
+                        });
+                    (token_resp.token, lifetime)
+                }
+            };
+
+        redact::register(&access_token);
+        let expires_at = Instant::now() + lifetime.unwrap_or(DEFAULT_TOKEN_LIFETIME);
+
+        Ok((access_token, expires_at))
+    }
+
+    /// The Jamf Pro version string (e.g. `11.5.0-t1712345`).
+    ///
+    /// Fetched from `/api/v1/jamf-pro-version` on first use and cached for the
+    /// lifetime of the client, so version-dependent logic can ask freely.
+    pub async fn jamf_version(&self) -> Result<&str> {
+        let version = self
+            .jamf_version
+            .get_or_try_init(|| async {
+                let url = format!("{}/api/v1/jamf-pro-version", self.base_url);
+
+                let resp = self
+                    .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+                    .await
+                    .context("Failed to read Jamf Pro version")?;
+
+                if !resp.status().is_success() {
+                    return Err(anyhow::Error::from(
+                        JamfError::from_response("Failed to read Jamf Pro version", resp).await,
+                    ));
+                }
+
+                let payload: JamfProVersionResponse = resp.json().await.map_err(|e| {
+                    JamfError::schema("Failed to parse Jamf Pro version response", e)
+                })?;
+                Ok(payload.version)
+            })
+            .await?;
+        Ok(version.as_str())
+    }
+
+    /// Describe who the current token belongs to, for audit output.
+    ///
+    /// Uses the account details from `/api/v1/auth` when Jamf provides them
+    /// and falls back to the API client ID otherwise.
+    pub async fn identity(&self) -> Result<String> {
+        let url = format!("{}/api/v1/auth", self.base_url);
+
+        let resp = self
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
             .await
-            .context("Failed to parse authentication response")?;
+            .context("Failed to read current account details")?;
+
+        let fallback = match self.auth_mode {
+            AuthMode::OAuth => format!("API client {}", self.client_id),
+            AuthMode::Basic => format!("user {}", self.client_id),
+        };
+        if !resp.status().is_success() {
+            return Ok(fallback);
+        }
 
-        let lifetime = token_resp
-            .expires_in
-            .map(Duration::from_secs)
-            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
-        let expires_at = Instant::now() + lifetime;
+        let payload: Value = match resp.json().await {
+            Ok(payload) => payload,
+            Err(_) => return Ok(fallback),
+        };
+        let account = &payload["account"];
+        let username = account["username"].as_str().filter(|s| !s.is_empty());
+        let real_name = account["realName"].as_str().filter(|s| !s.is_empty());
 
-        Ok((token_resp.access_token, expires_at))
+        Ok(match (real_name, username) {
+            (Some(real), Some(user)) if real != user => format!("{} ({})", real, user),
+            (_, Some(user)) => user.to_string(),
+            (Some(real), None) => real.to_string(),
+            (None, None) => fallback,
+        })
+    }
+
+    /// The privileges Jamf lists for the current account or API client in
+    /// `/api/v1/auth`. None when it does not say (older versions, or a role
+    /// that cannot read its own account details).
+    pub async fn privileges(&self) -> Result<Option<Vec<String>>> {
+        let Some(payload) = self.get_optional_json("/api/v1/auth").await? else {
+            return Ok(None);
+        };
+        Ok(payload["account"]["privileges"].as_array().map(|list| {
+            list.iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect()
+        }))
+    }
+
+    /// How long the current access token remains valid.
+    pub async fn token_expires_in(&self) -> Duration {
+        self.token_state
+            .read()
+            .await
+            .expires_at
+            .saturating_duration_since(Instant::now())
+    }
+
+    /// Build the HTTP client. Proxies come from the standard `HTTPS_PROXY`,
+    /// `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` variables.
+    ///
+    /// Only connecting has a client-wide limit; each request sets its own,
+    /// since an API call and a multi-gigabyte upload need very different ones.
+    pub fn build_http(options: &ConnectOptions) -> Result<Client> {
+        let mut builder = Client::builder().connect_timeout(options.timeouts.connect);
+
+        if let Some(path) = options.ca_cert.as_deref() {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid PEM certificate in {}", path.display()))?;
+            if certs.is_empty() {
+                bail!("No certificates found in {}", path.display());
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        builder.build().context("Failed to create HTTP client")
+    }
+
+    /// Make a cheap authenticated call (the first package page) to prove the
+    /// token is accepted and may read packages. Fails with Jamf's response
+    /// body otherwise.
+    pub async fn check_auth(&self) -> Result<()> {
+        let url = format!("{}/api/v1/packages?page=0&page-size=1", self.base_url);
+
+        let resp = self
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+            .await
+            .context("Failed to read packages")?;
+
+        if !resp.status().is_success() {
+            return Err(JamfError::from_response("Failed to read packages", resp)
+                .await
+                .into());
+        }
+        Ok(())
     }
 
     /// Returns a valid bearer token, refreshing it if it is near expiry.
@@ -98,10 +548,337 @@ impl JamfClient {
             return Ok(state.access_token.clone());
         }
 
-        let (access_token, expires_at) =
-            Self::fetch_token(&self.http, &self.base_url, &self.client_id, &self.client_secret).await?;
+        let rejected = state.rejected.then(|| state.access_token.clone());
+        let (access_token, expires_at) = self.obtain_token(rejected.as_deref()).await?;
         state.access_token = access_token.clone();
         state.expires_at = expires_at;
+        state.rejected = false;
         Ok(access_token)
     }
+
+    /// Mark `token` as rejected by Jamf so the next [`token`](Self::token)
+    /// call fetches a new one. A no-op if another task already replaced it.
+    pub(crate) async fn invalidate_token(&self, token: &str) {
+        let mut state = self.token_state.write().await;
+        if state.access_token == token {
+            state.expires_at = Instant::now();
+            state.rejected = true;
+        }
+    }
+
+    /// Send an authenticated request built by `build`.
+    ///
+    /// Tokens can lapse mid-run (a long upload outlives a 20-minute token, or
+    /// the token is revoked). When Jamf answers 401 the token is renewed and
+    /// the request is sent once more; a second 401 is returned to the caller.
+    ///
+    /// Idempotent methods (GET, PUT, DELETE) are also retried with backoff on
+    /// connection errors and transient statuses, per the client's
+    /// [`RetryPolicy`].
+    pub(crate) async fn authed_request<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let idempotent = build(&self.http)
+            .build()
+            .is_ok_and(|request| request.method().is_idempotent());
+        self.send_with_retries(&build, idempotent).await
+    }
+
+    /// [`authed_request`](Self::authed_request) for a non-GET request that is
+    /// safe to repeat, so it gets the same transient-failure retries.
+    pub(crate) async fn authed_idempotent_request<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.send_with_retries(&build, true).await
+    }
+
+    /// Send with backoff retries on connection errors and transient HTTP
+    /// statuses when `retry` is set, waiting at least as long as a
+    /// `Retry-After` header asks. Other failures, including 4xx responses,
+    /// are returned to the caller at once.
+    async fn send_with_retries<F>(&self, build: &F, retry: bool) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let attempts = if retry {
+            self.retry.max_attempts.max(1)
+        } else {
+            1
+        };
+        let mut attempt = 1;
+        loop {
+            let result = self.send_authed(build).await;
+            let (failure, requested) = match &result {
+                Ok(resp) if is_retryable_status(resp.status()) => {
+                    (format!("HTTP {}", resp.status()), retry_after(resp))
+                }
+                Err(e) if is_network_error(e) => (format!("{}", e.root_cause()), None),
+                _ => return result,
+            };
+            if attempt >= attempts {
+                return result;
+            }
+
+            // A rate-limited (429) or overloaded (503) Jamf Cloud instance
+            // says how long to back off; never retry sooner than that.
+            let delay = requested
+                .unwrap_or_default()
+                .max(self.retry.jittered_delay_for(attempt));
+            status!(
+                "  Request failed ({}); retrying in {:.1}s ({}/{})...",
+                failure,
+                delay.as_secs_f64(),
+                attempt,
+                attempts - 1
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send with the bearer token; on 401 renew the token and send once more.
+    async fn send_authed<F>(&self, build: &F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let token = self.token().await?;
+        let resp = self
+            .send_logged(build(&self.http).bearer_auth(&token))
+            .await?;
+        if resp.status() != StatusCode::UNAUTHORIZED {
+            return Ok(resp);
+        }
+
+        debug!("HTTP 401 with the current token; renewing it and retrying once");
+        self.invalidate_token(&token).await;
+        let token = self.token().await?;
+        self.send_logged(build(&self.http).bearer_auth(&token))
+            .await
+    }
+
+    /// Send a request, logging method, URL, status and timing at debug level.
+    /// Unless the request sets its own time limit it gets the API call one.
+    async fn send_logged(&self, request: RequestBuilder) -> Result<Response> {
+        let mut request = request.build().context("Failed to build request")?;
+        request.timeout_mut().get_or_insert(self.timeouts.request);
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let started = Instant::now();
+        debug!("{} {}", method, url);
+        match self.http.execute(request).await {
+            Ok(resp) => {
+                debug!(
+                    "{} {} -> HTTP {} in {:.2?}",
+                    method,
+                    url,
+                    resp.status(),
+                    started.elapsed()
+                );
+                Ok(resp)
+            }
+            Err(e) => {
+                debug!(
+                    "{} {} failed after {:.2?}: {}",
+                    method,
+                    url,
+                    started.elapsed(),
+                    e
+                );
+                Err(JamfError::Transport(e).into())
+            }
+        }
+    }
+}
+
+/// Parse an RFC 3339 UTC timestamp such as `2024-05-01T12:30:00.123Z`, the
+/// form Jamf uses for token expiry. Fractional seconds are ignored.
+fn parse_utc_timestamp(value: &str) -> Option<SystemTime> {
+    let value = value.strip_suffix('Z')?;
+    let (date, time) = value.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since the Unix epoch for a proleptic Gregorian date.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::{
+        AuthMode, ConnectOptions, JamfClient, JamfError, RetryPolicy, Timeouts, parse_utc_timestamp,
+    };
+    use crate::api::mock::{self, MockState, serve};
+
+    #[test]
+    fn parses_jamf_token_expiry() {
+        let expires = parse_utc_timestamp("2024-05-01T12:30:00.123Z").unwrap();
+        assert_eq!(
+            expires.duration_since(std::time::UNIX_EPOCH).unwrap(),
+            Duration::from_secs(1_714_566_600)
+        );
+        assert!(parse_utc_timestamp("2024-05-01 12:30:00").is_none());
+    }
+
+    #[test]
+    fn upload_time_limit_grows_with_file_size() {
+        let timeouts = Timeouts {
+            connect: Duration::from_secs(15),
+            request: Duration::from_secs(60),
+            min_upload_rate: 1 << 20,
+        };
+        assert_eq!(timeouts.upload(0), Duration::from_secs(60));
+        assert_eq!(
+            timeouts.upload(20 << 30),
+            Duration::from_secs(60 + 20 * 1024)
+        );
+    }
+
+    #[tokio::test]
+    async fn basic_auth_mode_uses_the_user_token_endpoint() {
+        let state = Arc::new(MockState::default());
+        let base_url = serve(state.clone()).await;
+
+        let options = ConnectOptions {
+            auth_mode: AuthMode::Basic,
+            ..ConnectOptions::default()
+        };
+        let client = JamfClient::connect(&base_url, "admin", "password", &options)
+            .await
+            .unwrap();
+
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(state.basic_token_hits.load(Ordering::SeqCst), 1);
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn jamf_version_is_fetched_at_most_once() {
+        let state = Arc::new(MockState::default());
+
+        let client = mock::connect(state.clone()).await;
+
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(state.version_hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_renewed_and_request_retried() {
+        let state = Arc::new(MockState {
+            reject_first_token: true,
+            ..MockState::default()
+        });
+
+        let client = mock::connect(state.clone()).await;
+
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn token_near_expiry_is_renewed_before_use() {
+        // Inside the refresh margin from the start, so every use renews it.
+        let state = Arc::new(MockState {
+            token_lifetime: Some(10),
+            ..MockState::default()
+        });
+
+        let client = mock::connect(state.clone()).await;
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 1);
+
+        assert_eq!(client.token().await.unwrap(), "test-token-2");
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_with_backoff() {
+        let state = Arc::new(MockState {
+            unavailable_versions: 2,
+            ..MockState::default()
+        });
+        let options = ConnectOptions {
+            retry: RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+            },
+            ..ConnectOptions::default()
+        };
+
+        let client = mock::connect_with(state.clone(), &options).await.unwrap();
+
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert_eq!(state.version_hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_token_requests_are_retried() {
+        let state = Arc::new(MockState {
+            throttled_tokens: 1,
+            ..MockState::default()
+        });
+        let options = ConnectOptions {
+            retry: RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(1),
+            },
+            ..ConnectOptions::default()
+        };
+
+        mock::connect_with(state.clone(), &options).await.unwrap();
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 2);
+
+        // Still throttled once the retries run out: a rate limit, not a
+        // credential failure.
+        let state = Arc::new(MockState {
+            throttled_tokens: 2,
+            ..MockState::default()
+        });
+        let err = mock::connect_with(state, &options).await.err().unwrap();
+        let jamf = err.downcast_ref::<JamfError>().unwrap();
+        assert!(!matches!(jamf, JamfError::AuthFailed { .. }));
+        assert_eq!(jamf.exit_code(), 5);
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_honored_on_rate_limit() {
+        let state = Arc::new(MockState {
+            throttled_versions: 1,
+            ..MockState::default()
+        });
+        let options = ConnectOptions {
+            retry: RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(1),
+            },
+            ..ConnectOptions::default()
+        };
+
+        let client = mock::connect_with(state.clone(), &options).await.unwrap();
+
+        let started = std::time::Instant::now();
+        assert_eq!(client.jamf_version().await.unwrap(), "11.5.0-t1712345");
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(state.version_hits.load(Ordering::SeqCst), 2);
+    }
 }
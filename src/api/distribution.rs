@@ -0,0 +1,722 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::UploadOptions;
+use crate::checksum::compute_file_md5;
+use crate::events::{outcome, status};
+use crate::files::private_temp_dir;
+
+/// Where package payloads are stored and how they get there.
+///
+/// The package record itself always lives in Jamf Pro; a backend only moves
+/// the file. Methods return boxed futures so the update flow can hold the
+/// backend as a trait object chosen at runtime.
+pub trait UploadBackend: Send + Sync {
+    /// Short name for messages, e.g. "JCDS".
+    fn label(&self) -> &'static str;
+
+    /// Whether the payload lands in the Jamf Cloud Distribution Server, where
+    /// Jamf computes the package digest and reports storage capacity. Other
+    /// backends have no digest to wait for; `verify` checks the copy instead.
+    fn is_jcds(&self) -> bool;
+
+    /// Remove a payload the update replaces. JCDS is given the name about to
+    /// be uploaded, before the upload; file shares are given the record's old
+    /// file name once the new copy is verified, since a copy overwrites a file
+    /// of the same name but leaves a differently named one behind.
+    fn remove_previous<'a>(
+        &'a self,
+        client: &'a JamfClient,
+        file_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Upload `path` as the payload of package `pkg_id`.
+    fn upload<'a>(
+        &'a self,
+        client: &'a JamfClient,
+        pkg_id: &'a str,
+        path: &'a Path,
+        options: &'a UploadOptions,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Ask Jamf to pick up new payloads (a no-op where Jamf does not track
+    /// them).
+    fn refresh_inventory<'a>(&'a self, client: &'a JamfClient) -> BoxFuture<'a, Result<()>>;
+
+    /// Confirm the stored copy of `file_name` matches the local file's MD5,
+    /// for backends whose digest Jamf does not compute.
+    fn verify<'a>(&'a self, file_name: &'a str, local_md5: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Jamf Cloud Distribution Server through the v1 packages API.
+pub struct JcdsBackend;
+
+impl UploadBackend for JcdsBackend {
+    fn label(&self) -> &'static str {
+        "JCDS"
+    }
+
+    fn is_jcds(&self) -> bool {
+        true
+    }
+
+    fn remove_previous<'a>(
+        &'a self,
+        client: &'a JamfClient,
+        file_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        // The v1 upload endpoint silently no-ops when a backing file already
+        // exists in JCDS.
+        Box::pin(client.delete_jcds_file(file_name))
+    }
+
+    fn upload<'a>(
+        &'a self,
+        client: &'a JamfClient,
+        pkg_id: &'a str,
+        path: &'a Path,
+        options: &'a UploadOptions,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(client.upload_package(pkg_id, path, options))
+    }
+
+    fn refresh_inventory<'a>(&'a self, client: &'a JamfClient) -> BoxFuture<'a, Result<()>> {
+        Box::pin(client.refresh_jcds_inventory())
+    }
+
+    fn verify<'a>(&'a self, _file_name: &'a str, _local_md5: &'a str) -> BoxFuture<'a, Result<()>> {
+        // Jamf computes and reports the digest; the update flow polls for it.
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Uploads through the v1 packages API on instances whose principal
+/// distribution point is not JCDS (an on-prem file share, or a third-party
+/// cloud such as Amazon S3 or Akamai). Jamf stores the file but keeps no JCDS
+/// inventory and computes no digest, so there is nothing to refresh or poll.
+pub struct ApiUploadBackend {
+    distribution_point: DistributionPoint,
+}
+
+impl ApiUploadBackend {
+    pub fn new(distribution_point: DistributionPoint) -> Self {
+        Self { distribution_point }
+    }
+}
+
+impl UploadBackend for ApiUploadBackend {
+    fn label(&self) -> &'static str {
+        "non-JCDS distribution point"
+    }
+
+    fn is_jcds(&self) -> bool {
+        false
+    }
+
+    fn remove_previous<'a>(
+        &'a self,
+        _client: &'a JamfClient,
+        _file_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        // There is no JCDS file to delete; Jamf replaces the stored payload.
+        Box::pin(async { Ok(()) })
+    }
+
+    fn upload<'a>(
+        &'a self,
+        client: &'a JamfClient,
+        pkg_id: &'a str,
+        path: &'a Path,
+        options: &'a UploadOptions,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(client.upload_package(pkg_id, path, options))
+    }
+
+    fn refresh_inventory<'a>(&'a self, _client: &'a JamfClient) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn verify<'a>(&'a self, _file_name: &'a str, _local_md5: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            outcome!(
+                "Jamf computes no digest on a {}; the upload was not verified.",
+                self.distribution_point
+            );
+            Ok(())
+        })
+    }
+}
+
+/// A classic file share distribution point (SMB/AFP), mounted locally. The
+/// installer is copied into `dir` under its own file name.
+pub struct FileShareBackend {
+    dir: PathBuf,
+}
+
+impl FileShareBackend {
+    pub fn new(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            bail!(
+                "Distribution point {} is not a directory; is the share mounted?",
+                dir.display()
+            );
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    fn destination(&self, file_name: &str) -> Result<PathBuf> {
+        Ok(self.dir.join(plain_file_name(file_name)?))
+    }
+}
+
+impl UploadBackend for FileShareBackend {
+    fn label(&self) -> &'static str {
+        "file share distribution point"
+    }
+
+    fn is_jcds(&self) -> bool {
+        false
+    }
+
+    fn remove_previous<'a>(
+        &'a self,
+        _client: &'a JamfClient,
+        file_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = self.destination(file_name)?;
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+            }
+        })
+    }
+
+    fn upload<'a>(
+        &'a self,
+        _client: &'a JamfClient,
+        _pkg_id: &'a str,
+        path: &'a Path,
+        _options: &'a UploadOptions,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let file_name = path.file_name().context("Invalid file path")?;
+            let destination = self.dir.join(file_name);
+            // Copy under a temporary name and rename, so clients never fetch
+            // a half-written installer from the share.
+            let partial = self
+                .dir
+                .join(format!(".{}.partial", file_name.to_string_lossy()));
+            status!("Copying to {}...", destination.display());
+            tokio::fs::copy(path, &partial).await.with_context(|| {
+                format!("Failed to copy {} to {}", path.display(), partial.display())
+            })?;
+            if let Err(e) = tokio::fs::rename(&partial, &destination).await {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to move the copy into place at {}",
+                        destination.display()
+                    )
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn refresh_inventory<'a>(&'a self, _client: &'a JamfClient) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn verify<'a>(&'a self, file_name: &'a str, local_md5: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let destination = self.destination(file_name)?;
+            let stored = compute_file_md5(&destination).await?;
+            if !stored.eq_ignore_ascii_case(local_md5) {
+                bail!(
+                    "The copy at {} has MD5 {} but the local file has {}",
+                    destination.display(),
+                    stored,
+                    local_md5
+                );
+            }
+            status!("Copy verified (MD5 {}).", stored);
+            Ok(())
+        })
+    }
+}
+
+/// Where `--distribution-point` sends installers: a locally mounted share, or
+/// an `smb://server/share/dir` URL uploaded to with `smbclient`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DistributionTarget {
+    Mounted(PathBuf),
+    Smb(SmbShare),
+}
+
+impl DistributionTarget {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.strip_prefix("smb://") {
+            Some(rest) => SmbShare::parse(rest).map(Self::Smb),
+            None => Ok(Self::Mounted(PathBuf::from(value))),
+        }
+    }
+
+    /// The backend that distributes to this target.
+    pub fn backend(&self) -> Result<Box<dyn UploadBackend>> {
+        Ok(match self {
+            Self::Mounted(dir) => Box::new(FileShareBackend::new(dir)?),
+            Self::Smb(share) => Box::new(SmbClientBackend::new(share.clone())?),
+        })
+    }
+}
+
+/// An SMB share and the directory in it that holds packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmbShare {
+    server: String,
+    share: String,
+    /// Path inside the share, `/`-separated; empty for the share's root.
+    dir: String,
+}
+
+impl SmbShare {
+    /// Parse `server/share[/dir...]`, the part of the URL after `smb://`.
+    fn parse(rest: &str) -> Result<Self, String> {
+        if let Some(c) = rest.chars().find(|c| is_smb_unsafe(*c)) {
+            return Err(format!("an SMB URL cannot contain {:?}", c));
+        }
+        let mut parts = rest.trim_end_matches('/').splitn(3, '/');
+        let server = parts.next().unwrap_or_default();
+        let share = parts.next().unwrap_or_default();
+        if server.is_empty() || share.is_empty() {
+            return Err(format!(
+                "'smb://{}' must name a server and a share, e.g. smb://server/CasperShare/Packages",
+                rest
+            ));
+        }
+        Ok(Self {
+            server: server.to_string(),
+            share: share.to_string(),
+            dir: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// `//server/share`, as `smbclient` takes it.
+    fn service(&self) -> String {
+        format!("//{}/{}", self.server, self.share)
+    }
+}
+
+impl fmt::Display for SmbShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "smb://{}/{}", self.server, self.share)?;
+        if !self.dir.is_empty() {
+            write!(f, "/{}", self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// An SMB file share reached with the Samba `smbclient` tool, without
+/// mounting it. Logs in as `JAMF_SMB_USERNAME` with `JAMF_SMB_PASSWORD`
+/// (passed through the environment, never the command line), or as a guest
+/// when no user name is set.
+pub struct SmbClientBackend {
+    share: SmbShare,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl SmbClientBackend {
+    pub fn new(share: SmbShare) -> Result<Self> {
+        which_smbclient()?;
+        Ok(Self {
+            share,
+            username: std::env::var("JAMF_SMB_USERNAME")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            password: std::env::var("JAMF_SMB_PASSWORD").ok(),
+        })
+    }
+
+    /// Run `commands` against the share, failing with smbclient's output
+    /// when any of them fails.
+    async fn run(&self, commands: &str) -> Result<()> {
+        let mut command = tokio::process::Command::new("smbclient");
+        command
+            .arg(self.share.service())
+            .arg("-E")
+            .arg("-c")
+            .arg(commands);
+        match &self.username {
+            Some(username) => {
+                command.env("USER", username);
+                if let Some(password) = &self.password {
+                    command.env("PASSWD", password);
+                }
+            }
+            None => {
+                command.arg("-N");
+            }
+        }
+        let output = command.output().await.context("Failed to run smbclient")?;
+        // smbclient reports failed commands on stderr, sometimes with exit 0.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || stderr.contains("NT_STATUS_") {
+            bail!(
+                "smbclient failed on {} ({}): {}",
+                self.share,
+                output.status,
+                stderr.trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn which_smbclient() -> Result<()> {
+    match std::process::Command::new("smbclient")
+        .arg("--version")
+        .output()
+    {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+            "smbclient was not found; install Samba's client tools, or mount the share and \
+             pass its directory to --distribution-point"
+        ),
+        Err(e) => Err(e).context("Failed to run smbclient"),
+    }
+}
+
+/// `file_name` if it is a bare name. Names come from the package record, so
+/// one with a directory part (`../x.pkg`, `/etc/x`, `..\x`) is refused
+/// rather than allowed to reach outside the share directory.
+fn plain_file_name(file_name: &str) -> Result<&str> {
+    if matches!(file_name, "" | "." | "..") || file_name.contains(['/', '\\']) {
+        bail!(
+            "'{}' is not a plain file name; refusing to touch it on the share",
+            file_name
+        );
+    }
+    Ok(file_name)
+}
+
+/// Characters that would let a name break out of a quoted `smbclient -c`
+/// argument: `"` ends it, `;` and line breaks start another command and `!`
+/// runs a shell command.
+fn is_smb_unsafe(c: char) -> bool {
+    matches!(c, '"' | ';' | '!') || c.is_control()
+}
+
+/// `value` quoted for an `smbclient -c` script, or an error naming `what`
+/// when it cannot be quoted safely.
+fn smb_quote(value: &str, what: &str) -> Result<String> {
+    if let Some(c) = value.chars().find(|c| is_smb_unsafe(*c)) {
+        bail!(
+            "Cannot use {} '{}' over SMB: it contains {:?}",
+            what,
+            value.escape_debug(),
+            c
+        );
+    }
+    Ok(format!("\"{}\"", value))
+}
+
+/// The `smbclient -c` script that uploads `local` as `file_name` in `dir`:
+/// put it under a temporary name, then rename it over the old payload.
+fn smb_put_script(dir: &str, local: &Path, file_name: &str) -> Result<String> {
+    let local = smb_quote(&local.to_string_lossy(), "the path")?;
+    let partial = smb_quote(&format!(".{}.partial", file_name), "the file name")?;
+    let target = smb_quote(file_name, "the file name")?;
+    Ok(format!(
+        "{}put {} {}; rename {} {} -f",
+        smb_cd(dir)?,
+        local,
+        partial,
+        partial,
+        target
+    ))
+}
+
+fn smb_cd(dir: &str) -> Result<String> {
+    if dir.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!("cd {}; ", smb_quote(dir, "the share directory")?))
+    }
+}
+
+impl UploadBackend for SmbClientBackend {
+    fn label(&self) -> &'static str {
+        "SMB file share"
+    }
+
+    fn is_jcds(&self) -> bool {
+        false
+    }
+
+    fn remove_previous<'a>(
+        &'a self,
+        _client: &'a JamfClient,
+        file_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let script = format!(
+                "{}del {}",
+                smb_cd(&self.share.dir)?,
+                smb_quote(plain_file_name(file_name)?, "the file name")?
+            );
+            self.run(&script).await
+        })
+    }
+
+    fn upload<'a>(
+        &'a self,
+        _client: &'a JamfClient,
+        _pkg_id: &'a str,
+        path: &'a Path,
+        _options: &'a UploadOptions,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let file_name = path
+                .file_name()
+                .context("Invalid file path")?
+                .to_string_lossy();
+            let script = smb_put_script(&self.share.dir, path, &file_name)?;
+            status!("Uploading to {}/{}...", self.share, file_name);
+            self.run(&script).await
+        })
+    }
+
+    fn refresh_inventory<'a>(&'a self, _client: &'a JamfClient) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn verify<'a>(&'a self, file_name: &'a str, local_md5: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // Fetch the stored copy back into a private directory and hash it.
+            let dir = private_temp_dir("jamf-package-updater-verify")?;
+            let copy = dir.join("copy");
+            let fetched = async {
+                let script = format!(
+                    "{}get {} {}",
+                    smb_cd(&self.share.dir)?,
+                    smb_quote(plain_file_name(file_name)?, "the file name")?,
+                    smb_quote(&copy.to_string_lossy(), "the path")?
+                );
+                self.run(&script).await
+            }
+            .await;
+            let stored = match fetched {
+                Ok(()) => compute_file_md5(&copy).await,
+                Err(e) => Err(e),
+            };
+            let _ = tokio::fs::remove_dir_all(&dir).await;
+            let stored = stored.context("Failed to read back the uploaded copy")?;
+            if !stored.eq_ignore_ascii_case(local_md5) {
+                bail!(
+                    "The copy at {}/{} has MD5 {} but the local file has {}",
+                    self.share,
+                    file_name,
+                    stored,
+                    local_md5
+                );
+            }
+            status!("Copy verified (MD5 {}).", stored);
+            Ok(())
+        })
+    }
+}
+
+/// The kind of principal distribution point an instance uses, from its cloud
+/// distribution point settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DistributionPoint {
+    /// Jamf Cloud Distribution Service: Jamf computes digests after upload.
+    Jcds,
+    /// Another cloud provider (`cdnType`, e.g. `AMAZON_S3`).
+    OtherCloud(String),
+    /// No cloud distribution point: on-prem file shares only.
+    FileShare,
+    /// The settings could not be read; assumed to be JCDS.
+    Unknown,
+}
+
+impl DistributionPoint {
+    /// Classify `GET /api/v1/cloud-distribution-point`.
+    fn from_settings(settings: &Value) -> Self {
+        match settings.get("cdnType").and_then(Value::as_str) {
+            Some("JAMF_CLOUD") => Self::Jcds,
+            Some("NONE") => Self::FileShare,
+            Some(other) => Self::OtherCloud(other.to_string()),
+            None => Self::Unknown,
+        }
+    }
+
+    /// Whether uploads land in JCDS, or may (when the settings are unknown).
+    pub fn is_jcds(&self) -> bool {
+        matches!(self, Self::Jcds | Self::Unknown)
+    }
+}
+
+impl fmt::Display for DistributionPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jcds => f.write_str("Jamf Cloud Distribution Service"),
+            Self::OtherCloud(cdn) => write!(f, "cloud distribution point ({})", cdn),
+            Self::FileShare => f.write_str("file share distribution point"),
+            Self::Unknown => f.write_str("distribution point of unknown type"),
+        }
+    }
+}
+
+impl JamfClient {
+    /// Find out where this instance stores package payloads. Reads the cloud
+    /// distribution point settings; an instance that hides them (missing
+    /// endpoint or API permission) is reported as [`DistributionPoint::Unknown`].
+    pub async fn detect_distribution_point(&self) -> Result<DistributionPoint> {
+        Ok(
+            match self
+                .get_optional_json("/api/v1/cloud-distribution-point")
+                .await?
+            {
+                Some(settings) => DistributionPoint::from_settings(&settings),
+                None => DistributionPoint::Unknown,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_share_removes_the_replaced_payload() {
+        use std::sync::Arc;
+
+        use crate::api::mock::{self, MockState};
+
+        let dir = std::env::temp_dir().join(format!("jpu-share-remove-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("App-1.0.pkg"), b"old").unwrap();
+        std::fs::write(dir.join("App-2.0.pkg"), b"new").unwrap();
+        let client = mock::connect(Arc::new(MockState::default())).await;
+
+        let backend = FileShareBackend::new(&dir).unwrap();
+        backend
+            .remove_previous(&client, "App-1.0.pkg")
+            .await
+            .unwrap();
+        assert!(!dir.join("App-1.0.pkg").exists());
+        assert!(dir.join("App-2.0.pkg").exists());
+        // Already gone is not an error.
+        backend
+            .remove_previous(&client, "App-1.0.pkg")
+            .await
+            .unwrap();
+
+        // A record's fileName must not reach outside the share.
+        let outside = dir.with_extension("outside.pkg");
+        std::fs::write(&outside, b"keep").unwrap();
+        let escapes = [
+            format!("../{}", outside.file_name().unwrap().to_string_lossy()),
+            outside.to_string_lossy().to_string(),
+        ];
+        for name in &escapes {
+            assert!(
+                backend.remove_previous(&client, name).await.is_err(),
+                "{}",
+                name
+            );
+            assert!(backend.verify(name, "00").await.is_err(), "{}", name);
+        }
+        assert!(outside.exists());
+        std::fs::remove_file(&outside).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_share_copy_is_verified_by_md5() {
+        let dir = std::env::temp_dir().join(format!("jpu-share-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("App.pkg"), b"payload").unwrap();
+
+        let backend = FileShareBackend::new(&dir).unwrap();
+        let md5 = compute_file_md5(&dir.join("App.pkg")).await.unwrap();
+        assert!(backend.verify("App.pkg", &md5).await.is_ok());
+        assert!(
+            backend
+                .verify("App.pkg", "00000000000000000000000000000000")
+                .await
+                .is_err()
+        );
+        assert!(FileShareBackend::new(&dir.join("missing")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn smb_urls_name_server_share_and_directory() {
+        let Ok(DistributionTarget::Smb(share)) =
+            DistributionTarget::parse("smb://dp.example.com/CasperShare/Packages/")
+        else {
+            panic!("expected an SMB target");
+        };
+        assert_eq!(share.service(), "//dp.example.com/CasperShare");
+        assert_eq!(share.dir, "Packages");
+        assert_eq!(
+            smb_put_script(&share.dir, Path::new("/tmp/App.pkg"), "App.pkg").unwrap(),
+            "cd \"Packages\"; put \"/tmp/App.pkg\" \".App.pkg.partial\"; \
+             rename \".App.pkg.partial\" \"App.pkg\" -f"
+        );
+        // A name must not be able to add commands (`;`, newlines) or shell
+        // escapes (`!`) to the script.
+        for name in ["x.pkg; del *", "x.pkg\n!sh", "x\".pkg"] {
+            assert!(smb_put_script("", Path::new("/tmp/x.pkg"), name).is_err());
+        }
+        assert!(DistributionTarget::parse("smb://dp/share/a;b").is_err());
+        assert!(plain_file_name("App 1.0.pkg").is_ok());
+        for name in ["", "..", "../App.pkg", "/etc/App.pkg", "..\\App.pkg"] {
+            assert!(plain_file_name(name).is_err(), "{}", name);
+        }
+
+        assert_eq!(
+            DistributionTarget::parse("/Volumes/CasperShare/Packages"),
+            Ok(DistributionTarget::Mounted(PathBuf::from(
+                "/Volumes/CasperShare/Packages"
+            )))
+        );
+        assert!(DistributionTarget::parse("smb://dp.example.com").is_err());
+    }
+
+    #[test]
+    fn distribution_point_is_classified_by_cdn_type() {
+        let classify = |v| DistributionPoint::from_settings(&v);
+        assert_eq!(
+            classify(serde_json::json!({ "cdnType": "JAMF_CLOUD" })),
+            DistributionPoint::Jcds
+        );
+        assert_eq!(
+            classify(serde_json::json!({ "cdnType": "NONE" })),
+            DistributionPoint::FileShare
+        );
+        assert_eq!(
+            classify(serde_json::json!({ "cdnType": "AMAZON_S3" })),
+            DistributionPoint::OtherCloud("AMAZON_S3".to_string())
+        );
+        assert_eq!(classify(serde_json::json!({})), DistributionPoint::Unknown);
+        assert!(DistributionPoint::Unknown.is_jcds());
+        assert!(!DistributionPoint::FileShare.is_jcds());
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode};
+
+use crate::api::client::retry_after;
+
+/// A failed Jamf Pro API call, by what went wrong rather than where.
+///
+/// API methods still return `anyhow::Result`; this sits in the error chain
+/// so callers (and the exit code, see [`JamfError::exit_code`]) can tell a
+/// broken login from a missing record, a rate limit or a Jamf outage.
+#[derive(Debug, thiserror::Error)]
+pub enum JamfError {
+    /// The credentials were refused, or the token lacks the privilege
+    /// (HTTP 401/403).
+    #[error("{action} (HTTP {status}): {body}")]
+    AuthFailed {
+        action: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// HTTP 404.
+    #[error("{action} (HTTP {status}): {body}")]
+    NotFound {
+        action: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// HTTP 429, with how long Jamf asked to wait.
+    #[error("{action} (HTTP {status}): {body}")]
+    RateLimited {
+        action: String,
+        status: StatusCode,
+        retry_after: Option<Duration>,
+        body: String,
+    },
+    /// HTTP 409: the change clashes with the record's current state.
+    #[error("{action} (HTTP {status}): {body}")]
+    Conflict {
+        action: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// Any other 4xx: Jamf rejected the request as sent.
+    #[error("{action} (HTTP {status}): {body}")]
+    Rejected {
+        action: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// HTTP 5xx: Jamf (or a gateway in front of it) failed.
+    #[error("{action} (HTTP {status}): {body}")]
+    ServerError {
+        action: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// No response: connection, DNS, TLS or timeout.
+    #[error(transparent)]
+    Transport(reqwest::Error),
+    /// A response that did not have the shape this tool expects, e.g. after
+    /// a Jamf API change.
+    #[error("{action}: {detail}")]
+    UnexpectedSchema { action: String, detail: String },
+}
+
+impl JamfError {
+    /// Classify an unsuccessful response status. `action` says what was
+    /// attempted, e.g. "Failed to delete package 12".
+    pub fn from_status(action: impl Into<String>, status: StatusCode, body: String) -> Self {
+        let action = action.into();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::AuthFailed {
+                action,
+                status,
+                body,
+            },
+            StatusCode::NOT_FOUND => Self::NotFound {
+                action,
+                status,
+                body,
+            },
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited {
+                action,
+                status,
+                retry_after: None,
+                body,
+            },
+            StatusCode::CONFLICT => Self::Conflict {
+                action,
+                status,
+                body,
+            },
+            s if s.is_server_error() => Self::ServerError {
+                action,
+                status,
+                body,
+            },
+            _ => Self::Rejected {
+                action,
+                status,
+                body,
+            },
+        }
+    }
+
+    /// Classify an unsuccessful response, reading its body and `Retry-After`.
+    pub async fn from_response(action: impl Into<String>, resp: Response) -> Self {
+        let status = resp.status();
+        let requested = retry_after(&resp);
+        let body = resp.text().await.unwrap_or_default();
+        match Self::from_status(action, status, body) {
+            Self::RateLimited {
+                action,
+                status,
+                body,
+                ..
+            } => Self::RateLimited {
+                action,
+                status,
+                retry_after: requested,
+                body,
+            },
+            other => other,
+        }
+    }
+
+    /// A response body that failed to parse.
+    pub fn schema(action: impl Into<String>, detail: impl fmt::Display) -> Self {
+        Self::UnexpectedSchema {
+            action: action.into(),
+            detail: detail.to_string(),
+        }
+    }
+
+    /// The process exit code for a run that failed with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::AuthFailed { .. } => 3,
+            Self::NotFound { .. } => 4,
+            Self::RateLimited { .. } => 5,
+            Self::Conflict { .. } => 6,
+            Self::ServerError { .. } => 7,
+            Self::Transport(_) => 8,
+            Self::UnexpectedSchema { .. } => 9,
+            Self::Rejected { .. } => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_kinds_and_exit_codes() {
+        let code = |status| JamfError::from_status("Failed", status, String::new()).exit_code();
+        assert_eq!(code(StatusCode::UNAUTHORIZED), 3);
+        assert_eq!(code(StatusCode::FORBIDDEN), 3);
+        assert_eq!(code(StatusCode::NOT_FOUND), 4);
+        assert_eq!(code(StatusCode::TOO_MANY_REQUESTS), 5);
+        assert_eq!(code(StatusCode::CONFLICT), 6);
+        assert_eq!(code(StatusCode::BAD_GATEWAY), 7);
+        assert_eq!(code(StatusCode::BAD_REQUEST), 1);
+
+        let err = JamfError::from_status(
+            "Failed to delete package 12",
+            StatusCode::NOT_FOUND,
+            "{}".to_string(),
+        );
+        assert_eq!(
+            err.to_string(),
+            "Failed to delete package 12 (HTTP 404 Not Found): {}"
+        );
+    }
+}
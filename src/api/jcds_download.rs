@@ -0,0 +1,179 @@
+//! Downloading package files back out of JCDS storage.
+//!
+//! Jamf hands out a short-lived pre-signed link per file
+//! (`GET /api/v1/jcds/files/{fileName}`); the file itself is then fetched
+//! from storage without the Jamf token.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use futures_util::TryStreamExt;
+use indicatif::HumanDuration;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+
+use crate::api::client::JamfClient;
+use crate::api::error::JamfError;
+use crate::events::ProgressCallback;
+use crate::models::jcds::JcdsDownloadLink;
+
+/// Characters left as-is in a path segment: RFC 3986 unreserved.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+impl JamfClient {
+    /// Ask Jamf for a download link to a file in JCDS storage.
+    pub async fn jcds_download_link(&self, file_name: &str) -> Result<String> {
+        let url = format!(
+            "{}/api/v1/jcds/files/{}",
+            self.base_url,
+            utf8_percent_encode(file_name, PATH_SEGMENT)
+        );
+        let resp = self
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+            .await
+            .context("Failed to request JCDS download link")?;
+
+        if !resp.status().is_success() {
+            return Err(JamfError::from_response(
+                format!(
+                    "Failed to get a download link for JCDS file '{}'",
+                    file_name
+                ),
+                resp,
+            )
+            .await
+            .into());
+        }
+
+        let link: JcdsDownloadLink = resp
+            .json()
+            .await
+            .map_err(|e| JamfError::schema("Failed to parse JCDS download link", e))?;
+        Ok(link.uri)
+    }
+
+    /// Download a JCDS file to `dest`, returning the number of bytes written.
+    ///
+    /// The file is streamed to `<dest>.part` and renamed into place only once
+    /// complete, so an interrupted download never leaves a truncated file
+    /// under the final name. There is no limit on the whole transfer, only on
+    /// waiting for the next bytes (the client's API call time limit).
+    pub async fn download_jcds_file(
+        &self,
+        file_name: &str,
+        dest: &Path,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<u64> {
+        let uri = self.jcds_download_link(file_name).await?;
+        debug!("Downloading JCDS file {}", file_name);
+
+        let stall = self.timeouts.request;
+        let resp = match tokio::time::timeout(stall, self.http.get(&uri).send()).await {
+            Ok(resp) => {
+                resp.with_context(|| format!("Failed to download JCDS file '{}'", file_name))?
+            }
+            Err(_) => bail!(
+                "JCDS storage did not answer the download of '{}' within {}",
+                file_name,
+                HumanDuration(stall)
+            ),
+        };
+        if !resp.status().is_success() {
+            return Err(JamfError::from_status(
+                format!("Failed to download JCDS file '{}'", file_name),
+                resp.status(),
+                String::new(),
+            )
+            .into());
+        }
+
+        let partial = partial_path(dest);
+        let result = async {
+            let mut file = tokio::fs::File::create(&partial)
+                .await
+                .with_context(|| format!("Failed to create {}", partial.display()))?;
+            let total = resp.content_length().unwrap_or(0);
+            let mut stream = resp.bytes_stream();
+            let mut written = 0_u64;
+            while let Some(chunk) = tokio::time::timeout(stall, stream.try_next())
+                .await
+                .map_err(|_| {
+                    anyhow!(
+                        "Download of JCDS file '{}' stalled for {}",
+                        file_name,
+                        HumanDuration(stall)
+                    )
+                })?
+                .with_context(|| format!("Download of JCDS file '{}' failed", file_name))?
+            {
+                file.write_all(&chunk)
+                    .await
+                    .with_context(|| format!("Failed writing {}", partial.display()))?;
+                written += chunk.len() as u64;
+                if let Some(progress) = progress {
+                    progress.report(written, total);
+                }
+            }
+            file.flush()
+                .await
+                .with_context(|| format!("Failed writing {}", partial.display()))?;
+            Ok::<_, anyhow::Error>(written)
+        }
+        .await;
+
+        match result {
+            Ok(written) => {
+                tokio::fs::rename(&partial, dest).await.with_context(|| {
+                    format!("Failed to move download into place at {}", dest.display())
+                })?;
+                Ok(written)
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// `<dest>.part`, next to the destination so the final rename stays on one
+/// filesystem.
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::api::mock::{self, MockState};
+
+    #[tokio::test]
+    async fn download_writes_file_through_link_and_leaves_no_partial() {
+        let state = Arc::new(MockState {
+            jcds_file: Some("installer payload"),
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+
+        let dir = std::env::temp_dir().join(format!("jpu-download-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("App 1.0.pkg");
+        let bytes = client
+            .download_jcds_file("App 1.0.pkg", &dest, None)
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, 17);
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "installer payload");
+        assert!(!dir.join("App 1.0.pkg.part").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
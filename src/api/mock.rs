@@ -0,0 +1,308 @@
+//! Minimal in-process Jamf Pro stand-in for API client tests.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use percent_encoding::percent_decode_str;
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::api::client::{ConnectOptions, JamfClient};
+
+#[derive(Default)]
+pub struct MockState {
+    pub token_hits: AtomicUsize,
+    /// Tokens issued to `POST /api/v1/auth/token` with basic auth.
+    pub basic_token_hits: AtomicUsize,
+    pub version_hits: AtomicUsize,
+    /// `expires_in` of issued tokens, in seconds (default 1200).
+    pub token_lifetime: Option<u64>,
+    /// Answer 401 to requests carrying the first token issued.
+    pub reject_first_token: bool,
+    /// Answer 503 to this many version requests before succeeding.
+    pub unavailable_versions: usize,
+    /// Answer 429 with `Retry-After: 1` to this many version requests
+    /// before succeeding.
+    pub throttled_versions: usize,
+    /// Answer 429 to this many OAuth token requests before issuing tokens.
+    pub throttled_tokens: usize,
+    /// Number of package records served by `GET /api/v1/packages`.
+    pub packages: usize,
+    /// Package records served by the name search and by
+    /// `GET /api/v1/packages/{id}`, replacing the generated ones.
+    pub records: Vec<Value>,
+    pub record_hits: AtomicUsize,
+    /// Served by `GET /api/v1/jcds/files`; 404 when None.
+    pub jcds_files: Option<Value>,
+    /// Category names listed by `GET /api/v1/categories`, with IDs from 1.
+    pub categories: Vec<&'static str>,
+    /// Categories created through `POST /api/v1/categories`.
+    pub created_categories: AtomicUsize,
+    pub package_page_hits: AtomicUsize,
+    /// Privileges listed for the account by `GET /api/v1/auth`; an empty
+    /// document when None.
+    pub privileges: Option<Vec<&'static str>>,
+    /// Answer 403 to `GET /JSSResource/policies`.
+    pub forbid_policies: bool,
+    /// Uploads accepted by `POST /api/v1/packages/{id}/upload`.
+    pub upload_hits: AtomicUsize,
+    /// Contents of every JCDS file, served through a download link.
+    pub jcds_file: Option<&'static str>,
+    /// Raw body served by `GET /api/v1/cloud-distribution-point`.
+    pub cloud_distribution_point: Option<&'static str>,
+}
+
+/// Serve `state` and connect a client to it with default options.
+pub async fn connect(state: Arc<MockState>) -> JamfClient {
+    connect_with(state, &ConnectOptions::default())
+        .await
+        .unwrap()
+}
+
+/// Serve `state` and connect a client to it with `options`.
+pub async fn connect_with(state: Arc<MockState>, options: &ConnectOptions) -> Result<JamfClient> {
+    let base_url = serve(state).await;
+    JamfClient::connect(&base_url, "id", "client-secret", options).await
+}
+
+/// Minimal HTTP server answering the token, version, package list and record,
+/// and JCDS endpoints, counting how often each is requested.
+pub async fn serve(state: Arc<MockState>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut socket).await;
+                let mut headers = String::new();
+                let (status, body) = if request.starts_with("POST /api/oauth/token") {
+                    let n = state.token_hits.fetch_add(1, Ordering::SeqCst) + 1;
+                    if n <= state.throttled_tokens {
+                        ("429 Too Many Requests", "{}".to_string())
+                    } else {
+                        (
+                            "200 OK",
+                            format!(
+                                r#"{{"access_token":"test-token-{}","expires_in":{}}}"#,
+                                n - state.throttled_tokens,
+                                state.token_lifetime.unwrap_or(1200)
+                            ),
+                        )
+                    }
+                } else if request.starts_with("POST /api/v1/auth/token") {
+                    if request.contains("authorization: Basic ")
+                        || request.contains("Authorization: Basic ")
+                    {
+                        let n = state.basic_token_hits.fetch_add(1, Ordering::SeqCst) + 1;
+                        (
+                            "200 OK",
+                            format!(
+                                r#"{{"token":"basic-token-{}","expires":"2999-01-01T00:00:00.000Z"}}"#,
+                                n
+                            ),
+                        )
+                    } else {
+                        ("401 Unauthorized", "{}".to_string())
+                    }
+                } else if state.reject_first_token && request.contains("Bearer test-token-1\r\n") {
+                    ("401 Unauthorized", "{}".to_string())
+                } else if request.starts_with("GET /api/v1/jamf-pro-version") {
+                    let n = state.version_hits.fetch_add(1, Ordering::SeqCst);
+                    if n < state.unavailable_versions {
+                        ("503 Service Unavailable", "{}".to_string())
+                    } else if n < state.unavailable_versions + state.throttled_versions {
+                        headers.push_str("Retry-After: 1\r\n");
+                        ("429 Too Many Requests", "{}".to_string())
+                    } else {
+                        ("200 OK", r#"{"version":"11.5.0-t1712345"}"#.to_string())
+                    }
+                } else if let Some(query) = request.strip_prefix("GET /api/v1/packages?") {
+                    state.package_page_hits.fetch_add(1, Ordering::SeqCst);
+                    ("200 OK", package_page(&state, query))
+                } else if request.starts_with("POST /api/v1/packages/")
+                    && request.contains("/upload HTTP/")
+                {
+                    state.upload_hits.fetch_add(1, Ordering::SeqCst);
+                    ("201 Created", "{}".to_string())
+                } else if let Some(rest) = request.strip_prefix("GET /api/v1/packages/")
+                    && let Some((id, _)) = rest.split_once(' ')
+                    && !id.contains(['/', '?'])
+                {
+                    state.record_hits.fetch_add(1, Ordering::SeqCst);
+                    match state.records.iter().find(|r| r["id"] == id) {
+                        Some(record) => ("200 OK", record.to_string()),
+                        None => ("404 Not Found", "{}".to_string()),
+                    }
+                } else if request.starts_with("GET /api/v1/auth ") {
+                    let body = match &state.privileges {
+                        Some(privileges) => json!({
+                            "account": { "username": "ci", "privileges": privileges }
+                        }),
+                        None => json!({}),
+                    };
+                    ("200 OK", body.to_string())
+                } else if state.forbid_policies && request.starts_with("GET /JSSResource/policies ")
+                {
+                    ("403 Forbidden", "{}".to_string())
+                } else if request.starts_with("GET /api/v1/categories?") {
+                    let results: Vec<_> = state
+                        .categories
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| json!({ "id": (i + 1).to_string(), "name": name }))
+                        .collect();
+                    (
+                        "200 OK",
+                        json!({ "totalCount": results.len(), "results": results }).to_string(),
+                    )
+                } else if request.starts_with("POST /api/v1/categories ") {
+                    let n = state.created_categories.fetch_add(1, Ordering::SeqCst) + 1;
+                    let id = state.categories.len() + n;
+                    ("201 Created", json!({ "id": id.to_string() }).to_string())
+                } else if request.starts_with("GET /api/v1/jcds/files ") {
+                    match &state.jcds_files {
+                        Some(files) => ("200 OK", files.to_string()),
+                        None => ("404 Not Found", "{}".to_string()),
+                    }
+                } else if let Some(body) = state.cloud_distribution_point
+                    && request.starts_with("GET /api/v1/cloud-distribution-point ")
+                {
+                    ("200 OK", body.to_string())
+                } else if let Some(contents) = state.jcds_file
+                    && let Some(rest) = request.strip_prefix("GET ")
+                {
+                    let path = rest.split_whitespace().next().unwrap_or_default();
+                    if let Some(name) = path.strip_prefix("/api/v1/jcds/files/") {
+                        (
+                            "200 OK",
+                            json!({ "uri": format!("http://{}/storage/{}", addr, name) })
+                                .to_string(),
+                        )
+                    } else if path.starts_with("/storage/") {
+                        ("200 OK", contents.to_string())
+                    } else {
+                        ("200 OK", "{}".to_string())
+                    }
+                } else {
+                    ("200 OK", "{}".to_string())
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    format!("http://{}", addr)
+}
+
+/// Read one request: the headers, then as much body as `Content-Length`
+/// announces, or up to the last chunk of a chunked body.
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0_u8; 4096];
+    loop {
+        let n = socket.read(&mut chunk).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&buf[..header_end]).to_ascii_lowercase();
+        let body = buf.len() - header_end - 4;
+        let length = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|len| len.trim().parse::<usize>().ok());
+        let complete = match length {
+            Some(length) => body >= length,
+            None if headers.contains("transfer-encoding: chunked") => buf.ends_with(b"0\r\n\r\n"),
+            None => true,
+        };
+        if complete {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// One page of package records, honoring the `page`, `page-size` and
+/// `packageName==` `filter` query parameters. Without `state.records`,
+/// `state.packages` generated records are listed.
+fn package_page(state: &MockState, query: &str) -> String {
+    let query = query.split_whitespace().next().unwrap_or_default();
+    let param = |name: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    };
+    let number = |name: &str| param(name).and_then(|v| v.parse().ok()).unwrap_or(0);
+    let (page, size) = (number("page"), number("page-size").max(1));
+    let filter = param("filter").map(|f| percent_decode_str(f).decode_utf8_lossy().to_string());
+
+    let records: Vec<Value> = if state.records.is_empty() {
+        (0..state.packages).map(generated_record).collect()
+    } else {
+        state
+            .records
+            .iter()
+            .filter(|r| {
+                filter.as_deref().is_none_or(|f| {
+                    r["packageName"]
+                        .as_str()
+                        .is_some_and(|name| f == format!("packageName==\"{}\"", name))
+                })
+            })
+            .cloned()
+            .collect()
+    };
+    let results: Vec<_> = records
+        .iter()
+        .skip(page * size)
+        .take(size)
+        .cloned()
+        .collect();
+    json!({ "totalCount": records.len(), "results": results }).to_string()
+}
+
+/// The `i`th generated package record.
+fn generated_record(i: usize) -> Value {
+    package_record(
+        &(i + 1).to_string(),
+        &format!("Package {}", i + 1),
+        &format!("Package-{}.pkg", i + 1),
+    )
+}
+
+/// A package record with default settings and no digest fields.
+pub fn package_record(id: &str, name: &str, file_name: &str) -> Value {
+    json!({
+                "id": id,
+                "packageName": name,
+                "fileName": file_name,
+                "categoryId": "-1",
+                "priority": 10,
+                "fillUserTemplate": false,
+                "fillExistingUsers": false,
+                "rebootRequired": false,
+                "osInstall": false,
+                "suppressUpdates": false,
+                "suppressFromDock": false,
+                "suppressEula": false,
+        "suppressRegistration": false,
+    })
+}
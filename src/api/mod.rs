@@ -1,3 +1,13 @@
+pub mod categories;
 pub mod client;
+pub mod distribution;
+pub mod error;
+pub mod jcds_download;
+pub mod jcds_upload;
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod packages;
+pub mod patch;
 pub mod policies;
+pub mod prestages;
+pub mod token_cache;
@@ -1,14 +1,73 @@
 use anyhow::{Context, Result, bail};
-use reqwest::multipart;
+use futures_util::TryStreamExt;
+use indicatif::{HumanBytes, HumanDuration};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::{StatusCode, multipart};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::fs::File;
 use tokio_util::codec::{BytesCodec, FramedRead};
+use tracing::debug;
 
-use crate::api::client::JamfClient;
-use crate::models::package::{HrefResponse, Package, PackageCreateRequest, PackageSearchResponse};
+use crate::api::client::{JamfClient, retry_after};
+use crate::api::error::JamfError;
+use crate::checksum::HashType;
+use crate::events::{ProgressCallback, status};
+use crate::models::jcds::JcdsFile;
+use crate::models::package::{
+    HrefResponse, Package, PackageCreateRequest, PackageHistoryResponse, PackageSearchResponse,
+};
+use crate::redact;
 
-#[derive(Debug, Clone, Default)]
+const PACKAGE_PAGE_SIZE: usize = 100;
+/// Inventory-refresh endpoints in the order they are tried: the current
+/// default, then the location used by other Jamf Pro releases.
+const INVENTORY_REFRESH_PATHS: &[&str] = &[
+    "/api/v1/jcds/refresh-inventory",
+    "/api/v1/cloud-distribution-point/refresh-inventory",
+];
+const FILE_OPEN_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Wait before resending a failed upload, unless Jamf asks for longer.
+const UPLOAD_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// How much of a failed upload's response body `-v` logs.
+const UPLOAD_BODY_LOG_CHARS: usize = 500;
+const SEARCH_CONSISTENCY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Tuning knobs for [`JamfClient::upload_package`].
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Attempts at opening (and stat-ing) the local file before giving up.
+    pub file_open_attempts: u32,
+    /// Multipart form field carrying the file (`file` for Jamf's endpoint).
+    pub field_name: String,
+    /// Sent as the `Idempotency-Key` header on every attempt so a server or
+    /// gateway that supports it can dedupe a retried upload.
+    pub idempotency_key: Option<String>,
+    /// Told how many bytes have been sent as the file uploads.
+    pub progress: Option<ProgressCallback>,
+    /// Send the file straight to JCDS storage in resumable parts of this many
+    /// bytes instead of one multipart form request.
+    pub chunk_size: Option<u64>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            file_open_attempts: 3,
+            field_name: "file".to_string(),
+            idempotency_key: None,
+            progress: None,
+            chunk_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PackageDigestSnapshot {
     pub md5_hash: Option<String>,
     pub hash_type: Option<String>,
@@ -28,6 +87,44 @@ impl PackageDigestSnapshot {
         self.md5_hash.is_some() || self.hash_value.is_some() || self.file_size.is_some()
     }
 
+    /// Whether Jamf reports a digest stronger than MD5 (SHA-2 or SHA-3 family)
+    /// alongside a value.
+    pub fn has_strong_hash(&self) -> bool {
+        let Some(hash_type) = self.hash_type.as_deref() else {
+            return false;
+        };
+        let normalized = hash_type.to_ascii_uppercase().replace(['-', '_'], "");
+        self.hash_value.is_some()
+            && (normalized.starts_with("SHA2")
+                || normalized.starts_with("SHA3")
+                || normalized.starts_with("SHA5")
+                || normalized.starts_with("SHA384"))
+    }
+
+    /// The digest Jamf reports for `hash_type`, if any.
+    pub fn hash_of(&self, hash_type: HashType) -> Option<&str> {
+        if hash_type == HashType::Md5 {
+            return self.md5_hash.as_deref();
+        }
+        let reported = self.hash_type.as_deref().and_then(HashType::from_jamf)?;
+        if reported == hash_type {
+            self.hash_value.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// The digest to verify local content against: `hashValue` when its
+    /// `hashType` is an algorithm we can compute, otherwise `md5Hash`.
+    pub fn comparable_digest(&self) -> Option<(HashType, &str)> {
+        let strong = self
+            .hash_type
+            .as_deref()
+            .and_then(HashType::from_jamf)
+            .zip(self.hash_value.as_deref());
+        strong.or_else(|| self.md5_hash.as_deref().map(|md5| (HashType::Md5, md5)))
+    }
+
     pub fn content_updated_from(&self, old: &Self) -> bool {
         value_became_known_or_changed(old.md5_hash.as_deref(), self.md5_hash.as_deref())
             || value_became_known_or_changed(old.hash_value.as_deref(), self.hash_value.as_deref())
@@ -49,36 +146,184 @@ impl PackageDigestSnapshot {
     }
 }
 
+/// Storage details for the cloud distribution point, where the instance
+/// reports them. Every field is optional because most instances expose little
+/// or none of this.
+#[derive(Debug, Clone, Default)]
+pub struct JcdsStorageInfo {
+    pub available_bytes: Option<u64>,
+    pub upload_capable: Option<bool>,
+}
+
+/// Several package records share the name being looked up. Jamf does not
+/// enforce unique names, and picking one of them could update the wrong record.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "{} packages are named '{name}' (IDs: {}); pass --id <ID> to choose one",
+    candidates.len(),
+    describe_candidates(candidates)
+)]
+pub struct AmbiguousPackage {
+    pub name: String,
+    pub candidates: Vec<Package>,
+}
+
+/// No known JCDS inventory refresh endpoint exists on this instance, as on
+/// on-prem servers without JCDS.
+#[derive(Debug, thiserror::Error)]
+#[error("this Jamf Pro instance has no JCDS inventory refresh endpoint")]
+pub struct InventoryRefreshUnavailable;
+
+/// How a command names the package it acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageTarget<'a> {
+    /// An exact name, or an ID when no package has that name.
+    NameOrId(&'a str),
+    /// A Jamf package ID; no name search.
+    Id(&'a str),
+}
+
+fn describe_candidates(candidates: &[Package]) -> String {
+    candidates
+        .iter()
+        .map(|p| format!("{} ({})", p.id, p.file_name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl JamfClient {
-    /// Find a package by name. Returns None if not found.
+    /// Find a package by name. Returns None if not found, and
+    /// [`AmbiguousPackage`] if more than one record has the name.
     pub async fn find_package(&self, name: &str) -> Result<Option<Package>> {
-        let url = format!(
-            "{}/api/v1/packages?page=0&page-size=100&filter=packageName%3D%3D%22{}%22",
-            self.base_url,
-            urlencoding(name)
-        );
+        let mut found = self.find_packages(name).await?;
+        if found.len() > 1 {
+            return Err(AmbiguousPackage {
+                name: name.to_string(),
+                candidates: found,
+            }
+            .into());
+        }
+        Ok(found.pop())
+    }
 
-        let resp = self
-            .http
-            .get(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .send()
-            .await
-            .context("Failed to search for package")?;
+    /// Find every package whose name matches exactly. Jamf does not enforce
+    /// unique package names, so this may return more than one record.
+    pub async fn find_packages(&self, name: &str) -> Result<Vec<Package>> {
+        let query = format!("filter={}", encode_query_value(&name_filter(name)));
+        self.fetch_package_pages(&query, "search packages").await
+    }
 
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Failed to search packages (HTTP {}): {}", status, body);
+    /// Look a package up by exact name, or by ID when `target` is numeric and
+    /// no package has that name. Several records sharing the name is an
+    /// error listing their IDs, so the caller can pass one instead. An
+    /// explicit [`PackageTarget::Id`] reads that record without searching.
+    pub async fn resolve_package(&self, target: PackageTarget<'_>) -> Result<Package> {
+        let target = match target {
+            PackageTarget::Id(id) => {
+                return self
+                    .get_package(id)
+                    .await
+                    .with_context(|| format!("Package ID {} could not be read", id));
+            }
+            PackageTarget::NameOrId(target) => target,
+        };
+        match self.find_packages(target).await?.as_slice() {
+            [package] => Ok(package.clone()),
+            [] if !target.is_empty() && target.chars().all(|c| c.is_ascii_digit()) => {
+                self.get_package(target).await
+            }
+            [] => bail!("Package '{}' not found", target),
+            many => bail!(
+                "{} packages are named '{}' (IDs: {}); pass --id <ID> to choose one",
+                many.len(),
+                target,
+                describe_candidates(many)
+            ),
         }
+    }
 
-        let search: PackageSearchResponse = resp
-            .json()
+    /// Fetch every package record, one page at a time.
+    pub async fn list_packages(&self) -> Result<Vec<Package>> {
+        self.fetch_package_pages("sort=id%3Aasc", "list packages")
             .await
-            .context("Failed to parse package search response")?;
+    }
+
+    /// Collect every page of `GET /api/v1/packages?<query>`, stopping once
+    /// `totalCount` records have been read (or a page comes back empty).
+    async fn fetch_package_pages(&self, query: &str, action: &str) -> Result<Vec<Package>> {
+        let mut packages = Vec::new();
+        let mut page = 0;
+
+        loop {
+            let url = format!(
+                "{}/api/v1/packages?page={}&page-size={}&{}",
+                self.base_url, page, PACKAGE_PAGE_SIZE, query
+            );
 
-        Ok(search.results.into_iter().next())
+            let resp = self
+                .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+                .await
+                .with_context(|| format!("Failed to {}", action))?;
+
+            if !resp.status().is_success() {
+                return Err(
+                    JamfError::from_response(format!("Failed to {}", action), resp)
+                        .await
+                        .into(),
+                );
+            }
+
+            let search: PackageSearchResponse = resp.json().await.map_err(|e| {
+                JamfError::schema(format!("Failed to parse {} response", action), e)
+            })?;
+
+            let received = search.results.len();
+            packages.extend(search.results);
+            if received == 0 || packages.len() as i64 >= search.total_count {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(packages)
+    }
+
+    /// Find packages whose name matches after trimming whitespace and ignoring
+    /// case, for when the exact `packageName==` filter misses.
+    pub async fn find_similar_packages(&self, name: &str) -> Result<Vec<Package>> {
+        let wanted = normalize_package_name(name);
+        Ok(self
+            .list_packages()
+            .await?
+            .into_iter()
+            .filter(|p| normalize_package_name(&p.package_name) == wanted)
+            .collect())
+    }
+
+    /// Poll the name search until the package with `id` shows up in it.
+    ///
+    /// Jamf's package search is eventually consistent: a record returned by
+    /// `create_package` can be missing from `packageName==` results for a few
+    /// seconds. Flows that will look a fresh package up by name again (a
+    /// retry, a later batch step) should wait here first, otherwise they may
+    /// conclude it doesn't exist and create a duplicate. Returns whether the
+    /// package became searchable before `timeout`.
+    pub async fn wait_until_searchable(
+        &self,
+        name: &str,
+        id: &str,
+        timeout: Duration,
+    ) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.find_packages(name).await?.iter().any(|p| p.id == id) {
+                return Ok(true);
+            }
+            if Instant::now() + SEARCH_CONSISTENCY_POLL_INTERVAL > deadline {
+                return Ok(false);
+            }
+            tokio::time::sleep(SEARCH_CONSISTENCY_POLL_INTERVAL).await;
+        }
     }
 
     /// Create a new package record in Jamf Pro.
@@ -89,24 +334,24 @@ impl JamfClient {
         let url = format!("{}/api/v1/packages", self.base_url);
 
         let resp = self
-            .http
-            .post(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .json(req)
-            .send()
+            .authed_request(|http| {
+                http.post(&url)
+                    .header("Accept", "application/json")
+                    .json(req)
+            })
             .await
             .context("Failed to create package")?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Failed to create package (HTTP {}): {}", status, body);
+            return Err(JamfError::from_response("Failed to create package", resp)
+                .await
+                .into());
         }
 
-        resp.json()
+        Ok(resp
+            .json()
             .await
-            .context("Failed to parse create-package response")
+            .map_err(|e| JamfError::schema("Failed to parse create-package response", e))?)
     }
 
     /// Update an existing package's metadata in-place.
@@ -114,21 +359,15 @@ impl JamfClient {
         let url = format!("{}/api/v1/packages/{}", self.base_url, id);
 
         let resp = self
-            .http
-            .put(&url)
-            .bearer_auth(&self.token().await?)
-            .json(req)
-            .send()
+            .authed_request(|http| http.put(&url).json(req))
             .await
             .context("Failed to update package metadata")?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!(
-                "Failed to update package metadata (HTTP {}): {}",
-                status,
-                body
+            return Err(
+                JamfError::from_response("Failed to update package metadata", resp)
+                    .await
+                    .into(),
             );
         }
 
@@ -136,7 +375,18 @@ impl JamfClient {
     }
 
     /// Upload a file to an existing package record, with retries.
-    pub async fn upload_package(&self, id: &str, file_path: &Path) -> Result<()> {
+    pub async fn upload_package(
+        &self,
+        id: &str,
+        file_path: &Path,
+        options: &UploadOptions,
+    ) -> Result<()> {
+        if let Some(chunk_size) = options.chunk_size {
+            return self
+                .upload_package_chunked(file_path, chunk_size, options.progress.as_ref())
+                .await;
+        }
+
         let url = format!("{}/api/v1/packages/{}/upload", self.base_url, id);
 
         let file_name = file_path
@@ -145,18 +395,35 @@ impl JamfClient {
             .to_string_lossy()
             .to_string();
 
-        let metadata = tokio::fs::metadata(file_path)
-            .await
-            .context("Failed to read file metadata")?;
+        let metadata = retry_file_op(
+            options.file_open_attempts,
+            "read metadata of",
+            file_path,
+            || tokio::fs::metadata(file_path),
+        )
+        .await
+        .context("Failed to read file metadata")?;
         let file_size = metadata.len();
 
         let max_attempts = 3;
+        let mut reauthenticated = false;
         for attempt in 1..=max_attempts {
-            let file = File::open(file_path)
-                .await
-                .context("Failed to open package file")?;
+            let file = retry_file_op(options.file_open_attempts, "open", file_path, || {
+                File::open(file_path)
+            })
+            .await
+            .context("Failed to open package file")?;
 
-            let stream = FramedRead::new(file, BytesCodec::new());
+            // A fresh count per attempt, so a retry starts again from 0.
+            let sent = Arc::new(AtomicU64::new(0));
+            let progress = options.progress.clone();
+            let stream = FramedRead::new(file, BytesCodec::new()).inspect_ok(move |chunk| {
+                let sent =
+                    sent.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+                if let Some(progress) = &progress {
+                    progress.report(sent, file_size);
+                }
+            });
             let body = reqwest::Body::wrap_stream(stream);
 
             let part = multipart::Part::stream_with_length(body, file_size)
@@ -164,33 +431,79 @@ impl JamfClient {
                 .mime_str("application/octet-stream")
                 .context("Failed to set MIME type")?;
 
-            let form = multipart::Form::new().part("file", part);
+            let form = multipart::Form::new().part(options.field_name.clone(), part);
 
-            let resp = self
+            let token = self.token().await?;
+            let mut request = self
                 .http
                 .post(&url)
-                .bearer_auth(&self.token().await?)
-                .header("Accept", "application/json")
-                .multipart(form)
-                .send()
-                .await
-                .context("Failed to upload package file")?;
+                .bearer_auth(&token)
+                .header("Accept", "application/json");
+            if let Some(key) = options.idempotency_key.as_deref() {
+                request = request.header("Idempotency-Key", key);
+            }
+
+            debug!(
+                "POST {} (attempt {}/{}, {} bytes)",
+                url, attempt, max_attempts, file_size
+            );
+            let started = Instant::now();
+            let limit = self.timeouts.upload(file_size);
+            let resp = request.multipart(form).timeout(limit).send().await;
+            let resp = match resp {
+                Err(e) if e.is_timeout() => {
+                    return Err(e).context(format!(
+                        "Upload did not finish within {} (the time allowed at {}/s); \
+                         lower --min-upload-rate for a slow link",
+                        HumanDuration(limit),
+                        HumanBytes(self.timeouts.min_upload_rate)
+                    ));
+                }
+                resp => resp.context("Failed to upload package file")?,
+            };
+            debug!(
+                "Upload attempt {} -> HTTP {} in {:.2?}",
+                attempt,
+                resp.status(),
+                started.elapsed()
+            );
 
             if resp.status().is_success() {
                 return Ok(());
             }
 
             let status = resp.status();
+            let requested = retry_after(&resp);
             let resp_body = resp.text().await.unwrap_or_default();
+            debug!(
+                "Upload response body: {}",
+                redact::excerpt(&resp_body, UPLOAD_BODY_LOG_CHARS)
+            );
 
-            if attempt < max_attempts && status.is_server_error() {
-                eprintln!(
-                    "\n  Upload attempt {}/{} failed (HTTP {}), retrying in 10s...",
-                    attempt, max_attempts, status
+            if attempt < max_attempts && status == StatusCode::UNAUTHORIZED && !reauthenticated {
+                // The token lapsed while the body was streaming; renew it and
+                // resend rather than failing a long upload outright.
+                status!(
+                    "  Upload rejected with HTTP 401, renewing the access token and retrying..."
                 );
-                tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+                self.invalidate_token(&token).await;
+                reauthenticated = true;
+            } else if attempt < max_attempts
+                && (status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+            {
+                let delay = requested.unwrap_or_default().max(UPLOAD_RETRY_DELAY);
+                status!(
+                    "  Upload attempt {}/{} failed (HTTP {}), retrying in {}s...",
+                    attempt,
+                    max_attempts,
+                    status,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
             } else {
-                bail!("Failed to upload package (HTTP {}): {}", status, resp_body);
+                return Err(
+                    JamfError::from_status("Failed to upload package", status, resp_body).into(),
+                );
             }
         }
 
@@ -198,29 +511,52 @@ impl JamfClient {
     }
 
     /// Trigger JCDS inventory recalculation to refresh checksums.
+    ///
+    /// Uses the configured endpoint path if one was given. Otherwise the
+    /// default path is tried first and, if this Jamf version answers 404, the
+    /// known alternate location before giving up with
+    /// [`InventoryRefreshUnavailable`].
     pub async fn refresh_jcds_inventory(&self) -> Result<()> {
-        let url = format!("{}/api/v1/jcds/refresh-inventory", self.base_url);
+        let candidates: Vec<&str> = match self.inventory_refresh_path.as_deref() {
+            Some(path) => vec![path],
+            None => INVENTORY_REFRESH_PATHS.to_vec(),
+        };
 
-        let resp = self
-            .http
-            .post(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .send()
-            .await
-            .context("Failed to refresh JCDS inventory")?;
+        for (i, path) in candidates.iter().enumerate() {
+            let url = format!("{}{}", self.base_url, path);
+
+            let resp = self
+                .authed_idempotent_request(|http| {
+                    http.post(&url).header("Accept", "application/json")
+                })
+                .await
+                .context("Failed to refresh JCDS inventory")?;
 
-        if !resp.status().is_success() {
             let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!(
-                "Failed to refresh JCDS inventory (HTTP {}): {}",
-                status,
-                body
-            );
+            if status.is_success() {
+                return Ok(());
+            }
+            if status == StatusCode::NOT_FOUND && i + 1 < candidates.len() {
+                status!(
+                    "  {} not available on this Jamf version, trying {}...",
+                    path,
+                    candidates[i + 1]
+                );
+                continue;
+            }
+            if status == StatusCode::NOT_FOUND && self.inventory_refresh_path.is_none() {
+                return Err(InventoryRefreshUnavailable.into());
+            }
+
+            return Err(JamfError::from_response(
+                format!("Failed to refresh JCDS inventory via {}", path),
+                resp,
+            )
+            .await
+            .into());
         }
 
-        Ok(())
+        unreachable!()
     }
 
     /// Delete a file from the Jamf Cloud Distribution Service.
@@ -232,54 +568,176 @@ impl JamfClient {
         let url = format!("{}/api/v1/jcds/files/{}", self.base_url, file_name);
 
         let resp = self
-            .http
-            .delete(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .send()
+            .authed_request(|http| http.delete(&url).header("Accept", "application/json"))
             .await
             .context("Failed to delete JCDS file")?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!(
-                "Failed to delete JCDS file '{}' (HTTP {}): {}",
-                file_name,
-                status,
-                body
-            );
+            return Err(JamfError::from_response(
+                format!("Failed to delete JCDS file '{}'", file_name),
+                resp,
+            )
+            .await
+            .into());
         }
 
         Ok(())
     }
 
-    /// Read package digest/checksum fields as currently reported by Jamf Pro.
-    pub async fn get_package_digest_snapshot(
-        &self,
-        id: &str,
-    ) -> Result<Option<PackageDigestSnapshot>> {
+    /// List the files JCDS currently holds. Returns None when the endpoint is
+    /// missing or not permitted (on-prem instances, restricted API roles).
+    pub async fn list_jcds_files(&self) -> Result<Option<Vec<JcdsFile>>> {
+        match self.get_optional_json("/api/v1/jcds/files").await? {
+            Some(payload) => {
+                Ok(Some(serde_json::from_value(payload).map_err(|e| {
+                    JamfError::schema("Failed to parse JCDS file list", e)
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Best-effort read of JCDS free space and upload capability.
+    ///
+    /// Returns an empty [`JcdsStorageInfo`] rather than an error when the
+    /// endpoints are missing or not permitted. An answer that can't be parsed
+    /// is an error; callers treating the check as a preflight may skip it.
+    pub async fn get_jcds_storage_info(&self) -> Result<JcdsStorageInfo> {
+        let mut info = JcdsStorageInfo::default();
+
+        if let Some(payload) = self
+            .get_optional_json("/api/v1/cloud-distribution-point")
+            .await?
+        {
+            info.available_bytes = find_first_u64(
+                &payload,
+                &["availableSpace", "availableBytes", "freeSpace", "freeBytes"],
+            );
+        }
+
+        if let Some(payload) = self
+            .get_optional_json("/api/v1/cloud-distribution-point/upload-capability")
+            .await?
+        {
+            info.upload_capable = find_first_bool(&payload, &["directUploadCapable"]);
+        }
+
+        Ok(info)
+    }
+
+    /// GET a JSON document, returning None for 403/404 instead of failing.
+    /// A success answer that isn't JSON is a [`JamfError::UnexpectedSchema`]
+    /// error, not an absent endpoint.
+    pub async fn get_optional_json(&self, path: &str) -> Result<Option<Value>> {
+        let url = format!("{}{}", self.base_url, path);
+
+        let resp = self
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+            .await
+            .with_context(|| format!("Failed to read {}", path))?;
+
+        let status = resp.status();
+        if status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(
+                JamfError::from_response(format!("Failed to read {}", path), resp)
+                    .await
+                    .into(),
+            );
+        }
+
+        let payload = resp
+            .json()
+            .await
+            .map_err(|e| JamfError::schema(format!("Failed to parse {} response", path), e))?;
+        Ok(Some(payload))
+    }
+
+    /// Number of entries in the package's change history.
+    pub async fn get_package_history_count(&self, id: &str) -> Result<i64> {
+        let url = format!(
+            "{}/api/v1/packages/{}/history?page=0&page-size=1",
+            self.base_url, id
+        );
+
+        let resp = self
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
+            .await
+            .context("Failed to read package history")?;
+
+        if !resp.status().is_success() {
+            return Err(
+                JamfError::from_response("Failed to read package history", resp)
+                    .await
+                    .into(),
+            );
+        }
+
+        let history: PackageHistoryResponse = resp
+            .json()
+            .await
+            .map_err(|e| JamfError::schema("Failed to parse package history response", e))?;
+        Ok(history.total_count)
+    }
+
+    /// Fetch the full package record exactly as Jamf Pro returns it.
+    pub async fn get_package_record(&self, id: &str) -> Result<Value> {
         let url = format!("{}/api/v1/packages/{}", self.base_url, id);
 
         let resp = self
-            .http
-            .get(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .send()
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
             .await
             .context("Failed to read package details")?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Failed to read package details (HTTP {}): {}", status, body);
+            return Err(
+                JamfError::from_response("Failed to read package details", resp)
+                    .await
+                    .into(),
+            );
         }
 
-        let payload: Value = resp
+        Ok(resp
             .json()
             .await
-            .context("Failed to parse package details response")?;
+            .map_err(|e| JamfError::schema("Failed to parse package details response", e))?)
+    }
+
+    /// Fetch a package by its Jamf ID.
+    pub async fn get_package(&self, id: &str) -> Result<Package> {
+        let record = self.get_package_record(id).await?;
+        Ok(serde_json::from_value(record)
+            .map_err(|e| JamfError::schema("Failed to parse package details response", e))?)
+    }
+
+    /// Delete a package record.
+    pub async fn delete_package(&self, id: &str) -> Result<()> {
+        let url = format!("{}/api/v1/packages/{}", self.base_url, id);
+
+        let resp = self
+            .authed_request(|http| http.delete(&url).header("Accept", "application/json"))
+            .await
+            .context("Failed to delete package")?;
+
+        if !resp.status().is_success() {
+            return Err(
+                JamfError::from_response(format!("Failed to delete package {}", id), resp)
+                    .await
+                    .into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Read package digest/checksum fields as currently reported by Jamf Pro.
+    pub async fn get_package_digest_snapshot(
+        &self,
+        id: &str,
+    ) -> Result<Option<PackageDigestSnapshot>> {
+        let payload = self.get_package_record(id).await?;
 
         let snapshot = PackageDigestSnapshot {
             md5_hash: find_first_string(
@@ -299,14 +757,65 @@ impl JamfClient {
     }
 }
 
-/// Simple percent-encoding for the filter query parameter value.
-fn urlencoding(s: &str) -> String {
-    s.replace('%', "%25")
-        .replace(' ', "%20")
-        .replace('"', "%22")
-        .replace('#', "%23")
-        .replace('&', "%26")
-        .replace('+', "%2B")
+/// Run a filesystem operation, retrying briefly on failure. Network mounts can
+/// disappear for a moment; that shouldn't abort an otherwise-healthy upload.
+async fn retry_file_op<T, F, Fut>(
+    attempts: u32,
+    action: &str,
+    path: &Path,
+    mut op: F,
+) -> std::io::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::io::Result<T>>,
+{
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts => {
+                status!(
+                    "  Could not {} {} ({}); retrying in {}s ({}/{})...",
+                    action,
+                    path.display(),
+                    e,
+                    FILE_OPEN_RETRY_DELAY.as_secs(),
+                    attempt,
+                    attempts - 1
+                );
+                tokio::time::sleep(FILE_OPEN_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Normalize a package name for loose comparison: surrounding whitespace
+/// and letter case are ignored.
+pub fn normalize_package_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Characters left as-is in a query parameter value: RFC 3986 unreserved.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// RSQL filter matching `packageName` exactly. Inside the double-quoted
+/// argument only `"` and `\` are special, so those are backslash-escaped;
+/// everything else (parentheses, commas, `=`, `;`) is literal there.
+fn name_filter(name: &str) -> String {
+    let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("packageName==\"{}\"", escaped)
+}
+
+/// Percent-encode a query parameter value (UTF-8 for non-ASCII).
+fn encode_query_value(value: &str) -> String {
+    utf8_percent_encode(value, QUERY_VALUE).to_string()
 }
 
 fn value_became_known_or_changed<T: PartialEq + ?Sized>(old: Option<&T>, new: Option<&T>) -> bool {
@@ -357,6 +866,26 @@ fn find_first_u64(value: &Value, keys: &[&str]) -> Option<u64> {
     }
 }
 
+fn find_first_bool(value: &Value, keys: &[&str]) -> Option<bool> {
+    match value {
+        Value::Object(map) => {
+            for key in keys {
+                if let Some(found) = map.get(*key).and_then(Value::as_bool) {
+                    return Some(found);
+                }
+            }
+            for nested in map.values() {
+                if let Some(found) = find_first_bool(nested, keys) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+        Value::Array(items) => items.iter().find_map(|item| find_first_bool(item, keys)),
+        _ => None,
+    }
+}
+
 fn value_to_string(value: &Value) -> Option<String> {
     match value {
         Value::String(s) => {
@@ -381,9 +910,48 @@ fn value_to_u64(value: &Value) -> Option<u64> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
     use serde_json::json;
 
-    use super::{PackageDigestSnapshot, find_first_string, find_first_u64};
+    use crate::api::error::JamfError;
+    use crate::api::mock::{self, MockState};
+    use crate::events::ProgressCallback;
+
+    use super::{
+        PackageDigestSnapshot, UploadOptions, encode_query_value, find_first_string,
+        find_first_u64, name_filter,
+    };
+
+    fn filter_query(name: &str) -> String {
+        encode_query_value(&name_filter(name))
+    }
+
+    #[test]
+    fn encodes_spaces_and_parentheses_in_name_filter() {
+        assert_eq!(
+            filter_query("Acme App (2024), v1"),
+            "packageName%3D%3D%22Acme%20App%20%282024%29%2C%20v1%22"
+        );
+    }
+
+    #[test]
+    fn escapes_quotes_inside_name_filter() {
+        assert_eq!(
+            name_filter(r#"Say "hi" \ bye"#),
+            r#"packageName=="Say \"hi\" \\ bye""#
+        );
+        assert_eq!(filter_query(r#"a"b"#), "packageName%3D%3D%22a%5C%22b%22");
+    }
+
+    #[test]
+    fn encodes_unicode_name_filter_as_utf8() {
+        assert_eq!(
+            filter_query("Café+Ünïcode&"),
+            "packageName%3D%3D%22Caf%C3%A9%2B%C3%9Cn%C3%AFcode%26%22"
+        );
+    }
 
     #[test]
     fn parses_digest_fields_from_nested_json() {
@@ -409,4 +977,58 @@ mod tests {
         assert_eq!(snapshot.hash_value.as_deref(), Some("def456"));
         assert_eq!(snapshot.file_size, Some(42));
     }
+
+    #[tokio::test]
+    async fn list_packages_reads_every_page() {
+        let state = Arc::new(MockState {
+            packages: 250,
+            ..MockState::default()
+        });
+        let client = mock::connect(state.clone()).await;
+
+        let packages = client.list_packages().await.unwrap();
+        assert_eq!(packages.len(), 250);
+        assert_eq!(packages[249].id, "250");
+        assert_eq!(state.package_page_hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn upload_rejected_for_an_expired_token_is_sent_again() {
+        let state = Arc::new(MockState {
+            reject_first_token: true,
+            ..MockState::default()
+        });
+        let client = mock::connect(state.clone()).await;
+        let path = std::env::temp_dir().join(format!("jpu-upload-{}.pkg", std::process::id()));
+        std::fs::write(&path, vec![b'x'; 64 * 1024]).unwrap();
+
+        let sent = Arc::new(AtomicU64::new(0));
+        let reported = sent.clone();
+        let options = UploadOptions {
+            progress: Some(ProgressCallback::new(move |done, _| {
+                reported.store(done, Ordering::SeqCst)
+            })),
+            ..UploadOptions::default()
+        };
+        let uploaded = client.upload_package("1", &path, &options).await;
+        std::fs::remove_file(&path).unwrap();
+
+        uploaded.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 64 * 1024);
+        assert_eq!(state.token_hits.load(Ordering::SeqCst), 2);
+        assert_eq!(state.upload_hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unparsable_storage_info_is_a_schema_error() {
+        let state = Arc::new(MockState {
+            cloud_distribution_point: Some("<html>Maintenance</html>"),
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+
+        let err = client.get_jcds_storage_info().await.unwrap_err();
+        let jamf = err.downcast_ref::<JamfError>().unwrap();
+        assert_eq!(jamf.exit_code(), 9);
+    }
 }
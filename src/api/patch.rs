@@ -0,0 +1,164 @@
+use anyhow::Result;
+
+use crate::api::client::JamfClient;
+use crate::api::error::JamfError;
+use crate::models::patch::{
+    PatchPolicyReference, PatchPolicySearchResponse, PatchPolicySummary, PatchTitleConfiguration,
+    PatchTitleReference,
+};
+
+const PATCH_POLICY_PAGE_SIZE: usize = 100;
+
+impl JamfClient {
+    /// Fetch every patch software title configuration. Returns None when the
+    /// endpoint is missing or not permitted, so callers can skip patch checks.
+    ///
+    /// The endpoint has no paging: one response covers every title.
+    pub async fn list_patch_title_configurations(
+        &self,
+    ) -> Result<Option<Vec<PatchTitleConfiguration>>> {
+        match self
+            .get_optional_json("/api/v2/patch-software-title-configurations")
+            .await?
+        {
+            Some(payload) => Ok(Some(serde_json::from_value(payload).map_err(|e| {
+                JamfError::schema("Failed to parse patch software title list", e)
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch every patch policy, one page at a time. Returns None when the
+    /// endpoint is missing or not permitted.
+    pub async fn list_patch_policies(&self) -> Result<Option<Vec<PatchPolicySummary>>> {
+        let mut policies = Vec::new();
+        let mut page = 0;
+
+        loop {
+            let path = format!(
+                "/api/v2/patch-policies?page={}&page-size={}&sort=id%3Aasc",
+                page, PATCH_POLICY_PAGE_SIZE
+            );
+            let Some(payload) = self.get_optional_json(&path).await? else {
+                return Ok(None);
+            };
+            let search: PatchPolicySearchResponse = serde_json::from_value(payload)
+                .map_err(|e| JamfError::schema("Failed to parse patch policy list", e))?;
+
+            let received = search.results.len();
+            policies.extend(search.results);
+            if received == 0 || policies.len() as i64 >= search.total_count {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(Some(policies))
+    }
+
+    /// The patch title versions that install `package_id`, and the patch
+    /// policies deploying those versions. None when the token cannot read
+    /// patch titles; the policies are None when it cannot read patch policies.
+    pub async fn find_patch_references(
+        &self,
+        package_id: &str,
+    ) -> Result<Option<(Vec<PatchTitleReference>, Option<Vec<PatchPolicyReference>>)>> {
+        let Some(titles) = self.list_patch_title_configurations().await? else {
+            return Ok(None);
+        };
+        let titles = patch_title_references(&titles, package_id);
+        if titles.is_empty() {
+            return Ok(Some((titles, Some(Vec::new()))));
+        }
+        let policies = self
+            .list_patch_policies()
+            .await?
+            .map(|policies| patch_policy_references(&policies, &titles));
+        Ok(Some((titles, policies)))
+    }
+}
+
+/// Patch title versions whose definition installs the package `package_id`.
+pub fn patch_title_references(
+    titles: &[PatchTitleConfiguration],
+    package_id: &str,
+) -> Vec<PatchTitleReference> {
+    titles
+        .iter()
+        .flat_map(|title| {
+            title
+                .packages
+                .iter()
+                .filter(|p| p.package_id == package_id)
+                .map(|p| PatchTitleReference {
+                    id: title.id.clone(),
+                    name: title.display_name.clone(),
+                    version: p.version.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Patch policies whose target version is one of the `titles` versions, and
+/// so deploy the package those versions install.
+pub fn patch_policy_references(
+    policies: &[PatchPolicySummary],
+    titles: &[PatchTitleReference],
+) -> Vec<PatchPolicyReference> {
+    policies
+        .iter()
+        .filter_map(|policy| {
+            let title_id = policy.policy_software_title_configuration_id.as_deref()?;
+            let version = policy.policy_target_version.as_deref()?;
+            titles
+                .iter()
+                .any(|t| t.id == title_id && t.version == version)
+                .then(|| PatchPolicyReference {
+                    id: policy.id.clone(),
+                    name: policy.policy_name.clone(),
+                    title_id: title_id.to_string(),
+                    version: version.to_string(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{patch_policy_references, patch_title_references};
+    use crate::models::patch::{PatchPolicySummary, PatchTitleConfiguration};
+
+    #[test]
+    fn patch_policies_match_the_versions_that_install_the_package() {
+        let titles: Vec<PatchTitleConfiguration> = serde_json::from_str(
+            r#"[
+                {"id": "1", "displayName": "Firefox", "packages": [
+                    {"packageId": "10", "version": "128.0", "displayName": "Firefox 128"},
+                    {"packageId": "11", "version": "129.0", "displayName": "Firefox 129"}
+                ]},
+                {"id": "2", "displayName": "Chrome"}
+            ]"#,
+        )
+        .unwrap();
+        let policies: Vec<PatchPolicySummary> = serde_json::from_str(
+            r#"[
+                {"id": "5", "policyName": "Firefox 128", "policyTargetVersion": "128.0",
+                 "policySoftwareTitleConfigurationId": "1"},
+                {"id": "6", "policyName": "Firefox 129", "policyTargetVersion": "129.0",
+                 "policySoftwareTitleConfigurationId": "1"},
+                {"id": "7", "policyName": "Chrome 128", "policyTargetVersion": "128.0",
+                 "policySoftwareTitleConfigurationId": "2"}
+            ]"#,
+        )
+        .unwrap();
+
+        let title_refs = patch_title_references(&titles, "10");
+        assert_eq!(title_refs.len(), 1);
+        assert_eq!(title_refs[0].name, "Firefox");
+        assert_eq!(title_refs[0].version, "128.0");
+
+        let policy_refs = patch_policy_references(&policies, &title_refs);
+        let ids: Vec<&str> = policy_refs.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["5"]);
+    }
+}
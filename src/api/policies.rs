@@ -1,32 +1,54 @@
-use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+use futures_util::{StreamExt, stream};
+use quick_xml::events::{BytesText, Event};
+use quick_xml::{Reader, Writer};
+use reqwest::StatusCode;
 
 use crate::api::client::JamfClient;
+use crate::api::error::JamfError;
+use crate::events::{ProgressCallback, status};
 use crate::models::policy::{AffectedPolicy, PolicyListResponse};
 
+/// The token may write packages but not read classic-API policies (HTTP 403).
+/// Split-privilege API clients hit this; callers can skip the policy scan.
+#[derive(Debug, thiserror::Error)]
+#[error("{action} was forbidden (HTTP 403): the API client lacks classic API policy read access")]
+pub struct PolicyReadForbidden {
+    pub action: String,
+}
+
 impl JamfClient {
     /// Fetch the list of all policy IDs and names.
+    ///
+    /// The classic endpoint has no paging: it returns every policy in one
+    /// response, so a single request already covers the full list.
     pub async fn list_policies(&self) -> Result<Vec<(i64, String)>> {
         let url = format!("{}/JSSResource/policies", self.base_url);
 
         let resp = self
-            .http
-            .get(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/json")
-            .send()
+            .authed_request(|http| http.get(&url).header("Accept", "application/json"))
             .await
             .context("Failed to list policies")?;
 
+        if resp.status() == StatusCode::FORBIDDEN {
+            return Err(PolicyReadForbidden {
+                action: "Listing policies".to_string(),
+            }
+            .into());
+        }
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Failed to list policies (HTTP {}): {}", status, body);
+            return Err(JamfError::from_response("Failed to list policies", resp)
+                .await
+                .into());
         }
 
         let list: PolicyListResponse = resp
             .json()
             .await
-            .context("Failed to parse policy list response")?;
+            .map_err(|e| JamfError::schema("Failed to parse policy list response", e))?;
 
         Ok(list
             .policies
@@ -41,18 +63,22 @@ impl JamfClient {
         let url = format!("{}/JSSResource/policies/id/{}", self.base_url, id);
 
         let resp = self
-            .http
-            .get(&url)
-            .bearer_auth(&self.token().await?)
-            .header("Accept", "application/xml")
-            .send()
+            .authed_request(|http| http.get(&url).header("Accept", "application/xml"))
             .await
             .with_context(|| format!("Failed to fetch policy {}", id))?;
 
+        if resp.status() == StatusCode::FORBIDDEN {
+            return Err(PolicyReadForbidden {
+                action: format!("Reading policy {}", id),
+            }
+            .into());
+        }
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Failed to fetch policy {} (HTTP {}): {}", id, status, body);
+            return Err(
+                JamfError::from_response(format!("Failed to fetch policy {}", id), resp)
+                    .await
+                    .into(),
+            );
         }
 
         resp.text()
@@ -60,45 +86,591 @@ impl JamfClient {
             .with_context(|| format!("Failed to read policy {} body", id))
     }
 
-    /// Find all policies that reference a package by packageName or fileName.
-    /// The policy XML <name> field may contain either the display name or the file name.
+    /// Replace parts of a policy. `xml` is a `<policy>` document holding only
+    /// the sections to change; the classic API leaves everything else as is.
+    pub async fn update_policy_xml(&self, id: i64, xml: &str) -> Result<()> {
+        let url = format!("{}/JSSResource/policies/id/{}", self.base_url, id);
+
+        let resp = self
+            .authed_request(|http| {
+                http.put(&url)
+                    .header("Content-Type", "application/xml")
+                    .body(xml.to_string())
+            })
+            .await
+            .with_context(|| format!("Failed to update policy {}", id))?;
+
+        if !resp.status().is_success() {
+            return Err(
+                JamfError::from_response(format!("Failed to update policy {}", id), resp)
+                    .await
+                    .into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Flush a policy's entire log (`Zero Days` interval), so computers that
+    /// already completed it run it again at their next check-in.
+    pub async fn flush_policy_logs(&self, id: i64) -> Result<()> {
+        let url = format!(
+            "{}/JSSResource/logflush/policy/id/{}/interval/Zero+Days",
+            self.base_url, id
+        );
+
+        let resp = self
+            .authed_request(|http| http.delete(&url))
+            .await
+            .with_context(|| format!("Failed to flush policy {}", id))?;
+
+        if !resp.status().is_success() {
+            return Err(
+                JamfError::from_response(format!("Failed to flush policy {}", id), resp)
+                    .await
+                    .into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Find all policies that reference a package by ID, packageName or
+    /// fileName. The policy XML `<name>` field may contain either the display
+    /// name or the file name.
     pub async fn find_policies_with_package(
         &self,
-        package_name: &str,
-        file_name: &str,
+        package: &PackageMatch<'_>,
+        options: &ScanOptions,
+    ) -> Result<Vec<AffectedPolicy>> {
+        self.find_policies_with_packages(std::slice::from_ref(package), options)
+            .await
+    }
+
+    /// Like [`find_policies_with_package`](Self::find_policies_with_package),
+    /// for policies referencing any of `packages`, in a single scan.
+    pub async fn find_policies_with_packages(
+        &self,
+        packages: &[PackageMatch<'_>],
+        options: &ScanOptions,
     ) -> Result<Vec<AffectedPolicy>> {
+        if let Some(dir) = options.source_dir.as_deref() {
+            return scan_policy_dump(dir, packages).await;
+        }
+
+        if let Some(dir) = options.dump_dir.as_deref() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("Failed to create dump directory: {}", dir.display()))?;
+        }
+
         let policies = self.list_policies().await?;
         let total = policies.len();
+        let done = AtomicUsize::new(0);
+
+        // `buffered` keeps results in policy-list order however the requests
+        // complete; the counter tracks completions for the progress callback.
+        let mut fetches = stream::iter(&policies)
+            .map(|(id, name)| {
+                let done = &done;
+                async move {
+                    let xml = self
+                        .get_policy_xml(*id)
+                        .await
+                        .with_context(|| format!("Failed to scan policy '{}' (ID: {})", name, id));
+                    let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(progress) = &options.progress {
+                        progress.report(finished as u64, total as u64);
+                    }
+                    xml.map(|xml| (*id, name, xml))
+                }
+            })
+            .buffered(options.concurrency.max(1));
+
         let mut affected = Vec::new();
+        while let Some(fetched) = fetches.next().await {
+            let (id, name, xml) = fetched?;
+
+            if let Some(dir) = options.dump_dir.as_deref() {
+                let target = dir.join(policy_file_name(id));
+                tokio::fs::write(&target, &xml)
+                    .await
+                    .with_context(|| format!("Failed to write {}", target.display()))?;
+            }
+
+            let parsed = ParsedPolicy::parse(&xml).with_context(|| format!("Policy {}", id))?;
+            if packages.iter().any(|p| parsed.references(p)) {
+                affected.push(AffectedPolicy {
+                    id,
+                    name: name.clone(),
+                });
+            }
+        }
+
+        Ok(affected)
+    }
+}
+
+/// Policy XML requests `find_policies_with_package` keeps in flight.
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 8;
+
+/// Where `find_policies_with_package` reads policy XML from.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Read `policy-<id>.xml` files from this directory instead of the API.
+    pub source_dir: Option<PathBuf>,
+    /// Write every fetched policy's XML to this directory.
+    pub dump_dir: Option<PathBuf>,
+    /// Maximum number of policies fetched at once.
+    pub concurrency: usize,
+    /// Told how many policies have been fetched as the scan goes.
+    pub progress: Option<ProgressCallback>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            source_dir: None,
+            dump_dir: None,
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            progress: None,
+        }
+    }
+}
+
+/// File name used for a policy's XML in dump/export directories.
+pub fn policy_file_name(id: i64) -> String {
+    format!("policy-{}.xml", id)
+}
+
+/// Scan a directory of `policy-<id>.xml` files written by `--dump-policy-xml`.
+async fn scan_policy_dump(
+    dir: &Path,
+    packages: &[PackageMatch<'_>],
+) -> Result<Vec<AffectedPolicy>> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to read policy source: {}", dir.display()))?;
 
-        for (i, (id, name)) in policies.iter().enumerate() {
-            eprint!("\r  Scanning policy {}/{}...", i + 1, total);
+    let mut policies = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("Failed to read policy source: {}", dir.display()))?
+    {
+        let path = entry.path();
+        let Some(id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("policy-"))
+            .and_then(|n| n.strip_suffix(".xml"))
+            .and_then(|n| n.parse::<i64>().ok())
+        else {
+            continue;
+        };
 
-            let xml = self.get_policy_xml(*id).await?;
+        let xml = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        policies.push((id, xml));
+    }
+    policies.sort_by_key(|(id, _)| *id);
+
+    status!(
+        "  Scanning {} policies from {}...",
+        policies.len(),
+        dir.display()
+    );
+
+    let mut affected = Vec::new();
+    for (id, xml) in policies {
+        let parsed = ParsedPolicy::parse(&xml).with_context(|| format!("policy-{}.xml", id))?;
+        if packages.iter().any(|p| parsed.references(p)) {
+            affected.push(AffectedPolicy {
+                id,
+                name: parsed.name.unwrap_or_default(),
+            });
+        }
+    }
+    Ok(affected)
+}
 
-            if let Some(pkg_config) = extract_section(&xml, "package_configuration") {
-                let matches = pkg_config.contains(&format!("<name>{}</name>", package_name))
-                    || pkg_config.contains(&format!("<name>{}</name>", file_name));
+/// The package a rewritten policy entry should point at.
+#[derive(Debug, Clone)]
+pub struct PackageReference<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+}
 
-                if matches {
-                    affected.push(AffectedPolicy {
-                        id: *id,
-                        name: name.clone(),
-                    });
+/// Rewrite every `<package>` entry in the policy's `<package_configuration>`
+/// that refers to the package (by `old_names`, by `old_ids` or by its own ID)
+/// so it carries `target`'s ID and name. `old_ids` are the records of earlier
+/// versions the policies should move off, e.g. a version-pinned
+/// `MyApp-2.3.0` replaced by a new `MyApp-2.4.0` record.
+///
+/// Returns a `<policy>` document holding just the rewritten
+/// `<package_configuration>`, ready for [`JamfClient::update_policy_xml`], or
+/// None when every matching entry already points at `target`. All other
+/// markup inside the section is copied through unchanged.
+pub fn rewrite_package_configuration(
+    xml: &str,
+    old_ids: &[&str],
+    old_names: &[&str],
+    target: &PackageReference<'_>,
+) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut capturing = false;
+    let mut package: Option<Vec<Event<'static>>> = None;
+    let mut changed = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| JamfError::schema("Failed to parse policy XML", e))?
+            .into_owned();
+
+        match &event {
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                if !capturing && name == b"package_configuration" {
+                    capturing = true;
+                } else if capturing
+                    && package.is_none()
+                    && name == b"package"
+                    && path.last().is_some_and(|p| p == b"packages")
+                {
+                    package = Some(Vec::new());
+                }
+                path.push(name);
+            }
+            Event::End(e) => {
+                path.pop();
+                let name = e.local_name();
+                if capturing && name.as_ref() == b"package" && package.is_some() {
+                    let mut events = package.take().unwrap_or_default();
+                    events.push(event.clone());
+                    changed |= retarget_package_entry(&mut events, old_ids, old_names, target);
+                    for e in events {
+                        writer.write_event(e)?;
+                    }
+                    continue;
+                }
+                if capturing && name.as_ref() == b"package_configuration" {
+                    writer.write_event(event)?;
+                    break;
                 }
             }
+            Event::Eof => break,
+            _ => {}
         }
-        eprintln!(); // newline after progress
 
-        Ok(affected)
+        if let Some(events) = package.as_mut() {
+            events.push(event);
+        } else if capturing {
+            writer.write_event(event)?;
+        }
+    }
+
+    if !changed {
+        return Ok(None);
+    }
+    let section = String::from_utf8(writer.into_inner()).context("Policy XML is not UTF-8")?;
+    Ok(Some(format!("<policy>{}</policy>", section)))
+}
+
+/// Point one buffered `<package>` entry at `target` if it refers to the
+/// package. Returns whether anything changed.
+fn retarget_package_entry(
+    events: &mut Vec<Event<'static>>,
+    old_ids: &[&str],
+    old_names: &[&str],
+    target: &PackageReference<'_>,
+) -> bool {
+    let current_id = child_text(events, b"id");
+    let current_name = child_text(events, b"name");
+    let refers = current_id
+        .as_deref()
+        .is_some_and(|id| id == target.id || old_ids.contains(&id))
+        || current_name
+            .as_deref()
+            .is_some_and(|n| n == target.name || old_names.contains(&n));
+    if !refers
+        || (current_id.as_deref() == Some(target.id)
+            && current_name.as_deref() == Some(target.name))
+    {
+        return false;
+    }
+
+    set_child_text(events, b"id", target.id);
+    set_child_text(events, b"name", target.name);
+    true
+}
+
+/// Text of a direct child element of the buffered `<package>` entry.
+fn child_text(events: &[Event<'static>], child: &[u8]) -> Option<String> {
+    let start = direct_child_start(events, child)?;
+    match events.get(start + 1) {
+        Some(Event::Text(t)) => t.unescape().ok().map(|t| t.trim().to_string()),
+        Some(Event::CData(t)) => Some(String::from_utf8_lossy(t).trim().to_string()),
+        _ => Some(String::new()),
+    }
+}
+
+fn set_child_text(events: &mut Vec<Event<'static>>, child: &[u8], value: &str) {
+    let Some(start) = direct_child_start(events, child) else {
+        return;
+    };
+    let text = Event::Text(BytesText::new(value).into_owned());
+    match events.get(start + 1) {
+        Some(Event::Text(_) | Event::CData(_)) => events[start + 1] = text,
+        _ => events.insert(start + 1, text),
+    }
+}
+
+/// Index of the `Start` event for a direct child of the entry's root element.
+fn direct_child_start(events: &[Event<'static>], child: &[u8]) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(e) => {
+                depth += 1;
+                if depth == 2 && e.local_name().as_ref() == child {
+                    return Some(i);
+                }
+            }
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// What a policy scan matches against: the package's ID plus the names a
+/// policy might use for it.
+#[derive(Debug, Clone)]
+pub struct PackageMatch<'a> {
+    pub id: Option<&'a str>,
+    pub package_name: &'a str,
+    pub file_name: &'a str,
+}
+
+/// One `<package>` entry in a policy's `<package_configuration>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PolicyPackageEntry {
+    id: Option<String>,
+    name: Option<String>,
+}
+
+/// The parts of a classic-API policy document the scanner looks at.
+#[derive(Debug, Default)]
+struct ParsedPolicy {
+    name: Option<String>,
+    packages: Vec<PolicyPackageEntry>,
+}
+
+impl ParsedPolicy {
+    /// Walk the document with a real XML reader. Only elements at the exact
+    /// paths `policy/general/name` and
+    /// `policy/package_configuration/packages/package/{id,name}` count, so a
+    /// `<name>` nested anywhere else can't produce a false match. Elements are
+    /// compared by local name, so namespace prefixes are ignored, and CDATA
+    /// sections count as text.
+    fn parse(xml: &str) -> Result<Self> {
+        const GENERAL_NAME: &[&[u8]] = &[b"policy", b"general", b"name"];
+        const PACKAGE: &[&[u8]] = &[b"policy", b"package_configuration", b"packages", b"package"];
+
+        let mut reader = Reader::from_str(xml);
+        let mut policy = ParsedPolicy::default();
+        let mut path: Vec<Vec<u8>> = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            match reader
+                .read_event()
+                .map_err(|e| JamfError::schema("Failed to parse policy XML", e))?
+            {
+                Event::Start(e) => {
+                    path.push(e.local_name().as_ref().to_vec());
+                    text.clear();
+                    if path_is(&path, PACKAGE) {
+                        policy.packages.push(PolicyPackageEntry::default());
+                    }
+                }
+                Event::Empty(e) => {
+                    path.push(e.local_name().as_ref().to_vec());
+                    if path_is(&path, PACKAGE) {
+                        policy.packages.push(PolicyPackageEntry::default());
+                    }
+                    path.pop();
+                }
+                Event::Text(t) => text.push_str(&t.unescape().context("Invalid policy XML text")?),
+                Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+                Event::End(_) => {
+                    let value = text.trim().to_string();
+                    if path_is(&path, GENERAL_NAME) {
+                        policy.name = Some(value);
+                    } else if path.len() == PACKAGE.len() + 1
+                        && path_is(&path[..PACKAGE.len()], PACKAGE)
+                        && let Some(entry) = policy.packages.last_mut()
+                    {
+                        match path[PACKAGE.len()].as_slice() {
+                            b"id" => entry.id = Some(value),
+                            b"name" => entry.name = Some(value),
+                            _ => {}
+                        }
+                    }
+                    text.clear();
+                    path.pop();
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// Whether any package entry names the package or carries its ID.
+    fn references(&self, package: &PackageMatch<'_>) -> bool {
+        self.packages.iter().any(|entry| {
+            entry.id.as_deref().is_some_and(|id| package.id == Some(id))
+                || entry
+                    .name
+                    .as_deref()
+                    .is_some_and(|n| n == package.package_name || n == package.file_name)
+        })
     }
 }
 
-/// Extract the content between <tag>...</tag> from XML.
-fn extract_section<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
-    let open = format!("<{}>", tag);
-    let close = format!("</{}>", tag);
-    let start = xml.find(&open)?;
-    let end = xml.find(&close)?;
-    Some(&xml[start..end + close.len()])
+fn path_is(path: &[Vec<u8>], expected: &[&[u8]]) -> bool {
+    path.len() == expected.len() && path.iter().zip(expected).all(|(a, b)| a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PackageMatch, PackageReference, ParsedPolicy, rewrite_package_configuration};
+
+    const POLICY: &str = r#"<policy><general><id>7</id><name>Install Chrome</name></general><package_configuration><packages><size>2</size><package><id>12</id><name>Chrome-120.pkg</name><action>Install</action></package><package><id>40</id><name>Zoom.pkg</name><action>Install</action></package></packages></package_configuration><scripts/></policy>"#;
+
+    #[test]
+    fn rewrites_only_the_matching_package_entry() {
+        let target = PackageReference {
+            id: "12",
+            name: "Chrome",
+        };
+        let update = rewrite_package_configuration(POLICY, &[], &["Chrome-120.pkg"], &target)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            update,
+            "<policy><package_configuration><packages><size>2</size><package><id>12</id><name>Chrome</name><action>Install</action></package><package><id>40</id><name>Zoom.pkg</name><action>Install</action></package></packages></package_configuration></policy>"
+        );
+    }
+
+    #[test]
+    fn moves_entries_off_an_older_version_record() {
+        // Policy 7 pins the 120 record (ID 12); the new version is record 31.
+        let target = PackageReference {
+            id: "31",
+            name: "Chrome-121",
+        };
+        let update = rewrite_package_configuration(POLICY, &["12"], &[], &target)
+            .unwrap()
+            .unwrap();
+        assert!(update.contains(
+            "<package><id>31</id><name>Chrome-121</name><action>Install</action></package>"
+        ));
+        assert!(update.contains("<id>40</id><name>Zoom.pkg</name>"));
+    }
+
+    #[test]
+    fn skips_policies_already_pointing_at_the_package() {
+        let target = PackageReference {
+            id: "40",
+            name: "Zoom.pkg",
+        };
+        assert!(
+            rewrite_package_configuration(POLICY, &[], &["Zoom.pkg"], &target)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn matches_package_names_exactly_at_the_package_path() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<policy>
+  <general><id>3</id><name >Browsers</name><category><name>Chrome</name></category></general>
+  <scope><computers><computer><name>Chrome</name></computer></computers></scope>
+  <package_configuration>
+    <packages>
+      <size>1</size>
+      <package kind="install">
+        <id>55</id>
+        <name>
+          Chrome Beta
+        </name>
+      </package>
+    </packages>
+  </package_configuration>
+</policy>"#;
+        let parsed = ParsedPolicy::parse(xml).unwrap();
+        assert_eq!(parsed.name.as_deref(), Some("Browsers"));
+
+        let chrome = PackageMatch {
+            id: Some("5"),
+            package_name: "Chrome",
+            file_name: "Chrome.pkg",
+        };
+        assert!(!parsed.references(&chrome));
+
+        let beta = PackageMatch {
+            id: None,
+            package_name: "Chrome Beta",
+            file_name: "ChromeBeta.pkg",
+        };
+        assert!(parsed.references(&beta));
+
+        let by_id = PackageMatch {
+            id: Some("55"),
+            package_name: "Renamed",
+            file_name: "Renamed.pkg",
+        };
+        assert!(parsed.references(&by_id));
+    }
+
+    #[test]
+    fn reads_cdata_and_namespaced_elements_and_matches_whole_names() {
+        let xml = r#"<jss:policy xmlns:jss="urn:jamf"><jss:general><jss:name><![CDATA[Office & Teams]]></jss:name></jss:general><jss:package_configuration><jss:packages><jss:package><jss:id>9</jss:id><jss:name><![CDATA[Microsoft Office 2024.pkg]]></jss:name></jss:package></jss:packages></jss:package_configuration></jss:policy>"#;
+        let parsed = ParsedPolicy::parse(xml).unwrap();
+        assert_eq!(parsed.name.as_deref(), Some("Office & Teams"));
+
+        let office = PackageMatch {
+            id: None,
+            package_name: "Microsoft Office",
+            file_name: "Microsoft Office 2024.pkg",
+        };
+        assert!(parsed.references(&office));
+
+        // A name that is only a substring of the entry's must not match.
+        let substring = PackageMatch {
+            id: None,
+            package_name: "Office",
+            file_name: "Office.pkg",
+        };
+        assert!(!parsed.references(&substring));
+
+        let target = PackageReference {
+            id: "9",
+            name: "Microsoft Office",
+        };
+        let update =
+            rewrite_package_configuration(xml, &[], &["Microsoft Office 2024.pkg"], &target)
+                .unwrap()
+                .unwrap();
+        assert!(update.contains("<jss:name>Microsoft Office</jss:name>"));
+    }
 }
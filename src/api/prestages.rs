@@ -0,0 +1,37 @@
+use anyhow::Result;
+
+use crate::api::client::JamfClient;
+use crate::api::error::JamfError;
+use crate::models::prestage::{ComputerPrestage, ComputerPrestageSearchResponse};
+
+const PRESTAGE_PAGE_SIZE: usize = 100;
+
+impl JamfClient {
+    /// Fetch every computer prestage enrollment, one page at a time. Returns
+    /// None when the endpoint is missing or not permitted.
+    pub async fn list_computer_prestages(&self) -> Result<Option<Vec<ComputerPrestage>>> {
+        let mut prestages = Vec::new();
+        let mut page = 0;
+
+        loop {
+            let path = format!(
+                "/api/v3/computer-prestages?page={}&page-size={}&sort=id%3Aasc",
+                page, PRESTAGE_PAGE_SIZE
+            );
+            let Some(payload) = self.get_optional_json(&path).await? else {
+                return Ok(None);
+            };
+            let search: ComputerPrestageSearchResponse = serde_json::from_value(payload)
+                .map_err(|e| JamfError::schema("Failed to parse computer prestage list", e))?;
+
+            let received = search.results.len();
+            prestages.extend(search.results);
+            if received == 0 || prestages.len() as i64 >= search.total_count {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(Some(prestages))
+    }
+}
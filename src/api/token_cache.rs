@@ -0,0 +1,126 @@
+//! Cross-process cache for OAuth access tokens.
+//!
+//! Many short-lived invocations (e.g. a shell loop uploading one package per
+//! process) would otherwise each hit the token endpoint. With the cache, they
+//! share one valid token, and an exclusive file lock makes sure only one
+//! process refreshes it at a time. Only the short-lived token and its expiry
+//! are written — never the client secret.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct CachedToken {
+    access_token: String,
+    /// Expiry as seconds since the Unix epoch.
+    expires_at: u64,
+}
+
+/// Location of the cached token for one Jamf instance + API client.
+pub struct TokenCache {
+    path: PathBuf,
+}
+
+/// An exclusively locked cache file. The lock is released when this is dropped.
+pub struct LockedTokenCache {
+    file: File,
+}
+
+impl TokenCache {
+    pub fn new(base_url: &str, client_id: &str) -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .context("Cannot determine a cache directory for the token cache")?
+            .join("jamf-package-updater");
+        let key = format!("{:x}", Md5::digest(format!("{}\n{}", base_url, client_id)));
+        Ok(Self {
+            path: dir.join(format!("token-{}.json", key)),
+        })
+    }
+
+    /// Delete the cached token. Returns whether there was one.
+    pub fn remove(&self) -> Result<bool> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to remove token cache {}", self.path.display())),
+        }
+    }
+
+    /// Open the cache file and take an exclusive lock on it, waiting for any
+    /// other process that is currently refreshing.
+    pub async fn lock(&self) -> Result<LockedTokenCache> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || -> Result<LockedTokenCache> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).with_context(|| {
+                    format!("Failed to create token cache directory {}", dir.display())
+                })?;
+            }
+
+            let mut options = OpenOptions::new();
+            options.read(true).write(true).create(true).truncate(false);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let file = options
+                .open(&path)
+                .with_context(|| format!("Failed to open token cache {}", path.display()))?;
+            file.lock()
+                .with_context(|| format!("Failed to lock token cache {}", path.display()))?;
+            Ok(LockedTokenCache { file })
+        })
+        .await
+        .context("Token cache lock task failed")?
+    }
+}
+
+impl LockedTokenCache {
+    /// Return the cached token if it stays valid for at least `margin`.
+    pub fn read_valid(&mut self, margin: Duration) -> Option<(String, Instant)> {
+        let mut contents = String::new();
+        self.file.seek(SeekFrom::Start(0)).ok()?;
+        self.file.read_to_string(&mut contents).ok()?;
+        let cached: CachedToken = serde_json::from_str(&contents).ok()?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let remaining = Duration::from_secs(cached.expires_at.checked_sub(now)?);
+        if remaining <= margin {
+            return None;
+        }
+        Some((cached.access_token, Instant::now() + remaining))
+    }
+
+    /// Replace the cached token.
+    pub fn write(&mut self, access_token: &str, expires_at: Instant) -> Result<()> {
+        let remaining = expires_at.saturating_duration_since(Instant::now());
+        let expires_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("System clock is before the Unix epoch")?
+            + remaining;
+        let cached = CachedToken {
+            access_token: access_token.to_string(),
+            expires_at: expires_at.as_secs(),
+        };
+
+        let json = serde_json::to_string(&cached).context("Failed to serialize token cache")?;
+        self.file
+            .set_len(0)
+            .context("Failed to truncate token cache")?;
+        self.file
+            .seek(SeekFrom::Start(0))
+            .context("Failed to rewind token cache")?;
+        self.file
+            .write_all(json.as_bytes())
+            .context("Failed to write token cache")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! `update --autopkg-receipt`: take the package to upload from an AutoPkg run,
+//! so this tool can stand in for JamfUploader processors in recipes.
+//!
+//! Three plist shapes are understood:
+//!
+//! - a recipe receipt (`~/Library/AutoPkg/Cache/<recipe>/receipts/*.plist`),
+//!   an array of processor steps whose `Input`/`Output` dicts carry the
+//!   recipe's variables;
+//! - a run report (`autopkg run --report-plist`), whose
+//!   `pkg_creator_summary_result` lists each package built;
+//! - a plain dict of variables, as a post-processor would dump its `env`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use plist::{Dictionary, Value};
+
+use crate::cli::UpdateArgs;
+use crate::output::status;
+
+/// What AutoPkg says to upload.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AutoPkgResult {
+    /// `pkg_path` with its `version`: the built package(s). A report may list
+    /// several, each with its own version.
+    pub packages: Vec<(PathBuf, Option<String>)>,
+    /// `NAME`, the recipe's product name.
+    pub name: Option<String>,
+}
+
+/// Read the receipt named by `--autopkg-receipt` and fill in the update: the
+/// package path, the package name (`NAME`, unless `--name` or
+/// `--name-template` is given) and the version `--record-version` writes.
+pub fn apply(args: &mut UpdateArgs) -> Result<()> {
+    let Some(receipt) = args.autopkg_receipt.clone() else {
+        return Ok(());
+    };
+    let result = read(&receipt)?;
+    for (path, version) in &result.packages {
+        status!(
+            "AutoPkg: {} (version {})",
+            path.display(),
+            version.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    if args.name.is_none() && args.name_template.is_none() && result.packages.len() == 1 {
+        args.name = result.name;
+    }
+    args.paths = result
+        .packages
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect();
+    args.autopkg_versions = result.packages;
+    Ok(())
+}
+
+/// Parse a receipt, report or variables plist.
+pub fn read(path: &Path) -> Result<AutoPkgResult> {
+    let value = Value::from_file(path)
+        .with_context(|| format!("Failed to read AutoPkg plist {}", path.display()))?;
+    let result = parse(&value);
+    if result.packages.is_empty() {
+        bail!(
+            "{} names no pkg_path: the recipe built no package (nothing new was downloaded, \
+             or it has no PkgCreator step)",
+            path.display()
+        );
+    }
+    Ok(result)
+}
+
+/// A recipe's variables as they stand after the steps read so far.
+#[derive(Default)]
+struct Vars {
+    pkg_path: Option<String>,
+    version: Option<String>,
+    name: Option<String>,
+}
+
+impl Vars {
+    fn into_result(self) -> AutoPkgResult {
+        AutoPkgResult {
+            packages: self
+                .pkg_path
+                .map(|path| (path.into(), self.version))
+                .into_iter()
+                .collect(),
+            name: self.name,
+        }
+    }
+}
+
+fn parse(value: &Value) -> AutoPkgResult {
+    match value {
+        // A receipt: later steps override earlier ones, as in the recipe's env.
+        Value::Array(steps) => {
+            let mut vars = Vars::default();
+            for step in steps.iter().filter_map(Value::as_dictionary) {
+                collect(step, &mut vars);
+                for section in ["Input", "Output"] {
+                    if let Some(dict) = step.get(section).and_then(Value::as_dictionary) {
+                        collect(dict, &mut vars);
+                    }
+                }
+            }
+            vars.into_result()
+        }
+        Value::Dictionary(dict) => match dict.get("summary_results") {
+            Some(summary) => {
+                let rows = summary
+                    .as_dictionary()
+                    .and_then(|s| s.get("pkg_creator_summary_result"))
+                    .and_then(Value::as_dictionary)
+                    .and_then(|r| r.get("data_rows"))
+                    .and_then(Value::as_array);
+                let packages = rows
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_dictionary)
+                    .filter_map(|row| {
+                        let path = string(row, "pkg_path")?;
+                        Some((path.into(), string(row, "version")))
+                    })
+                    .collect();
+                AutoPkgResult {
+                    packages,
+                    name: None,
+                }
+            }
+            None => {
+                let mut vars = Vars::default();
+                collect(dict, &mut vars);
+                vars.into_result()
+            }
+        },
+        _ => AutoPkgResult::default(),
+    }
+}
+
+fn collect(dict: &Dictionary, vars: &mut Vars) {
+    if let Some(path) = string(dict, "pkg_path") {
+        vars.pkg_path = Some(path);
+    }
+    if let Some(version) = string(dict, "version") {
+        vars.version = Some(version);
+    }
+    if let Some(name) = string(dict, "NAME") {
+        vars.name = Some(name);
+    }
+}
+
+fn string(vars: &Dictionary, key: &str) -> Option<String> {
+    vars.get(key)
+        .and_then(Value::as_string)
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_xml(xml: &str) -> AutoPkgResult {
+        parse(&Value::from_reader_xml(xml.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn reads_receipts_and_reports() {
+        let receipt = parse_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><array>
+  <dict><key>Processor</key><string>URLDownloader</string>
+    <key>Input</key><dict><key>NAME</key><string>Firefox</string></dict>
+    <key>Output</key><dict><key>pathname</key><string>/tmp/Firefox.dmg</string></dict></dict>
+  <dict><key>Processor</key><string>PkgCreator</string>
+    <key>Input</key><dict><key>version</key><string>120.0</string></dict>
+    <key>Output</key><dict><key>pkg_path</key><string>/tmp/Firefox-120.0.pkg</string></dict></dict>
+  <dict><key>RECIPE_PATH</key><string>Firefox.pkg.recipe</string></dict>
+</array></plist>"#,
+        );
+        assert_eq!(
+            receipt,
+            AutoPkgResult {
+                packages: vec![("/tmp/Firefox-120.0.pkg".into(), Some("120.0".to_string()))],
+                name: Some("Firefox".to_string()),
+            }
+        );
+
+        let report = parse_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+  <key>failures</key><array/>
+  <key>summary_results</key><dict>
+    <key>pkg_creator_summary_result</key><dict>
+      <key>data_rows</key><array>
+        <dict><key>pkg_path</key><string>/tmp/Zoom-5.17.pkg</string><key>version</key><string>5.17</string></dict>
+        <dict><key>pkg_path</key><string>/tmp/Slack-4.36.pkg</string><key>version</key><string>4.36</string></dict>
+      </array>
+    </dict>
+  </dict>
+</dict></plist>"#,
+        );
+        assert_eq!(
+            report.packages,
+            vec![
+                ("/tmp/Zoom-5.17.pkg".into(), Some("5.17".to_string())),
+                ("/tmp/Slack-4.36.pkg".into(), Some("4.36".to_string())),
+            ]
+        );
+        assert_eq!(report.name, None);
+    }
+}
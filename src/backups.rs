@@ -0,0 +1,213 @@
+//! The last-known-good copy of a package, kept for `rollback`.
+//!
+//! `update --keep-previous` downloads the JCDS file a package is about to
+//! lose and stores it with the package record, one backup per package:
+//!
+//! ```text
+//! <root>/<instance host>/<package id>/backup.json
+//! <root>/<instance host>/<package id>/<file name>
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageDigestSnapshot;
+use crate::models::package::Package;
+use crate::progress::TransferProgress;
+
+const MANIFEST: &str = "backup.json";
+
+/// A saved package version: the record as it was and the digest Jamf
+/// reported for its file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub package: Package,
+    pub digest: Option<PackageDigestSnapshot>,
+    /// Unix time the backup was taken.
+    pub saved_at: u64,
+}
+
+impl Backup {
+    /// The saved installer, next to the manifest in `dir`. Only the last
+    /// component of the record's file name is used, so a name from the
+    /// server can never point outside `dir`.
+    pub fn file_in(&self, dir: &Path) -> Result<PathBuf> {
+        match Path::new(&self.package.file_name).file_name() {
+            Some(name) => Ok(dir.join(name)),
+            None => bail!(
+                "Package '{}' has no usable file name ('{}') to back up",
+                self.package.package_name,
+                self.package.file_name
+            ),
+        }
+    }
+}
+
+/// Where backups live: `root`, or the user data directory by default.
+pub fn backup_root(root: Option<&Path>) -> Result<PathBuf> {
+    match root {
+        Some(root) => Ok(root.to_path_buf()),
+        None => Ok(dirs::data_local_dir()
+            .context("Cannot determine a data directory for package backups; pass --backup-dir")?
+            .join("jamf-package-updater")
+            .join("backups")),
+    }
+}
+
+/// The backup directory of package `id` on the instance at `base_url`.
+pub fn package_dir(root: &Path, base_url: &str, id: &str) -> PathBuf {
+    root.join(instance_dir_name(base_url)).join(id)
+}
+
+/// Download `package`'s current JCDS file and record it as the package's
+/// backup, replacing any earlier one only once the new copy is complete.
+pub async fn save(
+    client: &JamfClient,
+    root: &Path,
+    package: &Package,
+    digest: Option<&PackageDigestSnapshot>,
+    quiet: bool,
+) -> Result<PathBuf> {
+    let dir = package_dir(root, &client.base_url, &package.id);
+    let staging = dir.with_extension("partial");
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    tokio::fs::create_dir_all(&staging)
+        .await
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let backup = Backup {
+        package: package.clone(),
+        digest: digest.cloned(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    };
+    client
+        .download_jcds_file(
+            &package.file_name,
+            &backup.file_in(&staging)?,
+            TransferProgress::download(quiet).as_ref(),
+        )
+        .await?;
+    let manifest = serde_json::to_vec_pretty(&backup).context("Failed to serialize backup")?;
+    tokio::fs::write(staging.join(MANIFEST), manifest)
+        .await
+        .with_context(|| format!("Failed to write {}", staging.join(MANIFEST).display()))?;
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::rename(&staging, &dir)
+        .await
+        .with_context(|| format!("Failed to move backup into place at {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// The backup saved in `dir`, if there is one.
+pub fn load(dir: &Path) -> Result<Option<Backup>> {
+    let path = dir.join(MANIFEST);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let backup = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(backup))
+}
+
+/// A directory name for an instance URL: everything after the scheme, with
+/// characters unusual in a file name replaced, so instances never share
+/// backups.
+fn instance_dir_name(base_url: &str) -> String {
+    let host = base_url
+        .split_once("://")
+        .map_or(base_url, |(_, rest)| rest)
+        .trim_end_matches('/');
+    host.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::{self, MockState};
+
+    fn package(file_name: &str) -> Package {
+        serde_json::from_value(serde_json::json!({
+            "id": "7",
+            "packageName": "App",
+            "fileName": file_name,
+            "categoryId": "-1",
+            "priority": 10,
+            "fillUserTemplate": false,
+            "fillExistingUsers": false,
+            "rebootRequired": false,
+            "osInstall": false,
+            "suppressUpdates": false,
+            "suppressFromDock": false,
+            "suppressEula": false,
+            "suppressRegistration": false,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn saved_backup_loads_back_and_stays_in_its_directory() {
+        let state = Arc::new(MockState {
+            jcds_file: Some("old payload"),
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+        let root = std::env::temp_dir().join(format!("jpu-backups-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let dir = save(&client, &root, &package("../../App-1.0.pkg"), None, true)
+            .await
+            .unwrap();
+        let backup = load(&dir).unwrap().unwrap();
+        let file = backup.file_in(&dir).unwrap();
+        let contents = std::fs::read_to_string(&file).unwrap();
+        let escaped = root.parent().unwrap().join("App-1.0.pkg").exists();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(dir, package_dir(&root, &client.base_url, "7"));
+        assert_eq!(file, dir.join("App-1.0.pkg"));
+        assert_eq!(contents, "old payload");
+        assert_eq!(backup.package.package_name, "App");
+        assert!(!escaped);
+        assert!(
+            Backup {
+                package: package(".."),
+                digest: None,
+                saved_at: 0
+            }
+            .file_in(&dir)
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn instance_dir_name_keeps_host_and_port_only() {
+        assert_eq!(
+            instance_dir_name("https://example.jamfcloud.com/"),
+            "example.jamfcloud.com"
+        );
+        assert_eq!(
+            instance_dir_name("https://jss.corp.example:8443/jamf"),
+            "jss.corp.example_8443_jamf"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, bail};
+use md5::{Digest, Md5};
+use sha2::{Sha256, Sha512};
+use sha3::Sha3_512;
+use tokio::io::AsyncReadExt;
+
+/// Digest algorithms Jamf reports for package payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashType {
+    Md5,
+    Sha256,
+    Sha512,
+    Sha3_512,
+}
+
+impl HashType {
+    /// Map Jamf's `hashType` label (`MD5`, `SHA_512`, `SHA3_512`, ...) to an
+    /// algorithm we can compute. None for anything else.
+    pub fn from_jamf(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().replace(['-', '_'], "").as_str() {
+            "MD5" => Some(HashType::Md5),
+            "SHA256" | "SHA2256" => Some(HashType::Sha256),
+            "SHA512" | "SHA2512" => Some(HashType::Sha512),
+            "SHA3512" => Some(HashType::Sha3_512),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HashType::Md5 => "MD5",
+            HashType::Sha256 => "SHA-256",
+            HashType::Sha512 => "SHA-512",
+            HashType::Sha3_512 => "SHA3-512",
+        })
+    }
+}
+
+/// Compute the lowercase hex digest of a file, streaming it in small chunks.
+pub async fn compute_file_digest(path: &Path, hash_type: HashType) -> Result<String> {
+    match hash_type {
+        HashType::Md5 => hash_file::<Md5>(path, hash_type).await,
+        HashType::Sha256 => hash_file::<Sha256>(path, hash_type).await,
+        HashType::Sha512 => hash_file::<Sha512>(path, hash_type).await,
+        HashType::Sha3_512 => hash_file::<Sha3_512>(path, hash_type).await,
+    }
+}
+
+/// Compute the lowercase hex MD5 of a file.
+pub async fn compute_file_md5(path: &Path) -> Result<String> {
+    compute_file_digest(path, HashType::Md5).await
+}
+
+async fn hash_file<D: Digest>(path: &Path, hash_type: HashType) -> Result<String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open file for {}: {}", hash_type, path.display()))?;
+    let mut hasher = D::new();
+    let mut buf = [0_u8; 8192];
+
+    loop {
+        let n = file.read(&mut buf).await.with_context(|| {
+            format!("Failed reading file for {}: {}", hash_type, path.display())
+        })?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hex(&hasher.finalize()))
+}
+
+/// A file's digest in every algorithm Jamf may report, so whichever one it
+/// computes after an upload can be checked without reading the file again.
+#[derive(Debug, Clone)]
+pub struct FileDigests {
+    pub md5: String,
+    pub sha256: String,
+    pub sha512: String,
+    pub sha3_512: String,
+}
+
+impl FileDigests {
+    pub fn of(&self, hash_type: HashType) -> &str {
+        match hash_type {
+            HashType::Md5 => &self.md5,
+            HashType::Sha256 => &self.sha256,
+            HashType::Sha512 => &self.sha512,
+            HashType::Sha3_512 => &self.sha3_512,
+        }
+    }
+}
+
+/// Compute [`FileDigests`] for a file in a single streaming pass.
+pub async fn compute_file_digests(path: &Path) -> Result<FileDigests> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open file for hashing: {}", path.display()))?;
+    let mut md5 = Md5::new();
+    let mut sha256 = Sha256::new();
+    let mut sha512 = Sha512::new();
+    let mut sha3_512 = Sha3_512::new();
+    let mut buf = vec![0_u8; 64 * 1024];
+
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .with_context(|| format!("Failed reading file for hashing: {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        md5.update(&buf[..n]);
+        sha256.update(&buf[..n]);
+        sha512.update(&buf[..n]);
+        sha3_512.update(&buf[..n]);
+    }
+
+    Ok(FileDigests {
+        md5: hex(&md5.finalize()),
+        sha256: hex(&sha256.finalize()),
+        sha512: hex(&sha512.finalize()),
+        sha3_512: hex(&sha3_512.finalize()),
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Checksum files kept next to an installer by `update --write-checksums`,
+/// and the extension of each.
+pub const CHECKSUM_FILES: [(HashType, &str); 2] =
+    [(HashType::Sha256, "sha256"), (HashType::Md5, "md5")];
+
+/// A `<file>.sha256` or `<file>.md5` found next to an installer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumFile {
+    pub path: PathBuf,
+    pub hash_type: HashType,
+    /// Lowercase hex.
+    pub digest: String,
+    /// When the checksum file was last written.
+    pub modified: Option<SystemTime>,
+}
+
+/// `<file>.<extension>`.
+pub fn checksum_file_path(file: &Path, extension: &str) -> PathBuf {
+    let mut path = OsString::from(file.as_os_str());
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// The checksum files present next to `file`.
+pub fn read_checksum_files(file: &Path) -> Result<Vec<ChecksumFile>> {
+    let mut found = Vec::new();
+    for (hash_type, extension) in CHECKSUM_FILES {
+        let path = checksum_file_path(file, extension);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let Some(digest) = parse_checksum_file(&contents, hash_type) else {
+            bail!("{} does not hold a {} checksum", path.display(), hash_type);
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        found.push(ChecksumFile {
+            path,
+            hash_type,
+            digest,
+            modified,
+        });
+    }
+    Ok(found)
+}
+
+/// Write a checksum file per [`CHECKSUM_FILES`] next to `file`, in the
+/// `sha256sum`/`md5sum` format (`<hex>  <file name>`). One that already
+/// holds the right digest is left alone. Returns the files written.
+pub fn write_checksum_files(file: &Path, digests: &FileDigests) -> Result<Vec<PathBuf>> {
+    let file_name = file
+        .file_name()
+        .context("Invalid file path")?
+        .to_string_lossy();
+    let existing = read_checksum_files(file).unwrap_or_default();
+    let mut written = Vec::new();
+    for (hash_type, extension) in CHECKSUM_FILES {
+        let digest = digests.of(hash_type);
+        if existing
+            .iter()
+            .any(|c| c.hash_type == hash_type && c.digest == digest)
+        {
+            continue;
+        }
+        let path = checksum_file_path(file, extension);
+        std::fs::write(&path, format!("{}  {}\n", digest, file_name))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The digest in a checksum file: a bare hex digest, a `sha256sum`-style
+/// `<hex>  <name>` line or a BSD-style `SHA256 (<name>) = <hex>` line.
+fn parse_checksum_file(contents: &str, hash_type: HashType) -> Option<String> {
+    let len = match hash_type {
+        HashType::Md5 => 32,
+        HashType::Sha256 => 64,
+        HashType::Sha512 | HashType::Sha3_512 => 128,
+    };
+    let line = contents.lines().find(|l| !l.trim().is_empty())?;
+    let mut words = line.split_whitespace();
+    [words.next(), words.last()]
+        .into_iter()
+        .flatten()
+        .find(|w| w.len() == len && w.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn computes_each_supported_digest() {
+        let path = std::env::temp_dir().join(format!("checksum-test-{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+
+        let md5 = compute_file_digest(&path, HashType::Md5).await.unwrap();
+        let sha256 = compute_file_digest(&path, HashType::Sha256).await.unwrap();
+        let sha3 = compute_file_digest(&path, HashType::Sha3_512)
+            .await
+            .unwrap();
+        let all = compute_file_digests(&path).await.unwrap();
+        let sha512 = compute_file_digest(&path, HashType::Sha512).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(all.of(HashType::Md5), md5);
+        assert_eq!(all.of(HashType::Sha256), sha256);
+        assert_eq!(all.of(HashType::Sha512), sha512);
+        assert_eq!(all.of(HashType::Sha3_512), sha3);
+
+        assert_eq!(md5, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(
+            sha3.starts_with("b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e")
+        );
+        assert_eq!(HashType::from_jamf("SHA3_512"), Some(HashType::Sha3_512));
+        assert_eq!(HashType::from_jamf("sha-512"), Some(HashType::Sha512));
+    }
+
+    #[tokio::test]
+    async fn writes_and_reads_checksum_files() {
+        let dir = std::env::temp_dir().join(format!("checksum-files-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("App.pkg");
+        std::fs::write(&path, b"abc").unwrap();
+        // An artifact store's BSD-style MD5 file, already correct.
+        std::fs::write(
+            checksum_file_path(&path, "md5"),
+            "MD5 (App.pkg) = 900150983CD24FB0D6963F7D28E17F72\n",
+        )
+        .unwrap();
+
+        let digests = compute_file_digests(&path).await.unwrap();
+        let written = write_checksum_files(&path, &digests).unwrap();
+        let sha256 = std::fs::read_to_string(checksum_file_path(&path, "sha256")).unwrap();
+        let found = read_checksum_files(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, vec![checksum_file_path(&path, "sha256")]);
+        assert_eq!(sha256, format!("{}  App.pkg\n", digests.sha256));
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|c| c.digest == digests.of(c.hash_type)));
+    }
+}
@@ -1,48 +1,899 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::api::client::{AuthMode, ConnectOptions, RetryPolicy, Timeouts};
+use crate::api::distribution::DistributionTarget;
+use crate::api::jcds_upload::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use crate::api::packages::PackageTarget;
+use crate::api::policies::DEFAULT_SCAN_CONCURRENCY;
+use crate::credentials::{Credentials, DEFAULT_PROFILE};
+use crate::files;
+use crate::models::package::PackageFlagOverrides;
 
 #[derive(Parser)]
 #[command(name = "jamf-package-updater")]
 #[command(about = "Simplify package updates in Jamf Pro")]
 pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// Options shared by every command that talks to Jamf Pro.
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Reuse an access token cached on disk by earlier runs, refreshing it
+    /// under a file lock so concurrent processes share a single refresh.
+    /// Only the short-lived token is cached, never the client secret.
+    #[arg(long, global = true)]
+    pub prefer_cached_token: bool,
+
+    /// API path used to trigger the JCDS inventory refresh, for Jamf versions
+    /// where it moved. By default the known paths are tried in turn.
+    #[arg(long, global = true, env = "JAMF_INVENTORY_REFRESH_PATH")]
+    pub inventory_refresh_path: Option<String>,
+
+    /// Credential profile to use, for managing several Jamf instances. Each
+    /// profile has its own keyring entries (`<profile>.client_id`, ...) and
+    /// environment variables (`JAMF_<PROFILE>_CLIENT_ID`, ...).
+    #[arg(long, global = true, env = "JAMF_PROFILE", default_value = DEFAULT_PROFILE)]
+    pub profile: String,
+
+    /// Config file holding credentials under `[profiles.<name>]`, consulted
+    /// after environment variables and before the keyring. Defaults to
+    /// `config.toml` in the platform config directory (on Linux
+    /// `~/.config/jamf-package-updater/`), used when present.
+    #[arg(long, global = true, env = "JAMF_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Output format. With `json`, every command prints a single JSON
+    /// document on stdout (its result, or `{"error": ...}` if it failed) and
+    /// progress messages go to stderr.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Trust the root certificate(s) in this PEM file in addition to the
+    /// system roots, e.g. for a TLS-inspecting corporate proxy. Proxies
+    /// themselves are taken from HTTPS_PROXY / NO_PROXY.
+    #[arg(long, global = true, env = "JAMF_CA_CERT", value_name = "PATH")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Retries for an API call that fails with a connection error or a
+    /// transient HTTP status (408, 429, 5xx gateway errors). Only idempotent
+    /// calls are retried; 0 disables retries.
+    #[arg(long, global = true, default_value_t = 3, value_name = "N")]
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for each further one (capped at
+    /// 30s), e.g. "1s", "500ms".
+    #[arg(long, global = true, default_value = "1s", value_parser = parse_duration)]
+    pub retry_delay: Duration,
+
+    /// Time allowed to connect to Jamf Pro or JCDS storage.
+    #[arg(long, global = true, default_value = "15s", value_parser = parse_positive_duration)]
+    pub connect_timeout: Duration,
+
+    /// Time allowed for an API call, from sending it to reading the whole
+    /// response. A download fails only if it stalls this long.
+    #[arg(long, global = true, default_value = "60s", value_parser = parse_positive_duration)]
+    pub request_timeout: Duration,
+
+    /// Slowest upload speed, per second, before an upload is abandoned: it
+    /// may take --request-timeout plus its size at this rate (about 11 hours
+    /// for 10GiB at the default).
+    #[arg(
+        long,
+        global = true,
+        default_value = "256KiB",
+        value_name = "SIZE",
+        value_parser = parse_upload_rate
+    )]
+    pub min_upload_rate: u64,
+
+    /// Number of policies fetched at once while scanning for package
+    /// references (`update`, `delete`), and of package digests fetched at
+    /// once by `list`. Lower it if Jamf rate-limits the API.
+    #[arg(
+        long,
+        global = true,
+        visible_alias = "concurrency",
+        value_name = "N",
+        default_value_t = DEFAULT_SCAN_CONCURRENCY,
+        value_parser = parse_concurrency
+    )]
+    pub scan_concurrency: usize,
+
+    /// Only print errors, warnings and final results: no progress lines or
+    /// spinners.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Log request-level detail to stderr (URLs, HTTP statuses, retries,
+    /// digest polling). Repeat for more (-vv). RUST_LOG overrides this.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Append a timestamped log of the run to this file: every status line
+    /// plus request-level detail (as with -v; -vv adds trace output).
+    #[arg(long, global = true, env = "JAMF_LOG_FILE", value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// JSON-lines file `update` records each run in and `history` reads.
+    /// Defaults to `history.jsonl` in the platform data directory (on Linux
+    /// `~/.local/share/jamf-package-updater/`).
+    #[arg(long, global = true, env = "JAMF_HISTORY_FILE", value_name = "PATH")]
+    pub history_file: Option<PathBuf>,
+}
+
+impl GlobalArgs {
+    /// Whether results are rendered as JSON (`--output json`).
+    pub fn json(&self) -> bool {
+        self.output == OutputFormat::Json
+    }
+
+    pub fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions {
+            prefer_cached_token: self.prefer_cached_token,
+            inventory_refresh_path: self.inventory_refresh_path.clone(),
+            ca_cert: self.ca_cert.clone(),
+            retry: RetryPolicy {
+                max_attempts: self.retries.saturating_add(1),
+                base_delay: self.retry_delay,
+            },
+            auth_mode: AuthMode::default(),
+            timeouts: Timeouts {
+                connect: self.connect_timeout,
+                request: self.request_timeout,
+                min_upload_rate: self.min_upload_rate,
+            },
+        }
+    }
+
+    /// [`connect_options`](Self::connect_options) for logging in with `creds`.
+    pub fn connect_options_for(&self, creds: &Credentials) -> ConnectOptions {
+        ConnectOptions {
+            auth_mode: creds.auth_mode,
+            ..self.connect_options()
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Store Jamf Pro API credentials
+    /// Verify and store Jamf Pro API credentials. Nothing is stored unless
+    /// Jamf accepts them, so this is also the way to rotate a secret.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Auth {
-        /// Jamf Pro API client ID
+        #[command(subcommand)]
+        action: Option<AuthAction>,
+
+        /// Jamf Pro API client ID (the username with --auth-mode basic)
+        #[arg(long, visible_alias = "username", required = true)]
+        client_id: Option<String>,
+
+        /// Jamf Pro API client secret (the password with --auth-mode basic)
+        #[arg(long, visible_alias = "password", required = true)]
+        client_secret: Option<String>,
+
+        /// Jamf Pro instance URL (e.g. https://example.jamfcloud.com).
+        /// Defaults to `url` under `[defaults]` in the config file.
         #[arg(long)]
-        client_id: String,
+        url: Option<String>,
+
+        /// `basic` logs in with a Jamf Pro username and password instead of
+        /// an API client, for instances without API clients enabled.
+        #[arg(long, value_enum, default_value_t = AuthMode::OAuth)]
+        auth_mode: AuthMode,
+
+        /// Where to store the credentials. `file` writes them to the config
+        /// file (owner-only permissions) for machines without a keyring;
+        /// `encrypted-file` does the same with the secret encrypted.
+        #[arg(
+            long,
+            visible_alias = "credential-store",
+            value_enum,
+            default_value_t = CredentialStore::Keyring
+        )]
+        store: CredentialStore,
+    },
+
+    /// Remove stored credentials (and any cached token) for the profile
+    Logout,
+
+    /// Check that the stored credentials authenticate and can read packages
+    Whoami,
+
+    /// Update packages in Jamf Pro and reassign them to all policies that used them
+    Update(Box<UpdateArgs>),
+
+    /// Delete a package record and its JCDS file, refusing while any policy
+    /// still references the package (unless --force)
+    Delete {
+        #[command(flatten)]
+        target: PackageSelector,
 
-        /// Jamf Pro API client secret
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Delete even if policies still reference the package
         #[arg(long)]
-        client_secret: String,
+        force: bool,
 
-        /// Jamf Pro instance URL (e.g. https://example.jamfcloud.com)
+        /// Leave the package's JCDS file in place; only the record is deleted
         #[arg(long)]
-        url: String,
+        keep_file: bool,
     },
 
-    /// Update a package in Jamf Pro and reassign it to all policies that used it
-    Update {
-        /// Path to a .pkg or .dmg file
-        path: PathBuf,
+    /// Show one package's full record, digest and JCDS file, what references
+    /// it, and whether a local file matches it
+    Info {
+        #[command(flatten)]
+        target: PackageSelector,
+
+        /// Local installer to compare with the package's digest
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
 
-        /// Package name to match in Jamf Pro (defaults to file stem)
+        /// Skip looking up the policies, patch titles and prestage enrollments
+        /// that reference the package (the policy scan reads every policy)
         #[arg(long)]
-        name: Option<String>,
+        no_references: bool,
+
+        /// Print the result as JSON (same as `--output json`)
+        #[arg(long)]
+        json: bool,
+    },
 
-        /// Package priority in Jamf Pro (0–20). Overrides the existing value
-        /// for updates and the default (3) for new packages.
+    /// List packages with their file name, category, priority and digest
+    List {
+        /// Only show packages whose name contains this text (case-insensitive)
         #[arg(long)]
-        priority: Option<i32>,
+        filter: Option<String>,
 
-        /// Maximum seconds to wait for Jamf digest metadata to update after upload.
-        #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
-        digest_wait_seconds: u64,
+        /// Print the list as JSON (same as `--output json`)
+        #[arg(long)]
+        json: bool,
     },
+
+    /// List all policies (ID and name)
+    Policies {
+        /// Print the list as JSON (same as `--output json`)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Detect drift between a package record and its JCDS object (fileName
+    /// pointing at the wrong object, stale digest) and optionally fix it
+    Reconcile {
+        #[command(flatten)]
+        target: PackageSelector,
+
+        /// Correct the record's fileName and trigger an inventory refresh
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Restore the package file and record saved by `update --keep-previous`
+    Rollback {
+        #[command(flatten)]
+        target: PackageSelector,
+
+        /// Directory the backup was saved in (default: the user data directory)
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Download a package's file from JCDS and verify it against the digest
+    /// Jamf stores for it
+    Download {
+        #[command(flatten)]
+        target: PackageSelector,
+
+        /// Where to save the file: a file path, or an existing directory to
+        /// save it in under its Jamf file name (default: current directory)
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+
+        /// Overwrite the destination if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check local installers against the digests Jamf stores for their
+    /// packages, changing nothing. Exits non-zero unless every file matches.
+    Verify(VerifyArgs),
+
+    /// Show past `update` runs recorded on this machine: when, by whom, the
+    /// digests before and after, affected policies and the result
+    History {
+        /// Only show this package (case-insensitive)
+        name: Option<String>,
+
+        /// Show at most this many of the most recent entries (0 for all)
+        #[arg(long, default_value_t = 20, value_name = "N")]
+        limit: usize,
+
+        /// Print the entries as JSON (same as `--output json`)
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuthAction {
+    /// Check that the stored credentials authenticate, how long the token is
+    /// valid, and which package and policy privileges the API client has
+    #[command(visible_alias = "verify")]
+    Test,
+}
+
+/// The package a command acts on: a positional name (or bare ID), or `--id`.
+#[derive(Args)]
+pub struct PackageSelector {
+    /// Package name in Jamf Pro, or its ID
+    #[arg(value_name = "NAME_OR_ID", required_unless_present = "id")]
+    pub package: Option<String>,
+
+    /// Use the package record with this Jamf ID instead of searching by name,
+    /// even if a package is named like the ID
+    #[arg(long, value_name = "ID", conflicts_with = "package")]
+    pub id: Option<String>,
+}
+
+impl PackageSelector {
+    pub fn target(&self) -> PackageTarget<'_> {
+        match (&self.id, &self.package) {
+            (Some(id), _) => PackageTarget::Id(id),
+            (None, package) => PackageTarget::NameOrId(package.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// .pkg, .dmg, .mpkg or .zip files, .app or bundle packages (compared
+    /// as the zip `update` uploads), directories of them, or a TOML manifest
+    /// (`.toml`) listing files with the package each should match
+    #[arg(required = true, value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Also accept files with this extension (e.g. "mobileconfig"). Repeatable.
+    #[arg(long = "allow-extension", value_name = "EXT")]
+    pub allow_extension: Vec<String>,
+
+    /// Package name to match for a single file (defaults to file stem)
+    #[arg(long, conflicts_with = "name_template")]
+    pub name: Option<String>,
+
+    /// Derive package names from file names, as for `update`
+    #[arg(long)]
+    pub name_template: Option<String>,
+
+    /// Regex with named groups for --name-template placeholders
+    #[arg(long, requires = "name_template")]
+    pub name_pattern: Option<String>,
+
+    /// Fail files whose package Jamf only reports an MD5 digest for
+    #[arg(long)]
+    pub require_strong_hash: bool,
+
+    /// Write a JUnit XML report with one test case per file
+    #[arg(long, value_name = "PATH")]
+    pub junit: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct UpdateArgs {
+    /// .pkg, .dmg, .mpkg or .zip files, .app or bundle packages (zipped
+    /// before upload), or directories of them. Several files are processed
+    /// in one session with a single inventory refresh at the end.
+    #[arg(required_unless_present = "autopkg_receipt", value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Upload the package an AutoPkg run built, read from a recipe receipt,
+    /// an `autopkg run --report-plist` report or a plist of recipe variables.
+    /// `pkg_path` is uploaded, `NAME` becomes the package name (unless --name
+    /// or --name-template is given) and `version` is what --record-version
+    /// writes.
+    #[arg(long, value_name = "PLIST", conflicts_with = "paths")]
+    pub autopkg_receipt: Option<PathBuf>,
+
+    /// Each `pkg_path` from --autopkg-receipt with its `version`.
+    #[arg(skip)]
+    pub autopkg_versions: Vec<(PathBuf, Option<String>)>,
+
+    /// Also accept files with this extension (e.g. "mobileconfig").
+    /// Repeatable. Non-standard types may not behave like installers in policies.
+    #[arg(long = "allow-extension", value_name = "EXT")]
+    pub allow_extension: Vec<String>,
+
+    /// Don't check that .pkg/.mpkg/.dmg/.zip files carry their format's magic
+    /// bytes (xar! header, koly trailer, PK header) before uploading.
+    #[arg(long)]
+    pub skip_validation: bool,
+
+    /// Package name to match in Jamf Pro (defaults to file stem)
+    #[arg(long, conflicts_with = "name_template")]
+    pub name: Option<String>,
+
+    /// Update the package record with this Jamf ID instead of searching by
+    /// name, e.g. when several packages share a name. Single file only.
+    #[arg(long, value_name = "ID", conflicts_with_all = ["name", "name_template"])]
+    pub id: Option<String>,
+
+    /// Build the package name from parts of the file name, e.g. "{app}".
+    /// Placeholders refer to named groups in --name-pattern.
+    #[arg(long)]
+    pub name_template: Option<String>,
+
+    /// Regex with named groups matched against the file stem
+    /// (default: "^(?P<app>.+?)[-_ ]v?(?P<version>\d[\w.]*)$").
+    #[arg(long, requires = "name_template")]
+    pub name_pattern: Option<String>,
+
+    /// Category name to assign (looked up in Jamf Pro). New packages
+    /// otherwise land in Unknown; existing ones keep their category.
+    #[arg(long)]
+    pub category: Option<String>,
+
+    /// Create the --category category when Jamf Pro has none by that name,
+    /// instead of failing.
+    #[arg(long, requires = "category")]
+    pub create_category: bool,
+
+    /// Package priority in Jamf Pro (0–20). Overrides the existing value
+    /// for updates and the default (3) for new packages.
+    #[arg(long)]
+    pub priority: Option<i32>,
+
+    /// Save the current XML of every policy that references the package to
+    /// this directory (as policy-<id>.xml) before anything is changed.
+    #[arg(long, value_name = "DIR")]
+    pub export_affected_policies: Option<PathBuf>,
+
+    /// Write the XML of every scanned policy to this directory, for offline
+    /// analysis with --policy-source.
+    #[arg(long, value_name = "DIR", conflicts_with = "policy_source")]
+    pub dump_policy_xml: Option<PathBuf>,
+
+    /// Scan policy XML from a directory written by --dump-policy-xml instead
+    /// of fetching it from Jamf Pro.
+    #[arg(long, value_name = "DIR")]
+    pub policy_source: Option<PathBuf>,
+
+    /// For existing packages, reset the install/suppress flags to the defaults
+    /// used for new packages instead of copying them from the old record.
+    /// Name, category and priority are still preserved unless overridden.
+    #[arg(long)]
+    pub reset_flags: bool,
+
+    #[command(flatten)]
+    pub flags: PackageFlagArgs,
+
+    /// After a successful upload, rewrite each affected policy's package
+    /// entry to reference this package's ID and name. Without it, policies
+    /// are only scanned and listed.
+    #[arg(long, conflicts_with = "policy_source")]
+    pub reassign_policies: bool,
+
+    /// Also move policies off an older package record, such as a
+    /// version-pinned "MyApp-2.3.0" when the new build gets its own record.
+    /// Its policies are scanned with this package's, and --reassign-policies
+    /// points their entries at this package. Repeatable.
+    #[arg(long, value_name = "NAME_OR_ID", requires = "reassign_policies")]
+    pub reassign_from: Vec<String>,
+
+    /// After a successful upload, flush the logs of every affected policy so
+    /// computers that already ran it run it again. Asks per policy unless
+    /// --yes is given.
+    #[arg(long, conflicts_with = "policy_source")]
+    pub flush_policies: bool,
+
+    /// Flush policies without asking (see --flush-policies).
+    #[arg(short, long, requires = "flush_policies")]
+    pub yes: bool,
+
+    /// Authenticate, look the package up, compare digests and scan policies,
+    /// but only report what would be created, updated or uploaded.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Exit with code 10 when nothing was uploaded because Jamf already has
+    /// every file, so a pipeline can tell "identical build" from "uploaded".
+    #[arg(long)]
+    pub fail_on_unchanged: bool,
+
+    /// What confirms a successful upload. By default the digest must change,
+    /// falling back to an MD5 match for content-identical rebuilds.
+    #[arg(long, value_enum)]
+    pub success_when: Option<SuccessCriterion>,
+
+    /// Maximum seconds to wait for Jamf digest metadata to update after upload.
+    #[arg(
+        long,
+        visible_alias = "digest-timeout",
+        default_value_t = 300,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub digest_wait_seconds: u64,
+
+    /// Pause between digest checks while waiting (e.g. "5s", "30s", "1m").
+    #[arg(long, default_value = "5s", value_parser = parse_positive_duration)]
+    pub digest_interval: Duration,
+
+    /// Attempts at opening the local file for upload, for network mounts that
+    /// briefly disappear.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub file_open_attempts: u32,
+
+    /// Multipart form field name for the uploaded file. Only needed for
+    /// proxies or gateways that expect something other than Jamf's default.
+    #[arg(long, default_value = "file")]
+    pub upload_field_name: String,
+
+    /// Copy the installer to a file share distribution point instead of
+    /// uploading it to JCDS: a directory of the mounted share (SMB/AFP), or
+    /// `smb://server/share/dir` to upload with `smbclient`. Jamf computes no
+    /// digest then, so the copy is verified by its MD5.
+    #[arg(
+        long,
+        env = "JAMF_DISTRIBUTION_POINT",
+        value_name = "PATH_OR_SMB_URL",
+        value_parser = DistributionTarget::parse
+    )]
+    pub distribution_point: Option<DistributionTarget>,
+
+    /// Upload straight to JCDS storage in parts of this size (e.g. `64MiB`,
+    /// 5MiB to 5GiB) instead of one request. Completed parts are remembered,
+    /// so re-running after a dropped connection resumes the upload.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_chunk_size,
+        conflicts_with = "distribution_point"
+    )]
+    pub chunk_size: Option<u64>,
+
+    /// Before replacing an existing package's file, download it and save it
+    /// with the package record so `rollback` can restore it.
+    #[arg(long, conflicts_with = "distribution_point")]
+    pub keep_previous: bool,
+
+    /// Where --keep-previous saves backups (default: the user data directory).
+    #[arg(long, value_name = "DIR", requires = "keep_previous")]
+    pub backup_dir: Option<PathBuf>,
+
+    /// After a successful run, fetch the package record from Jamf Pro and
+    /// print it on stdout as its own pretty JSON document.
+    #[arg(long)]
+    pub print_result_record: bool,
+
+    /// Fail instead of relying on MD5 when Jamf offers no SHA-2/SHA-3 digest
+    /// for the package.
+    #[arg(long)]
+    pub require_strong_hash: bool,
+
+    /// Pause between files when processing a directory (e.g. "30s", "2m",
+    /// "500ms"; a bare number is seconds).
+    #[arg(long, default_value = "0", value_parser = parse_duration)]
+    pub between_files_delay: Duration,
+
+    /// Files of a batch processed at once, sharing one authenticated session.
+    /// Each file's lines are prefixed with its name, and the summary at the
+    /// end reports every file. Progress bars and prompts are turned off.
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = parse_concurrency
+    )]
+    pub jobs: usize,
+
+    /// Only report whether each file is NEW, CHANGED, UNCHANGED or AMBIGUOUS
+    /// in Jamf Pro; nothing is uploaded or modified. Accepts a directory.
+    #[arg(long)]
+    pub compare_only: bool,
+
+    /// Read the version embedded in the installer (.pkg Distribution or
+    /// PackageInfo; .dmg app bundle on macOS) and warn when it doesn't match
+    /// the version in the package or file name.
+    #[arg(long)]
+    pub check_embedded_version: bool,
+
+    /// Write the version embedded in the installer into the package record's
+    /// info or notes field as "Version: <version> (<file name>)", replacing
+    /// the previous version line and keeping any other text.
+    #[arg(long, value_enum, value_name = "FIELD")]
+    pub record_version: Option<VersionField>,
+
+    /// Write a JUnit XML report (one test case per file) to this path for CI
+    /// test dashboards.
+    #[arg(long, value_name = "PATH")]
+    pub junit: Option<PathBuf>,
+
+    /// MD5 the build recorded for this file. The local file must match before
+    /// upload, and Jamf's digest must match it afterwards.
+    #[arg(long, value_name = "HASH", value_parser = parse_md5_hex)]
+    pub expected_md5: Option<String>,
+
+    /// SHA-512 the build recorded for this file; checked like --expected-md5.
+    #[arg(long, value_name = "HASH", value_parser = parse_sha512_hex)]
+    pub expected_sha512: Option<String>,
+
+    /// Keep `<file>.sha256` and `<file>.md5` checksum files next to each
+    /// uploaded file. Ones already there (e.g. from an artifact store) must
+    /// match the file before anything changes in Jamf, unless they are older
+    /// than it; after the upload, or when Jamf already has the file, missing
+    /// or outdated ones are written.
+    #[arg(long)]
+    pub write_checksums: bool,
+}
+
+/// Parse a human duration such as `500ms`, `30s`, `2m` or `1h`. A bare number
+/// is interpreted as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+
+    let seconds = match unit.trim() {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => Some(number),
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(3600),
+        other => {
+            return Err(format!(
+                "invalid duration unit '{}' (use ms, s, m or h)",
+                other
+            ));
+        }
+    };
+    seconds
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{}' is too large", value))
+}
+
+pub fn parse_positive_duration(value: &str) -> Result<Duration, String> {
+    match parse_duration(value)? {
+        Duration::ZERO => Err("duration must be greater than zero".to_string()),
+        duration => Ok(duration),
+    }
+}
+
+/// Parse a byte size such as `64MiB`, `100M` or `1GiB`. Units are binary
+/// (`K`, `M` and `G` are read as KiB, MiB and GiB); a bare number is bytes.
+pub fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        other => {
+            return Err(format!(
+                "invalid size unit '{}' (use B, KiB, MiB or GiB)",
+                other
+            ));
+        }
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", value))
+}
+
+pub fn parse_chunk_size(value: &str) -> Result<u64, String> {
+    let size = parse_byte_size(value)?;
+    if size < MIN_CHUNK_SIZE {
+        return Err("chunk size must be at least 5MiB, S3's minimum part size".to_string());
+    }
+    if size > MAX_CHUNK_SIZE {
+        return Err("chunk size must be at most 5GiB, S3's maximum part size".to_string());
+    }
+    Ok(size)
+}
+
+/// A byte size per second, e.g. `256KiB` or `1MiB/s`.
+pub fn parse_upload_rate(value: &str) -> Result<u64, String> {
+    match parse_byte_size(value.trim().trim_end_matches("/s"))? {
+        0 => Err("rate must be greater than zero".to_string()),
+        rate => Ok(rate),
+    }
+}
+
+pub fn parse_concurrency(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("invalid number '{}'", value)),
+    }
+}
+
+pub fn parse_md5_hex(value: &str) -> Result<String, String> {
+    parse_hex_digest(value, 32, "MD5")
+}
+
+pub fn parse_sha512_hex(value: &str) -> Result<String, String> {
+    parse_hex_digest(value, 128, "SHA-512")
+}
+
+/// Normalise a hex digest to lowercase, rejecting the wrong length.
+fn parse_hex_digest(value: &str, len: usize, label: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.len() != len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected a {}-character hex {} digest", len, label));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
+/// Package flags for create and update. Each takes an optional `=true` /
+/// `=false`; a bare flag means true. Unset flags keep the default for new
+/// packages and the existing value for updates.
+#[derive(Args)]
+#[command(next_help_heading = "Package flags")]
+pub struct PackageFlagArgs {
+    /// Fill the user template (FUT)
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub fill_user_template: Option<bool>,
+
+    /// Fill existing users (FEU)
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub fill_existing_users: Option<bool>,
+
+    /// Require a restart after installing
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub reboot_required: Option<bool>,
+
+    /// Install as part of an OS install
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub os_install: Option<bool>,
+
+    /// Suppress software updates during install
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub suppress_updates: Option<bool>,
+
+    /// Don't add the app to the Dock
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub suppress_from_dock: Option<bool>,
+
+    /// Suppress the EULA
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub suppress_eula: Option<bool>,
+
+    /// Suppress product registration
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub suppress_registration: Option<bool>,
+}
+
+impl PackageFlagArgs {
+    pub fn overrides(&self) -> PackageFlagOverrides {
+        PackageFlagOverrides {
+            fill_user_template: self.fill_user_template,
+            fill_existing_users: self.fill_existing_users,
+            reboot_required: self.reboot_required,
+            os_install: self.os_install,
+            suppress_updates: self.suppress_updates,
+            suppress_from_dock: self.suppress_from_dock,
+            suppress_eula: self.suppress_eula,
+            suppress_registration: self.suppress_registration,
+        }
+    }
+}
+
+impl UpdateArgs {
+    /// A single file rather than a directory or several paths (a batch).
+    pub fn single_file(&self) -> bool {
+        matches!(self.paths.as_slice(), [path] if !path.is_dir() || files::is_bundle(path))
+    }
+}
+
+/// Where `auth` stores credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialStore {
+    /// The OS keyring (Keychain, Secret Service, Credential Manager).
+    Keyring,
+    /// The plain-text config file, readable only by the owner.
+    File,
+    /// The config file with the client secret encrypted, by the
+    /// `JAMF_CREDENTIALS_PASSPHRASE` passphrase or else this machine's ID.
+    EncryptedFile,
+}
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines.
+    Text,
+    /// A JSON document on stdout.
+    Json,
+}
+
+/// Package record text field `--record-version` writes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VersionField {
+    /// The "Info" field.
+    Info,
+    /// The "Notes" field.
+    Notes,
+}
+
+impl std::fmt::Display for VersionField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VersionField::Info => "info",
+            VersionField::Notes => "notes",
+        })
+    }
+}
+
+/// Post-upload condition that marks the update as successful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SuccessCriterion {
+    /// Any digest field (MD5, hash value, size) differs from before the upload.
+    DigestChanged,
+    /// Jamf's MD5 equals the local file's MD5.
+    Md5Match,
+    /// Jamf's file size equals the local file's size.
+    SizeMatch,
+    /// A new entry appears in the package's change history.
+    HistoryEntry,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use super::{Cli, Commands};
+
+    #[test]
+    fn digest_polling_flags_must_be_positive() {
+        let update = |flags: &[&str]| {
+            let args = ["jamf-package-updater", "update", "App.pkg"];
+            Cli::try_parse_from(args.iter().chain(flags))
+        };
+        let Commands::Update(args) = update(&[]).unwrap().command else {
+            panic!("expected the update command");
+        };
+        assert_eq!(args.digest_wait_seconds, 300);
+        assert_eq!(args.digest_interval, Duration::from_secs(5));
+
+        let Commands::Update(args) =
+            update(&["--digest-timeout", "600", "--digest-interval", "30s"])
+                .unwrap()
+                .command
+        else {
+            panic!("expected the update command");
+        };
+        assert_eq!(args.digest_wait_seconds, 600);
+        assert_eq!(args.digest_interval, Duration::from_secs(30));
+
+        assert!(update(&["--digest-timeout", "0"]).is_err());
+        assert!(update(&["--digest-interval", "0s"]).is_err());
+    }
 }
@@ -1,9 +1,330 @@
-use anyhow::Result;
+use std::fmt;
+use std::path::PathBuf;
 
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::api::client::{AuthMode, ConnectOptions, JamfClient};
+use crate::api::policies::PolicyReadForbidden;
+use crate::cli::{CredentialStore, GlobalArgs};
 use crate::credentials;
+use crate::output::{self, outcome, status};
+use crate::progress::Spinner;
+
+/// What `auth` stored, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthReport<'a> {
+    profile: &'a str,
+    url: &'a str,
+    auth_mode: AuthMode,
+    identity: String,
+    store: CredentialStore,
+    /// The config file written, with `--store file`.
+    path: Option<PathBuf>,
+}
+
+pub async fn run(
+    client_id: &str,
+    client_secret: &str,
+    url: Option<&str>,
+    auth_mode: AuthMode,
+    store: CredentialStore,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let url = url
+        .context("--url is required (or set url under [defaults] in the config file)")?
+        .trim_end_matches('/');
+
+    // Prove the new credentials work before touching the stored ones, so a
+    // typo during rotation can't replace working credentials with broken ones.
+    status!("Verifying credentials with {}...", url);
+    let options = ConnectOptions {
+        prefer_cached_token: false,
+        auth_mode,
+        ..global.connect_options()
+    };
+    let client = JamfClient::connect(url, client_id, client_secret, &options)
+        .await
+        .context("New credentials were rejected; stored credentials were left unchanged")?;
+    let identity = client.identity().await?;
+    status!("Credentials verified (authenticated as {}).", identity);
 
-pub fn run(client_id: &str, client_secret: &str, url: &str) -> Result<()> {
-    credentials::store_credentials(client_id, client_secret, url)?;
-    println!("Credentials stored successfully.");
+    let path = match store {
+        CredentialStore::Keyring => {
+            credentials::store_credentials(
+                &global.profile,
+                client_id,
+                client_secret,
+                url,
+                auth_mode,
+            )?;
+            outcome!(
+                "Credentials stored successfully (profile: {}).",
+                global.profile
+            );
+            None
+        }
+        CredentialStore::File | CredentialStore::EncryptedFile => {
+            let encrypt = store == CredentialStore::EncryptedFile;
+            let path = credentials::store_file_credentials(
+                &global.profile,
+                global.config.as_deref(),
+                client_id,
+                client_secret,
+                url,
+                auth_mode,
+                encrypt,
+            )?;
+            outcome!(
+                "Credentials stored in {}{} (profile: {}).",
+                path.display(),
+                if encrypt { ", secret encrypted" } else { "" },
+                global.profile
+            );
+            Some(path)
+        }
+    };
+
+    if global.json() {
+        output::print_json(
+            &AuthReport {
+                profile: &global.profile,
+                url,
+                auth_mode,
+                identity,
+                store,
+                path,
+            },
+            "auth result",
+        )?;
+    }
     Ok(())
 }
+
+/// Whether the API client holds a privilege.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Access {
+    Granted,
+    Denied,
+    /// Jamf did not list the client's privileges and there is no read-only
+    /// request that would prove it.
+    Unknown,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Access::Granted => "GRANTED",
+            Access::Denied => "DENIED",
+            Access::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrivilegeCheck {
+    privilege: &'static str,
+    needed_for: &'static str,
+    access: Access,
+}
+
+/// What `auth test` found, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthTestReport {
+    url: String,
+    credential_source: String,
+    identity: String,
+    token_expires_in_seconds: u64,
+    /// Every privilege Jamf lists for the client, when it lists them.
+    privileges: Option<Vec<String>>,
+    checks: Vec<PrivilegeCheck>,
+}
+
+/// Privileges this tool relies on, with the commands that need them.
+const CHECKED_PRIVILEGES: &[(&str, &str)] = &[
+    ("Read Packages", "every package command"),
+    ("Create Packages", "update (new packages)"),
+    ("Update Packages", "update, rollback, reconcile --fix"),
+    ("Delete Packages", "delete"),
+    (
+        "Read Policies",
+        "the policy scan in update, delete and info",
+    ),
+    (
+        "Update Policies",
+        "update --reassign-policies / --flush-policies",
+    ),
+];
+
+/// The privileges `update` cannot work without.
+const REQUIRED_PRIVILEGES: &[&str] = &["Read Packages", "Update Packages"];
+
+/// Authenticate with the stored credentials and report the token's lifetime
+/// and which privileges the client holds, without changing anything. Reads
+/// are probed with a request; other privileges come from the list Jamf
+/// reports in `/api/v1/auth`. Fails when a privilege `update` needs is denied.
+pub async fn test(global: &GlobalArgs) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+    outcome!("Jamf Pro URL: {}", creds.url);
+    outcome!("Credentials from: {}", creds.source);
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await;
+    spinner.finish();
+    let client = client?;
+    let identity = client.identity().await?;
+    let expires_in = client.token_expires_in().await;
+    outcome!(
+        "Token valid for {}s (authenticated as {}).",
+        expires_in.as_secs(),
+        identity
+    );
+
+    let privileges = client.privileges().await?;
+    if privileges.is_none() {
+        outcome!("Jamf did not list this client's privileges; only reads can be checked.");
+    }
+    let checks = check_privileges(&client, privileges.as_deref()).await?;
+
+    let denied: Vec<&str> = checks
+        .iter()
+        .filter(|c| c.access == Access::Denied && REQUIRED_PRIVILEGES.contains(&c.privilege))
+        .map(|c| c.privilege)
+        .collect();
+
+    if global.json() {
+        output::print_json(
+            &AuthTestReport {
+                url: creds.url,
+                credential_source: creds.source.to_string(),
+                identity,
+                token_expires_in_seconds: expires_in.as_secs(),
+                privileges,
+                checks,
+            },
+            "auth test result",
+        )?;
+    }
+    if !denied.is_empty() {
+        bail!(
+            "The API client lacks {}; grant it in the client's API role",
+            denied.join(" and ")
+        );
+    }
+    Ok(())
+}
+
+/// Check each of [`CHECKED_PRIVILEGES`], printing a line per privilege.
+/// `privileges` is the list Jamf reports for the client, if any.
+async fn check_privileges(
+    client: &JamfClient,
+    privileges: Option<&[String]>,
+) -> Result<Vec<PrivilegeCheck>> {
+    let mut checks = Vec::with_capacity(CHECKED_PRIVILEGES.len());
+    for &(privilege, needed_for) in CHECKED_PRIVILEGES {
+        let access = match privilege {
+            "Read Packages" => probe_packages(client).await?,
+            "Read Policies" => probe_policies(client).await?,
+            _ => Access::Unknown,
+        };
+        // A probe is proof either way; otherwise go by the listed privileges.
+        let access = match (access, privileges) {
+            (Access::Unknown, Some(listed)) if listed.iter().any(|p| p == privilege) => {
+                Access::Granted
+            }
+            (Access::Unknown, Some(_)) => Access::Denied,
+            (access, _) => access,
+        };
+        outcome!("  {:<16} {:<8} ({})", privilege, access, needed_for);
+        checks.push(PrivilegeCheck {
+            privilege,
+            needed_for,
+            access,
+        });
+    }
+    Ok(checks)
+}
+
+async fn probe_packages(client: &JamfClient) -> Result<Access> {
+    Ok(
+        match client
+            .get_optional_json("/api/v1/packages?page=0&page-size=1")
+            .await?
+        {
+            Some(_) => Access::Granted,
+            None => Access::Denied,
+        },
+    )
+}
+
+async fn probe_policies(client: &JamfClient) -> Result<Access> {
+    match client.list_policies().await {
+        Ok(_) => Ok(Access::Granted),
+        Err(e) if e.chain().any(|cause| cause.is::<PolicyReadForbidden>()) => Ok(Access::Denied),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Access, check_privileges};
+    use crate::mock::{self, MockState};
+
+    async fn access(state: MockState) -> Vec<(&'static str, Access)> {
+        let client = mock::connect(Arc::new(state)).await;
+        let privileges = client.privileges().await.unwrap();
+        check_privileges(&client, privileges.as_deref())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.privilege, c.access))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn probes_reads_and_takes_the_rest_from_the_listed_privileges() {
+        let checks = access(MockState {
+            privileges: Some(vec!["Read Packages", "Update Packages", "Read Policies"]),
+            forbid_policies: true,
+            ..MockState::default()
+        })
+        .await;
+        assert_eq!(
+            checks,
+            [
+                ("Read Packages", Access::Granted),
+                ("Create Packages", Access::Denied),
+                ("Update Packages", Access::Granted),
+                ("Delete Packages", Access::Denied),
+                // The probe wins over the list.
+                ("Read Policies", Access::Denied),
+                ("Update Policies", Access::Denied),
+            ]
+        );
+
+        let checks = access(MockState::default()).await;
+        assert_eq!(
+            checks,
+            [
+                ("Read Packages", Access::Granted),
+                ("Create Packages", Access::Unknown),
+                ("Update Packages", Access::Unknown),
+                ("Delete Packages", Access::Unknown),
+                ("Read Policies", Access::Granted),
+                ("Update Policies", Access::Unknown),
+            ]
+        );
+    }
+}
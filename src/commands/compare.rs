@@ -0,0 +1,349 @@
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use serde::{Serialize, Serializer};
+
+use crate::api::client::JamfClient;
+use crate::checksum::compute_file_digest;
+use crate::cli::{GlobalArgs, UpdateArgs};
+use crate::commands::update::Unchanged;
+use crate::credentials;
+use crate::files::{InstallerExtensions, collect_all_files, zip_if_bundle};
+use crate::junit;
+use crate::naming::{NameTemplate, resolve_package_name};
+use crate::output::{self, status};
+use crate::progress::Spinner;
+use crate::redact;
+
+/// Outcome of comparing one local file against Jamf Pro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CompareStatus {
+    New,
+    Changed,
+    Unchanged,
+    Ambiguous,
+    Error,
+}
+
+impl fmt::Display for CompareStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            CompareStatus::New => "NEW",
+            CompareStatus::Changed => "CHANGED",
+            CompareStatus::Unchanged => "UNCHANGED",
+            CompareStatus::Ambiguous => "AMBIGUOUS",
+            CompareStatus::Error => "ERROR",
+        };
+        f.pad(label)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompareRow {
+    status: CompareStatus,
+    package_name: String,
+    file_name: String,
+    detail: String,
+    #[serde(rename = "elapsedMs", serialize_with = "serialize_millis")]
+    elapsed: Duration,
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
+impl CompareRow {
+    fn junit_case(&self) -> junit::TestCase {
+        let outcome = match self.status {
+            CompareStatus::New | CompareStatus::Changed | CompareStatus::Unchanged => {
+                junit::CaseOutcome::Passed
+            }
+            CompareStatus::Ambiguous => junit::CaseOutcome::Skipped(self.detail.clone()),
+            CompareStatus::Error => junit::CaseOutcome::Failed(self.detail.clone()),
+        };
+        junit::TestCase {
+            name: self.file_name.clone(),
+            classname: self.package_name.clone(),
+            time: self.elapsed,
+            outcome,
+            output: Some(format!("{}: {}", self.status, self.detail)),
+        }
+    }
+}
+
+/// Read-only report: for each file, resolve its package and compare digests
+/// without creating, updating, or uploading anything.
+pub async fn run(args: &UpdateArgs, global: &GlobalArgs) -> Result<()> {
+    let extensions = InstallerExtensions::new(&args.allow_extension);
+    let files = collect_all_files(&args.paths, &extensions)?;
+    extensions.warn_if_non_standard(&files);
+    if args.name.is_some() && files.len() > 1 {
+        bail!("--name cannot be used when comparing several files");
+    }
+    if args.id.is_some() && files.len() > 1 {
+        bail!("--id cannot be used when comparing several files");
+    }
+    let template = args
+        .name_template
+        .as_deref()
+        .map(|t| NameTemplate::new(t, args.name_pattern.as_deref()))
+        .transpose()?;
+
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+    status!("Jamf Pro URL: {}", creds.url);
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    let identity = client.identity().await?;
+    spinner.finish();
+    status!("Authenticated as {}.", identity);
+
+    status!("Comparing {} file(s)...", files.len());
+    let mut rows = Vec::with_capacity(files.len());
+    for (i, file) in files.iter().enumerate() {
+        if i > 0 && !args.between_files_delay.is_zero() {
+            tokio::time::sleep(args.between_files_delay).await;
+        }
+
+        let started = Instant::now();
+        let file_name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (package_name, result) =
+            match resolve_package_name(file, args.name.as_deref(), template.as_ref()) {
+                Ok(package_name) => {
+                    let result = compare_file(
+                        &client,
+                        file,
+                        &package_name,
+                        args.id.as_deref(),
+                        args.require_strong_hash,
+                    )
+                    .await;
+                    (package_name, result)
+                }
+                Err(e) => (file_name.clone(), Err(e)),
+            };
+
+        let (status, detail) = match result {
+            Ok(result) => result,
+            Err(e) => (CompareStatus::Error, redact::scrub(&format!("{:#}", e))),
+        };
+        rows.push(CompareRow {
+            status,
+            package_name,
+            file_name,
+            detail,
+            elapsed: started.elapsed(),
+        });
+    }
+
+    if global.json() {
+        output::print_json(&rows, "comparison")?;
+    } else {
+        print_report(&rows);
+    }
+
+    if let Some(report) = &args.junit {
+        let cases: Vec<junit::TestCase> = rows.iter().map(CompareRow::junit_case).collect();
+        junit::write_report(report, "compare", &cases)?;
+    }
+
+    let failed = rows
+        .iter()
+        .filter(|r| r.status == CompareStatus::Error)
+        .count();
+    if failed > 0 {
+        bail!("{} of {} file(s) could not be compared", failed, rows.len());
+    }
+    if args.fail_on_unchanged && rows.iter().all(|r| r.status == CompareStatus::Unchanged) {
+        return Err(Unchanged.into());
+    }
+
+    Ok(())
+}
+
+async fn compare_file(
+    client: &JamfClient,
+    path: &Path,
+    package_name: &str,
+    id: Option<&str>,
+    require_strong_hash: bool,
+) -> Result<(CompareStatus, String)> {
+    let matches = match id {
+        Some(id) => vec![
+            client
+                .get_package(id)
+                .await
+                .with_context(|| format!("Package ID {} could not be read", id))?,
+        ],
+        None => client.find_packages(package_name).await?,
+    };
+    let package = match matches.as_slice() {
+        [] => {
+            let similar = client.find_similar_packages(package_name).await?;
+            let detail = match similar.as_slice() {
+                [] => "no package record".to_string(),
+                found => {
+                    let names: Vec<String> = found
+                        .iter()
+                        .map(|p| format!("'{}' (ID: {})", p.package_name, p.id))
+                        .collect();
+                    format!("no exact record; similar: {}", names.join(", "))
+                }
+            };
+            return Ok((CompareStatus::New, detail));
+        }
+        [package] => package,
+        many => {
+            let ids: Vec<&str> = many.iter().map(|p| p.id.as_str()).collect();
+            return Ok((
+                CompareStatus::Ambiguous,
+                format!(
+                    "{} records share this name (IDs: {})",
+                    many.len(),
+                    ids.join(", ")
+                ),
+            ));
+        }
+    };
+
+    let digest = client.get_package_digest_snapshot(&package.id).await?;
+    if require_strong_hash && !digest.as_ref().is_some_and(|d| d.has_strong_hash()) {
+        bail!(
+            "ID {}, Jamf reports no SHA-2/SHA-3 digest and --require-strong-hash rejects MD5",
+            package.id
+        );
+    }
+
+    let Some((algorithm, remote)) = digest.as_ref().and_then(|d| d.comparable_digest()) else {
+        return Ok((
+            CompareStatus::Changed,
+            format!("ID {}, remote digest unavailable", package.id),
+        ));
+    };
+    // A bundle is compared as the archive `update` would upload.
+    let zipped = zip_if_bundle(path).await?;
+    let local = compute_file_digest(zipped.as_ref().map_or(path, |z| &z.path), algorithm).await?;
+    if remote.eq_ignore_ascii_case(&local) {
+        Ok((
+            CompareStatus::Unchanged,
+            format!("ID {}, {} {}", package.id, algorithm, local),
+        ))
+    } else {
+        Ok((
+            CompareStatus::Changed,
+            format!("ID {}, {} {} -> {}", package.id, algorithm, remote, local),
+        ))
+    }
+}
+
+fn print_report(rows: &[CompareRow]) {
+    let package_width = rows
+        .iter()
+        .map(|r| r.package_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("PACKAGE".len());
+    let file_width = rows
+        .iter()
+        .map(|r| r.file_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("FILE".len());
+
+    println!();
+    println!(
+        "{:<10} {:<package_width$} {:<file_width$} DETAIL",
+        "STATUS", "PACKAGE", "FILE"
+    );
+    for row in rows {
+        println!(
+            "{:<10} {:<package_width$} {:<file_width$} {}",
+            row.status, row.package_name, row.file_name, row.detail
+        );
+    }
+
+    let count = |status| rows.iter().filter(|r| r.status == status).count();
+    println!();
+    println!(
+        "{} new, {} changed, {} unchanged, {} ambiguous, {} error(s).",
+        count(CompareStatus::New),
+        count(CompareStatus::Changed),
+        count(CompareStatus::Unchanged),
+        count(CompareStatus::Ambiguous),
+        count(CompareStatus::Error)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{CompareStatus, compare_file};
+    use crate::mock::{self, MockState, package_record};
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[tokio::test]
+    async fn reports_unchanged_changed_ambiguous_and_new_rows() {
+        let mut same = package_record("1", "Same", "Same.pkg");
+        same["hashType"] = json!("SHA_256");
+        same["hashValue"] = json!(ABC_SHA256);
+        let mut edited = package_record("2", "Edited", "Edited.pkg");
+        edited["md5Hash"] = json!("00000000000000000000000000000000");
+        let state = Arc::new(MockState {
+            records: vec![
+                same,
+                edited,
+                package_record("3", "Twin", "Twin.pkg"),
+                package_record("4", "Twin", "Twin-copy.pkg"),
+            ],
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+        let path = std::env::temp_dir().join(format!("jpu-compare-{}.pkg", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+
+        let compare = |name| compare_file(&client, &path, name, None, false);
+        let (status, detail) = compare("Same").await.unwrap();
+        assert_eq!(status, CompareStatus::Unchanged);
+        assert_eq!(detail, format!("ID 1, SHA-256 {}", ABC_SHA256));
+
+        let (status, detail) = compare("Edited").await.unwrap();
+        assert_eq!(status, CompareStatus::Changed);
+        assert!(
+            detail.ends_with("-> 900150983cd24fb0d6963f7d28e17f72"),
+            "{}",
+            detail
+        );
+
+        let (status, detail) = compare("Twin").await.unwrap();
+        assert_eq!(status, CompareStatus::Ambiguous);
+        assert_eq!(detail, "2 records share this name (IDs: 3, 4)");
+
+        let (status, _) = compare("Fresh").await.unwrap();
+        assert_eq!(status, CompareStatus::New);
+
+        // --require-strong-hash turns an MD5-only record into an error row.
+        assert!(
+            compare_file(&client, &path, "Edited", None, true)
+                .await
+                .is_err()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageTarget;
+use crate::api::policies::{PackageMatch, PolicyReadForbidden, ScanOptions};
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::models::package::Package;
+use crate::models::policy::AffectedPolicy;
+use crate::output::{self, outcome, status};
+use crate::progress::{self, Spinner};
+use crate::prompt;
+
+/// What `delete` did, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteReport<'a> {
+    id: &'a str,
+    package_name: &'a str,
+    file_name: &'a str,
+    /// False when the confirmation prompt was declined.
+    deleted: bool,
+    /// Whether the package's JCDS file was removed as well.
+    file_deleted: bool,
+    /// False when the token could not read policies to check for references.
+    policies_checked: bool,
+    /// Policies that still referenced the package (deleted anyway with
+    /// `--force`).
+    referencing_policies: &'a [AffectedPolicy],
+}
+
+/// Delete a package record, found by exact name or by ID, and its JCDS file.
+/// Refuses while any policy still references it, unless `force`.
+pub async fn run(
+    target: PackageTarget<'_>,
+    yes: bool,
+    force: bool,
+    keep_file: bool,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    spinner.finish();
+
+    let package = client.resolve_package(target).await?;
+    status!(
+        "Package '{}' (ID: {}, file: {})",
+        package.package_name,
+        package.id,
+        package.file_name
+    );
+
+    status!("Scanning policies...");
+    let package_match = PackageMatch {
+        id: Some(&package.id),
+        package_name: &package.package_name,
+        file_name: &package.file_name,
+    };
+    let (policies, policies_checked) = match client
+        .find_policies_with_package(
+            &package_match,
+            &ScanOptions {
+                concurrency: global.scan_concurrency,
+                progress: progress::policy_scan(),
+                ..ScanOptions::default()
+            },
+        )
+        .await
+    {
+        Ok(policies) if policies.is_empty() => {
+            status!("No policies reference this package.");
+            (policies, true)
+        }
+        Ok(policies) => {
+            for p in &policies {
+                outcome!("  - {} (ID: {})", p.name, p.id);
+            }
+            let count = format!(
+                "{} {}",
+                policies.len(),
+                if policies.len() == 1 {
+                    "policy"
+                } else {
+                    "policies"
+                }
+            );
+            if !force {
+                bail!(
+                    "Package is still referenced by {}; remove it from them before deleting, \
+                     or pass --force",
+                    count
+                );
+            }
+            outcome!(
+                "Warning: package is still referenced by {}; deleting anyway (--force).",
+                count
+            );
+            (policies, true)
+        }
+        Err(e) if e.chain().any(|cause| cause.is::<PolicyReadForbidden>()) => {
+            outcome!(
+                "Warning: token lacks classic API read access; could not check whether policies \
+                 still reference this package."
+            );
+            (Vec::new(), false)
+        }
+        Err(e) => return Err(e),
+    };
+    let report = |deleted, file_deleted| {
+        if !global.json() {
+            return Ok(());
+        }
+        output::print_json(
+            &DeleteReport {
+                id: &package.id,
+                package_name: &package.package_name,
+                file_name: &package.file_name,
+                deleted,
+                file_deleted,
+                policies_checked,
+                referencing_policies: &policies,
+            },
+            "delete result",
+        )
+    };
+
+    if !yes {
+        if !prompt::is_interactive() {
+            bail!(
+                "Refusing to delete without confirmation; pass --yes to delete non-interactively"
+            );
+        }
+        let file = if keep_file {
+            String::new()
+        } else {
+            format!(" and its file {}", package.file_name)
+        };
+        if !prompt::confirm(&format!(
+            "Delete package '{}' (ID: {}){}?",
+            package.package_name, package.id, file
+        ))? {
+            outcome!("Nothing deleted.");
+            return report(false, false);
+        }
+    }
+
+    client.delete_package(&package.id).await?;
+    outcome!(
+        "Package '{}' (ID: {}) deleted.",
+        package.package_name,
+        package.id
+    );
+
+    let file_deleted = if keep_file {
+        false
+    } else {
+        delete_payload(&client, &package)
+            .await
+            .context("Package record deleted, but its JCDS file was not removed")?
+    };
+    report(true, file_deleted)
+}
+
+/// Remove the deleted record's JCDS file, unless another record still uses
+/// it. Returns whether a file was removed.
+async fn delete_payload(client: &JamfClient, package: &Package) -> Result<bool> {
+    let Some(files) = client.list_jcds_files().await? else {
+        outcome!(
+            "JCDS file listing is unavailable (on-prem distribution point or missing API \
+             permission); {} was left in place.",
+            package.file_name
+        );
+        return Ok(false);
+    };
+    // Some Jamf versions remove the file along with the record.
+    if !files.iter().any(|f| f.file_name == package.file_name) {
+        status!("No JCDS file {} to remove.", package.file_name);
+        return Ok(false);
+    }
+
+    let sharing: Vec<Package> = client
+        .list_packages()
+        .await?
+        .into_iter()
+        .filter(|p| p.id != package.id && p.file_name == package.file_name)
+        .collect();
+    if !sharing.is_empty() {
+        let ids: Vec<&str> = sharing.iter().map(|p| p.id.as_str()).collect();
+        outcome!(
+            "JCDS file {} is still used by package ID {}; it was left in place.",
+            package.file_name,
+            ids.join(", ")
+        );
+        return Ok(false);
+    }
+
+    client.delete_jcds_file(&package.file_name).await?;
+    outcome!("JCDS file {} deleted.", package.file_name);
+    Ok(true)
+}
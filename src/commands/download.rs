@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use indicatif::HumanBytes;
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageTarget;
+use crate::checksum::compute_file_digest;
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::output::{self, outcome, status};
+use crate::progress::{Spinner, TransferProgress};
+
+/// What `download` fetched, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadReport<'a> {
+    id: &'a str,
+    package_name: &'a str,
+    file_name: &'a str,
+    path: &'a Path,
+    bytes: u64,
+    /// The algorithm the download was checked with; None when Jamf reports
+    /// no digest for the package.
+    algorithm: Option<String>,
+    digest: Option<String>,
+    verified: bool,
+}
+
+/// Download a package's file (package found by exact name or ID) from JCDS
+/// and check it against the digest Jamf stores. A file that doesn't match is
+/// removed again.
+pub async fn run(
+    target: PackageTarget<'_>,
+    out: Option<&Path>,
+    force: bool,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    spinner.finish();
+
+    let package = client.resolve_package(target).await?;
+    let dest = destination(out, &package.file_name);
+    if dest.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            dest.display()
+        );
+    }
+    status!(
+        "Downloading {} (package '{}', ID: {}) to {}...",
+        package.file_name,
+        package.package_name,
+        package.id,
+        dest.display()
+    );
+
+    let bytes = client
+        .download_jcds_file(
+            &package.file_name,
+            &dest,
+            TransferProgress::download(global.quiet).as_ref(),
+        )
+        .await?;
+
+    let snapshot = client.get_package_digest_snapshot(&package.id).await?;
+    let (algorithm, digest) = match snapshot.as_ref().and_then(|d| d.comparable_digest()) {
+        Some((algorithm, expected)) => {
+            let local = compute_file_digest(&dest, algorithm).await?;
+            if !local.eq_ignore_ascii_case(expected) {
+                let _ = tokio::fs::remove_file(&dest).await;
+                bail!(
+                    "Downloaded file does not match the package's {} in Jamf (expected {}, got {}); \
+                     it was removed",
+                    algorithm,
+                    expected,
+                    local
+                );
+            }
+            (Some(algorithm.to_string()), Some(local))
+        }
+        None => {
+            outcome!(
+                "Warning: Jamf reports no digest for this package; the download was not verified."
+            );
+            (None, None)
+        }
+    };
+
+    match &algorithm {
+        Some(algorithm) => outcome!(
+            "Downloaded {} ({}) to {}; {} matches Jamf.",
+            package.file_name,
+            HumanBytes(bytes),
+            dest.display(),
+            algorithm
+        ),
+        None => outcome!(
+            "Downloaded {} ({}) to {}.",
+            package.file_name,
+            HumanBytes(bytes),
+            dest.display()
+        ),
+    }
+
+    if global.json() {
+        output::print_json(
+            &DownloadReport {
+                id: &package.id,
+                package_name: &package.package_name,
+                file_name: &package.file_name,
+                path: &dest,
+                bytes,
+                verified: algorithm.is_some(),
+                algorithm,
+                digest,
+            },
+            "download result",
+        )?;
+    }
+    Ok(())
+}
+
+/// Where to save the file: `out` itself, or `file_name` inside `out` when it
+/// is a directory (the current directory when `out` is not given).
+fn destination(out: Option<&Path>, file_name: &str) -> PathBuf {
+    // Never let a file name from the server point outside the chosen folder.
+    let file_name = Path::new(file_name)
+        .file_name()
+        .map_or_else(|| PathBuf::from("package"), PathBuf::from);
+    match out {
+        Some(path) if path.is_dir() => path.join(file_name),
+        Some(path) => path.to_path_buf(),
+        None => file_name,
+    }
+}
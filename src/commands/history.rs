@@ -0,0 +1,88 @@
+use anyhow::Result;
+
+use crate::cli::GlobalArgs;
+use crate::history::{self, HistoryEntry};
+use crate::output;
+
+/// Print the recorded `update` runs, newest last: all packages, or only
+/// `name` (case-insensitive), at most `limit` entries.
+pub fn run(name: Option<&str>, limit: usize, json: bool, global: &GlobalArgs) -> Result<()> {
+    let path = history::history_path(global.history_file.as_deref())?;
+    let needle = name.map(str::to_lowercase);
+    let mut entries: Vec<HistoryEntry> = history::read(&path)?
+        .into_iter()
+        .filter(|e| {
+            needle
+                .as_deref()
+                .is_none_or(|n| e.package_name.to_lowercase() == n)
+        })
+        .collect();
+    if limit > 0 && entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+
+    if json {
+        return output::print_json(&entries, "history");
+    }
+
+    if entries.is_empty() {
+        match name {
+            Some(name) => println!("No updates of '{}' recorded in {}.", name, path.display()),
+            None => println!("No updates recorded in {}.", path.display()),
+        }
+        return Ok(());
+    }
+    for entry in &entries {
+        println!(
+            "{}  {:<7}  {}{}",
+            entry.time(),
+            entry.action.label(),
+            entry.package_name,
+            entry
+                .package_id
+                .as_deref()
+                .map(|id| format!(" (ID: {})", id))
+                .unwrap_or_default()
+        );
+        println!("  File:     {}", entry.file.display());
+        let who = match (&entry.user, &entry.identity) {
+            (Some(user), Some(identity)) => format!("{} as {}", user, identity),
+            (Some(who), None) | (None, Some(who)) => who.clone(),
+            (None, None) => "unknown".to_string(),
+        };
+        println!("  By:       {} (profile {})", who, entry.profile);
+        if let Some(digest) = &entry.previous_digest {
+            println!("  Before:   {}", digest.display_line());
+        }
+        if let Some(digest) = &entry.new_digest {
+            println!("  After:    {}", digest.display_line());
+        } else if let Some(md5) = &entry.local_md5 {
+            println!("  Local:    md5={}", md5);
+        }
+        if let Some(size) = entry.file_size {
+            println!("  Size:     {} bytes", size);
+        }
+        if !entry.affected_policies.is_empty() {
+            let names: Vec<String> = entry
+                .affected_policies
+                .iter()
+                .map(|p| format!("{} ({})", p.name, p.id))
+                .collect();
+            println!("  Policies: {}", names.join(", "));
+        }
+        if let Some(error) = &entry.error {
+            println!("  Error:    {}", error);
+        }
+    }
+    println!();
+    println!(
+        "{} {}.",
+        entries.len(),
+        if entries.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        }
+    );
+    Ok(())
+}
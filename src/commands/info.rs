@@ -0,0 +1,353 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageDigestSnapshot;
+use crate::api::packages::PackageTarget;
+use crate::api::policies::{PackageMatch, PolicyReadForbidden, ScanOptions};
+use crate::checksum::{HashType, compute_file_digest, compute_file_md5};
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::models::package::Package;
+use crate::models::patch::{PatchPolicyReference, PatchTitleReference};
+use crate::models::policy::AffectedPolicy;
+use crate::models::prestage::PrestageReference;
+use crate::output;
+use crate::progress::{self, Spinner};
+
+/// A package record and, when a local file was given, how it compares.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageInfo {
+    #[serde(flatten)]
+    package: Package,
+    digest: Option<PackageDigestSnapshot>,
+    /// Size of the JCDS file, when JCDS lists it.
+    file_size: Option<u64>,
+    /// Whether JCDS holds the file; None when the listing is unavailable
+    /// (on-prem distribution point, missing permission).
+    in_jcds: Option<bool>,
+    references: Option<References>,
+    local_file: Option<LocalComparison>,
+}
+
+/// What still uses the package. Each list is None when the token could not
+/// read it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct References {
+    policies: Option<Vec<AffectedPolicy>>,
+    patch_titles: Option<Vec<PatchTitleReference>>,
+    patch_policies: Option<Vec<PatchPolicyReference>>,
+    prestages: Option<Vec<PrestageReference>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocalComparison {
+    path: PathBuf,
+    md5: String,
+    /// The algorithm the comparison used: the strongest one Jamf reports.
+    algorithm: Option<String>,
+    /// The local file's digest in `algorithm`.
+    digest: Option<String>,
+    /// None when Jamf reports no digest to compare with.
+    matches: Option<bool>,
+}
+
+/// Show one package (by exact name or ID) with its full record, the digest
+/// Jamf reports, its JCDS file and, unless `references` is false, the
+/// policies, patch titles and prestage enrollments that use it. With `file`,
+/// also whether that local file matches the digest. Changes nothing.
+pub async fn run(
+    target: PackageTarget<'_>,
+    file: Option<&Path>,
+    references: bool,
+    json: bool,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Fetching package...", global.quiet || json);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    let found = fetch_package(&client, target).await;
+    spinner.finish();
+    let (package, digest) = found?;
+
+    let jcds_file = client
+        .list_jcds_files()
+        .await?
+        .map(|files| files.into_iter().find(|f| f.file_name == package.file_name));
+    let references = if references {
+        Some(find_references(&client, &package, global).await?)
+    } else {
+        None
+    };
+
+    let local_file = match file {
+        Some(path) => Some(compare_local_file(path, digest.as_ref()).await?),
+        None => None,
+    };
+
+    let info = PackageInfo {
+        file_size: jcds_file.as_ref().and_then(|f| f.as_ref()?.length),
+        in_jcds: jcds_file.as_ref().map(Option::is_some),
+        package,
+        digest,
+        references,
+        local_file,
+    };
+
+    if json {
+        return output::print_json(&info, "package info");
+    }
+
+    let package = &info.package;
+    println!("{} (ID: {})", package.package_name, package.id);
+    println!("  File:     {}", package.file_name);
+    match (info.in_jcds, info.file_size) {
+        (Some(true), Some(size)) => println!("  JCDS:     present ({} bytes)", size),
+        (Some(true), None) => println!("  JCDS:     present"),
+        (Some(false), _) => println!("  JCDS:     missing"),
+        (None, _) => println!("  JCDS:     unknown (file listing unavailable)"),
+    }
+    println!("  Category: {}", package.category_id);
+    println!("  Priority: {}", package.priority);
+    match &info.digest {
+        Some(digest) => println!("  Digest:   {}", digest.display_line()),
+        None => println!("  Digest:   none reported"),
+    }
+    let flags = enabled_flags(package);
+    if !flags.is_empty() {
+        println!("  Options:  {}", flags.join(", "));
+    }
+    if let Some(text) = package.info.as_deref().filter(|t| !t.is_empty()) {
+        println!("  Info:     {}", text.replace('\n', "\n            "));
+    }
+    if let Some(text) = package.notes.as_deref().filter(|t| !t.is_empty()) {
+        println!("  Notes:    {}", text.replace('\n', "\n            "));
+    }
+
+    if let Some(references) = &info.references {
+        println!();
+        print_references("Policies", references.policies.as_deref(), |p| {
+            format!("{} (ID: {})", p.name, p.id)
+        });
+        print_references("Patch titles", references.patch_titles.as_deref(), |t| {
+            format!("{} {} (ID: {})", t.name, t.version, t.id)
+        });
+        print_references(
+            "Patch policies",
+            references.patch_policies.as_deref(),
+            |p| format!("{} (ID: {}, version {})", p.name, p.id, p.version),
+        );
+        print_references(
+            "Prestage enrollments",
+            references.prestages.as_deref(),
+            |p| format!("{} (ID: {})", p.name, p.id),
+        );
+    }
+
+    if let Some(local) = &info.local_file {
+        println!();
+        println!("Local file {}", local.path.display());
+        println!("  MD5:      {}", local.md5);
+        if let (Some(algorithm), Some(digest)) = (&local.algorithm, &local.digest)
+            && algorithm != "MD5"
+        {
+            println!("  {}: {}", algorithm, digest);
+        }
+        match (local.matches, &local.algorithm) {
+            (Some(true), Some(algorithm)) => {
+                println!("Matches the package in Jamf ({} identical).", algorithm)
+            }
+            (Some(false), Some(algorithm)) => {
+                println!("Differs from the package in Jamf ({} mismatch).", algorithm)
+            }
+            _ => println!("Jamf reports no digest to compare with."),
+        }
+    }
+    Ok(())
+}
+
+/// The package's enabled install options, by their Jamf UI names.
+fn enabled_flags(package: &Package) -> Vec<&'static str> {
+    [
+        (package.reboot_required, "reboot required"),
+        (package.os_install, "OS installer"),
+        (package.fill_user_template, "fill user template"),
+        (package.fill_existing_users, "fill existing users"),
+        (package.suppress_updates, "suppress updates"),
+        (package.suppress_from_dock, "suppress from Dock"),
+        (package.suppress_eula, "suppress EULA"),
+        (package.suppress_registration, "suppress registration"),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, name)| enabled.then_some(name))
+    .collect()
+}
+
+fn print_references<T>(heading: &str, items: Option<&[T]>, describe: impl Fn(&T) -> String) {
+    match items {
+        None => println!("{}: not checked (no read access)", heading),
+        Some([]) => println!("{}: none", heading),
+        Some(items) => {
+            println!("{}:", heading);
+            for item in items {
+                println!("  - {}", describe(item));
+            }
+        }
+    }
+}
+
+/// Look up everything that installs the package. A list the token can't read
+/// is left as None rather than failing the whole command.
+async fn find_references(
+    client: &JamfClient,
+    package: &Package,
+    global: &GlobalArgs,
+) -> Result<References> {
+    let spinner = Spinner::start("Checking references...", global.quiet || global.json());
+    let package_match = PackageMatch {
+        id: Some(&package.id),
+        package_name: &package.package_name,
+        file_name: &package.file_name,
+    };
+    let policies = match client
+        .find_policies_with_package(
+            &package_match,
+            &ScanOptions {
+                concurrency: global.scan_concurrency,
+                progress: progress::policy_scan(),
+                ..ScanOptions::default()
+            },
+        )
+        .await
+    {
+        Ok(policies) => Some(policies),
+        Err(e) if e.chain().any(|cause| cause.is::<PolicyReadForbidden>()) => None,
+        Err(e) => return Err(e),
+    };
+
+    let (patch_titles, patch_policies) = match client.find_patch_references(&package.id).await? {
+        Some((titles, policies)) => (Some(titles), policies),
+        None => (None, None),
+    };
+
+    let prestages = client.list_computer_prestages().await?.map(|prestages| {
+        prestages
+            .into_iter()
+            .filter(|p| p.custom_package_ids.contains(&package.id))
+            .map(|p| PrestageReference {
+                id: p.id,
+                name: p.display_name,
+            })
+            .collect()
+    });
+    spinner.finish();
+
+    Ok(References {
+        policies,
+        patch_titles,
+        patch_policies,
+        prestages,
+    })
+}
+
+async fn fetch_package(
+    client: &JamfClient,
+    target: PackageTarget<'_>,
+) -> Result<(Package, Option<PackageDigestSnapshot>)> {
+    let package = client.resolve_package(target).await?;
+    let digest = client.get_package_digest_snapshot(&package.id).await?;
+    Ok((package, digest))
+}
+
+/// Hash the local file and compare it with the strongest digest Jamf reports,
+/// the same comparison `update` uses to decide whether to skip.
+async fn compare_local_file(
+    path: &Path,
+    digest: Option<&PackageDigestSnapshot>,
+) -> Result<LocalComparison> {
+    let md5 = compute_file_md5(path).await?;
+    let (algorithm, local, matches) = match digest.and_then(|d| d.comparable_digest()) {
+        Some((algorithm, remote)) => {
+            let local = match algorithm {
+                HashType::Md5 => md5.clone(),
+                other => compute_file_digest(path, other).await?,
+            };
+            let matches = remote.eq_ignore_ascii_case(&local);
+            (Some(algorithm.to_string()), Some(local), Some(matches))
+        }
+        None => (None, None, None),
+    };
+    Ok(LocalComparison {
+        path: path.to_path_buf(),
+        md5,
+        algorithm,
+        digest: local,
+        matches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{compare_local_file, fetch_package};
+    use crate::api::packages::PackageTarget;
+    use crate::mock::{self, MockState, package_record};
+
+    #[tokio::test]
+    async fn compares_a_local_file_with_the_package_digest() {
+        let mut app = package_record("1", "App", "App.pkg");
+        app["hashType"] = json!("SHA_256");
+        app["hashValue"] =
+            json!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let state = Arc::new(MockState {
+            records: vec![app, package_record("2", "Bare", "Bare.pkg")],
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+        let path = std::env::temp_dir().join(format!("jpu-info-{}.pkg", std::process::id()));
+
+        let (package, digest) = fetch_package(&client, PackageTarget::NameOrId("App"))
+            .await
+            .unwrap();
+        assert_eq!(package.id, "1");
+        std::fs::write(&path, b"abc").unwrap();
+        let local = compare_local_file(&path, digest.as_ref()).await.unwrap();
+        assert_eq!(local.algorithm.as_deref(), Some("SHA-256"));
+        assert_eq!(local.md5, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(local.matches, Some(true));
+
+        std::fs::write(&path, b"abcd").unwrap();
+        let local = compare_local_file(&path, digest.as_ref()).await.unwrap();
+        assert_eq!(local.matches, Some(false));
+
+        // A numeric target falls back to the ID when no package has that name.
+        let (package, digest) = fetch_package(&client, PackageTarget::NameOrId("2"))
+            .await
+            .unwrap();
+        assert_eq!(package.package_name, "Bare");
+        let local = compare_local_file(&path, digest.as_ref()).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((local.algorithm, local.matches), (None, None));
+
+        assert!(
+            fetch_package(&client, PackageTarget::NameOrId("Missing"))
+                .await
+                .is_err()
+        );
+    }
+}
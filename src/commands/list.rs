@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageDigestSnapshot;
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::output;
+use crate::progress::Spinner;
+
+/// One package as printed by `list`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageListing {
+    id: String,
+    package_name: String,
+    file_name: String,
+    category_id: String,
+    category: Option<String>,
+    priority: i32,
+    digest: Option<PackageDigestSnapshot>,
+}
+
+/// List packages (optionally only those whose name contains `filter`) with
+/// their category, priority and the digest Jamf currently reports.
+pub async fn run(filter: Option<&str>, json: bool, global: &GlobalArgs) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Fetching packages...", global.quiet || json);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    let listings = fetch_listings(&client, filter, global.scan_concurrency).await;
+    spinner.finish();
+    let listings = listings?;
+
+    if json {
+        return output::print_json(&listings, "packages");
+    }
+
+    for listing in &listings {
+        println!("{} (ID: {})", listing.package_name, listing.id);
+        println!("  File:     {}", listing.file_name);
+        println!(
+            "  Category: {}",
+            listing.category.as_deref().unwrap_or(&listing.category_id)
+        );
+        println!("  Priority: {}", listing.priority);
+        match &listing.digest {
+            Some(digest) => println!("  Digest:   {}", digest.display_line()),
+            None => println!("  Digest:   none reported"),
+        }
+    }
+    if !listings.is_empty() {
+        println!();
+    }
+    println!("{} packages.", listings.len());
+
+    Ok(())
+}
+
+/// The matching packages with their digests, fetched `concurrency` records
+/// at a time.
+async fn fetch_listings(
+    client: &JamfClient,
+    filter: Option<&str>,
+    concurrency: usize,
+) -> Result<Vec<PackageListing>> {
+    let needle = filter.map(str::to_lowercase);
+    let packages: Vec<_> = client
+        .list_packages()
+        .await?
+        .into_iter()
+        .filter(|p| {
+            needle
+                .as_deref()
+                .is_none_or(|n| p.package_name.to_lowercase().contains(n))
+        })
+        .collect();
+
+    // Category names are a convenience; a token without category read access
+    // still gets the listing, with category IDs instead.
+    let categories: HashMap<String, String> = client
+        .list_categories()
+        .await
+        .map(|all| all.into_iter().map(|c| (c.id, c.name)).collect())
+        .unwrap_or_default();
+
+    // `buffered` keeps the listing in package order.
+    stream::iter(packages)
+        .map(|package| {
+            let categories = &categories;
+            async move {
+                let digest = client.get_package_digest_snapshot(&package.id).await?;
+                Ok(PackageListing {
+                    category: categories.get(&package.category_id).cloned(),
+                    id: package.id,
+                    package_name: package.package_name,
+                    file_name: package.file_name,
+                    category_id: package.category_id,
+                    priority: package.priority,
+                    digest,
+                })
+            }
+        })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+
+    use serde_json::json;
+
+    use super::fetch_listings;
+    use crate::mock::{self, MockState, package_record};
+
+    #[tokio::test]
+    async fn digests_are_fetched_concurrently_in_package_order() {
+        let records = (1..=12)
+            .map(|i| {
+                let mut record = package_record(
+                    &i.to_string(),
+                    &format!("App {}", i),
+                    &format!("App-{}.pkg", i),
+                );
+                record["md5Hash"] = json!(format!("{:032x}", i));
+                record
+            })
+            .collect();
+        let state = Arc::new(MockState {
+            records,
+            categories: vec!["Browsers"],
+            ..MockState::default()
+        });
+        let client = mock::connect(state.clone()).await;
+
+        let listings = fetch_listings(&client, Some("app 1"), 4).await.unwrap();
+        let ids: Vec<&str> = listings.iter().map(|l| l.id.as_str()).collect();
+        assert_eq!(ids, ["1", "10", "11", "12"]);
+        assert_eq!(
+            listings[3].digest.as_ref().unwrap().md5_hash.as_deref(),
+            Some("0000000000000000000000000000000c")
+        );
+        assert_eq!(state.record_hits.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::api::token_cache::TokenCache;
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::output::{self, outcome};
+
+/// What `logout` removed, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogoutReport<'a> {
+    profile: &'a str,
+    /// Config file the profile was removed from.
+    config_file: Option<PathBuf>,
+    keyring_entries: Vec<String>,
+    cached_token: bool,
+    /// Credential variables still set in the environment.
+    environment: Vec<String>,
+}
+
+/// Remove the profile's stored credentials (and any cached token for them).
+pub fn run(global: &GlobalArgs) -> Result<()> {
+    let profile = &global.profile;
+
+    let config = global.config.as_deref();
+
+    // Read what is stored first: the token cache is keyed by URL + client ID.
+    let stored = [
+        credentials::load_file_credentials(profile, config)
+            .ok()
+            .flatten(),
+        credentials::load_keyring_credentials(profile).ok(),
+    ];
+
+    let removed_file = credentials::clear_file_credentials(profile, config)?;
+    if let Some(path) = &removed_file {
+        outcome!("Removed profile '{}' from {}.", profile, path.display());
+    }
+    // Machines using the config file often have no keyring at all.
+    let removed = match credentials::clear_credentials(profile) {
+        Ok(removed) => removed,
+        Err(e) if removed_file.is_some() => {
+            outcome!("Warning: keyring not cleared: {:#}", e);
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+    for account in &removed {
+        outcome!("Removed {} from the keyring.", account);
+    }
+    if removed.is_empty() && removed_file.is_none() {
+        outcome!("No stored credentials for profile '{}'.", profile);
+    }
+
+    let mut removed_token = false;
+    for creds in stored.iter().flatten() {
+        if TokenCache::new(&creds.url, &creds.client_id)?.remove()? {
+            outcome!("Removed cached access token.");
+            removed_token = true;
+        }
+    }
+
+    let environment = credentials::env_credentials_in_effect(profile)
+        .map(Vec::from)
+        .unwrap_or_default();
+    if !environment.is_empty() {
+        outcome!(
+            "Warning: {} are set in the environment and will still be used; unset them to log out completely.",
+            environment.join(", ")
+        );
+    }
+
+    if global.json() {
+        output::print_json(
+            &LogoutReport {
+                profile,
+                config_file: removed_file,
+                keyring_entries: removed,
+                cached_token: removed_token,
+                environment,
+            },
+            "logout result",
+        )?;
+    }
+    Ok(())
+}
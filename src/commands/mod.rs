@@ -1,2 +1,14 @@
 pub mod auth;
+pub mod compare;
+pub mod delete;
+pub mod download;
+pub mod history;
+pub mod info;
+pub mod list;
+pub mod logout;
+pub mod policies;
+pub mod reconcile;
+pub mod rollback;
 pub mod update;
+pub mod verify;
+pub mod whoami;
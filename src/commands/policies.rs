@@ -0,0 +1,48 @@
+use anyhow::Result;
+
+use crate::api::client::JamfClient;
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::models::policy::PolicySummary;
+use crate::output;
+use crate::progress::Spinner;
+
+/// List every policy's ID and name.
+pub async fn run(json: bool, global: &GlobalArgs) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Fetching policies...", global.quiet || json);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    let policies: Vec<PolicySummary> = client
+        .list_policies()
+        .await?
+        .into_iter()
+        .map(|(id, name)| PolicySummary { id, name })
+        .collect();
+    spinner.finish();
+
+    if json {
+        return output::print_json(&policies, "policies");
+    }
+
+    let id_width = policies
+        .iter()
+        .map(|p| p.id.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("ID".len());
+    println!("{:>id_width$}  NAME", "ID");
+    for policy in &policies {
+        println!("{:>id_width$}  {}", policy.id, policy.name);
+    }
+    println!();
+    println!("{} policies.", policies.len());
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageDigestSnapshot;
+use crate::api::packages::PackageTarget;
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::models::jcds::JcdsFile;
+use crate::models::package::{Package, PackageCreateRequest};
+use crate::output::{self, outcome, status};
+use crate::progress::Spinner;
+
+/// A way in which a package record disagrees with what JCDS actually stores.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Drift {
+    /// `fileName` names an object JCDS doesn't hold, but another stored
+    /// object is clearly this package's payload.
+    FileName {
+        stored: String,
+        reason: &'static str,
+    },
+    /// `fileName` is not in JCDS and nothing else matches it.
+    MissingObject,
+    /// The stored object's MD5 differs from the digest on the record.
+    StaleDigest {
+        #[serde(rename = "storedMd5")]
+        stored_md5: String,
+    },
+}
+
+/// What `reconcile` found and fixed, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReconcileReport<'a> {
+    id: &'a str,
+    package_name: &'a str,
+    file_name: &'a str,
+    digest: &'a PackageDigestSnapshot,
+    drift: &'a [Drift],
+    /// Whether `--fix` corrected the record (and requested a refresh).
+    fixed: bool,
+}
+
+/// Compare a package record against the JCDS file listing and report (or,
+/// with `fix`, correct) fileName and digest drift.
+pub async fn run(target: PackageTarget<'_>, fix: bool, global: &GlobalArgs) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    spinner.finish();
+
+    let package = client.resolve_package(target).await?;
+    status!(
+        "Package '{}' (ID: {}, file: {})",
+        package.package_name,
+        package.id,
+        package.file_name
+    );
+
+    let Some(files) = client.list_jcds_files().await? else {
+        bail!(
+            "The JCDS file listing is unavailable (on-prem distribution point or missing API \
+             permission), so there is nothing to reconcile against"
+        );
+    };
+    let digest = client
+        .get_package_digest_snapshot(&package.id)
+        .await?
+        .unwrap_or_default();
+    status!("Record digest: {}", digest.display_line());
+
+    let drift = detect_drift(&package, &digest, &files);
+    let report = |fixed| {
+        if !global.json() {
+            return Ok(());
+        }
+        output::print_json(
+            &ReconcileReport {
+                id: &package.id,
+                package_name: &package.package_name,
+                file_name: &package.file_name,
+                digest: &digest,
+                drift: &drift,
+                fixed,
+            },
+            "reconcile result",
+        )
+    };
+    if drift.is_empty() {
+        outcome!("No drift detected: the record matches the stored JCDS object.");
+        return report(false);
+    }
+
+    for d in &drift {
+        match d {
+            Drift::FileName { stored, reason } => outcome!(
+                "Drift: fileName '{}' is not in JCDS; the stored object appears to be '{}' ({}).",
+                package.file_name,
+                stored,
+                reason
+            ),
+            Drift::MissingObject => outcome!(
+                "Drift: fileName '{}' is not in JCDS and no stored object matches it. \
+                 Re-upload the package with `update` to repopulate it.",
+                package.file_name
+            ),
+            Drift::StaleDigest { stored_md5 } => outcome!(
+                "Drift: JCDS holds md5={} but the record reports md5={}.",
+                stored_md5,
+                digest.md5_hash.as_deref().unwrap_or("unknown")
+            ),
+        }
+    }
+
+    if !fix {
+        status!("Run again with --fix to correct the record.");
+        return report(false);
+    }
+
+    let mut fixed = false;
+    for d in &drift {
+        if let Drift::FileName { stored, .. } = d {
+            let req = PackageCreateRequest::from_old(&package, stored, None, None);
+            client.update_package(&package.id, &req).await?;
+            outcome!("fileName updated to '{}'.", stored);
+            fixed = true;
+        }
+    }
+    if drift
+        .iter()
+        .any(|d| matches!(d, Drift::FileName { .. } | Drift::StaleDigest { .. }))
+    {
+        client.refresh_jcds_inventory().await?;
+        outcome!("Inventory refresh requested; the digest will be recalculated.");
+        fixed = true;
+    }
+    if !fixed {
+        bail!("Nothing could be fixed automatically; re-upload the package with `update`");
+    }
+
+    report(true)
+}
+
+fn detect_drift(
+    package: &Package,
+    digest: &PackageDigestSnapshot,
+    files: &[JcdsFile],
+) -> Vec<Drift> {
+    let mut drift = Vec::new();
+
+    let stored = match files.iter().find(|f| f.file_name == package.file_name) {
+        Some(f) => Some(f),
+        None => {
+            let by_case = files
+                .iter()
+                .find(|f| f.file_name.eq_ignore_ascii_case(&package.file_name));
+            let by_md5 = || {
+                let md5 = digest.md5_hash.as_deref()?;
+                let mut matches = files.iter().filter(|f| {
+                    f.md5
+                        .as_deref()
+                        .is_some_and(|m| m.eq_ignore_ascii_case(md5))
+                });
+                let first = matches.next();
+                // Several objects with the same content can't be told apart.
+                if matches.next().is_some() {
+                    None
+                } else {
+                    first
+                }
+            };
+            match (by_case, by_md5()) {
+                (Some(f), _) => {
+                    drift.push(Drift::FileName {
+                        stored: f.file_name.clone(),
+                        reason: "same name, different case",
+                    });
+                    Some(f)
+                }
+                (None, Some(f)) => {
+                    drift.push(Drift::FileName {
+                        stored: f.file_name.clone(),
+                        reason: "MD5 matches the record",
+                    });
+                    Some(f)
+                }
+                (None, None) => {
+                    drift.push(Drift::MissingObject);
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(stored_md5) = stored.and_then(|f| f.md5.as_deref())
+        && !digest
+            .md5_hash
+            .as_deref()
+            .is_some_and(|m| m.eq_ignore_ascii_case(stored_md5))
+    {
+        drift.push(Drift::StaleDigest {
+            stored_md5: stored_md5.to_string(),
+        });
+    }
+
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{Drift, detect_drift};
+    use crate::mock::{self, MockState, package_record};
+
+    const MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+
+    /// Drift for a record `App.pkg` with [`MD5`] against the given JCDS listing.
+    async fn drift_against(files: serde_json::Value) -> Vec<Drift> {
+        let mut record = package_record("1", "App", "App.pkg");
+        record["md5Hash"] = json!(MD5);
+        let state = Arc::new(MockState {
+            records: vec![record],
+            jcds_files: Some(files),
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+
+        let package = client.get_package("1").await.unwrap();
+        let digest = client
+            .get_package_digest_snapshot("1")
+            .await
+            .unwrap()
+            .unwrap();
+        let files = client.list_jcds_files().await.unwrap().unwrap();
+        detect_drift(&package, &digest, &files)
+    }
+
+    #[tokio::test]
+    async fn detects_file_name_and_digest_drift() {
+        let drift = drift_against(json!([{ "fileName": "App.pkg", "md5": MD5 }])).await;
+        assert!(drift.is_empty(), "{:?}", drift);
+
+        let drift = drift_against(json!([{ "fileName": "app.PKG", "md5": MD5 }])).await;
+        assert!(
+            matches!(drift.as_slice(), [Drift::FileName { stored, .. }] if stored == "app.PKG"),
+            "{:?}",
+            drift
+        );
+
+        let drift = drift_against(json!([
+            { "fileName": "Other.pkg", "md5": "ffffffffffffffffffffffffffffffff" },
+            { "fileName": "App-1.pkg", "md5": MD5 },
+        ]))
+        .await;
+        assert!(
+            matches!(drift.as_slice(), [Drift::FileName { stored, .. }] if stored == "App-1.pkg"),
+            "{:?}",
+            drift
+        );
+
+        let drift = drift_against(json!([
+            { "fileName": "App.pkg", "md5": "ffffffffffffffffffffffffffffffff" }
+        ]))
+        .await;
+        assert!(
+            matches!(drift.as_slice(), [Drift::StaleDigest { .. }]),
+            "{:?}",
+            drift
+        );
+
+        let drift = drift_against(json!([])).await;
+        assert!(
+            matches!(drift.as_slice(), [Drift::MissingObject]),
+            "{:?}",
+            drift
+        );
+    }
+}
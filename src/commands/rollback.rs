@@ -0,0 +1,234 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::api::client::JamfClient;
+use crate::api::packages::PackageTarget;
+use crate::api::packages::{PackageDigestSnapshot, UploadOptions};
+use crate::backups::{self, Backup};
+use crate::checksum::{compute_file_digest, compute_file_digests};
+use crate::cli::GlobalArgs;
+use crate::commands::update::{
+    DigestOutcome, DigestPolling, wait_for_digest_availability, wait_for_digest_change,
+};
+use crate::credentials;
+use crate::models::package::{Package, PackageCreateRequest};
+use crate::output::{self, outcome, status};
+use crate::progress::{Spinner, TransferProgress};
+use crate::prompt;
+
+const DIGEST_WAIT: Duration = Duration::from_secs(300);
+const DIGEST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What `rollback` did, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RollbackReport<'a> {
+    id: &'a str,
+    package_name: &'a str,
+    /// The file name the package had before the rollback.
+    replaced_file_name: &'a str,
+    /// The file name restored from the backup.
+    file_name: &'a str,
+    /// Unix time the backup was taken.
+    backup_saved_at: u64,
+    /// False when the confirmation prompt was declined.
+    restored: bool,
+    /// False when Jamf already had the backed-up file and only the record was
+    /// restored.
+    uploaded: bool,
+    digest: Option<PackageDigestSnapshot>,
+}
+
+/// Restore a package (found by exact name or ID) to the file and record saved
+/// by `update --keep-previous`, and check Jamf's digest of the restored file.
+pub async fn run(
+    target: PackageTarget<'_>,
+    backup_dir: Option<&Path>,
+    yes: bool,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    spinner.finish();
+
+    let package = client.resolve_package(target).await?;
+    let root = backups::backup_root(backup_dir)?;
+    let dir = backups::package_dir(&root, &client.base_url, &package.id);
+    let Some(backup) = backups::load(&dir)? else {
+        bail!(
+            "No backup of package '{}' (ID: {}) in {}; backups are saved by `update --keep-previous`",
+            package.package_name,
+            package.id,
+            root.display()
+        );
+    };
+    let file = backup.file_in(&dir)?;
+    if !file.is_file() {
+        bail!("Backup is incomplete: {} is missing", file.display());
+    }
+    status!(
+        "Package '{}' (ID: {}) currently uses {}; the backup has {}.",
+        package.package_name,
+        package.id,
+        package.file_name,
+        backup.package.file_name
+    );
+
+    let report = |restored, uploaded, digest| {
+        if !global.json() {
+            return Ok(());
+        }
+        output::print_json(
+            &RollbackReport {
+                id: &package.id,
+                package_name: &package.package_name,
+                replaced_file_name: &package.file_name,
+                file_name: &backup.package.file_name,
+                backup_saved_at: backup.saved_at,
+                restored,
+                uploaded,
+                digest,
+            },
+            "rollback result",
+        )
+    };
+
+    if !yes {
+        if !prompt::is_interactive() {
+            bail!(
+                "Refusing to roll back without confirmation; pass --yes to roll back non-interactively"
+            );
+        }
+        if !prompt::confirm(&format!(
+            "Replace {} with the backed-up {} in package '{}'?",
+            package.file_name, backup.package.file_name, package.package_name
+        ))? {
+            outcome!("Nothing changed.");
+            return report(false, false, None);
+        }
+    }
+
+    let current = client.get_package_digest_snapshot(&package.id).await?;
+    let already_there = package.file_name == backup.package.file_name
+        && match current.as_ref().and_then(|d| d.comparable_digest()) {
+            Some((algorithm, remote)) => {
+                remote.eq_ignore_ascii_case(&compute_file_digest(&file, algorithm).await?)
+            }
+            None => false,
+        };
+
+    let spinner = Spinner::start("Restoring package record...", global.quiet);
+    let restored = client
+        .update_package(
+            &package.id,
+            &PackageCreateRequest::from_old(&backup.package, &backup.package.file_name, None, None),
+        )
+        .await;
+    spinner.finish();
+    restored?;
+
+    if already_there {
+        outcome!(
+            "Package '{}' (ID: {}) already has the backed-up file; its record was restored.",
+            package.package_name,
+            package.id
+        );
+        return report(true, false, current);
+    }
+
+    let digest = restore_file(&client, &package, &backup, &file, current, global.quiet).await?;
+    outcome!(
+        "Package '{}' (ID: {}) rolled back to {}.",
+        package.package_name,
+        package.id,
+        backup.package.file_name
+    );
+    report(true, true, Some(digest))
+}
+
+/// Replace the package's JCDS file with the backed-up one and wait until Jamf
+/// reports a digest that matches it.
+async fn restore_file(
+    client: &JamfClient,
+    package: &Package,
+    backup: &Backup,
+    file: &Path,
+    current: Option<PackageDigestSnapshot>,
+    quiet: bool,
+) -> Result<PackageDigestSnapshot> {
+    // An upload is a no-op while a file of that name exists, so clear both
+    // the current file and any stale copy under the backed-up name.
+    if let Some(files) = client.list_jcds_files().await? {
+        let others = client.list_packages().await?;
+        let mut names = vec![
+            package.file_name.as_str(),
+            backup.package.file_name.as_str(),
+        ];
+        names.dedup();
+        for name in names {
+            let shared = others
+                .iter()
+                .any(|p| p.id != package.id && p.file_name == name);
+            if shared || !files.iter().any(|f| f.file_name == name) {
+                continue;
+            }
+            status!("Removing JCDS file {}...", name);
+            client.delete_jcds_file(name).await?;
+        }
+    }
+
+    status!("Uploading {}...", backup.package.file_name);
+    client
+        .upload_package(
+            &package.id,
+            file,
+            &UploadOptions {
+                progress: TransferProgress::upload(quiet),
+                ..UploadOptions::default()
+            },
+        )
+        .await?;
+    client.refresh_jcds_inventory().await?;
+
+    status!("Waiting for Jamf to process the restored file...");
+    let local = compute_file_digests(file)
+        .await
+        .context("Failed to hash the backed-up file")?;
+    let polling = DigestPolling::new(DIGEST_WAIT, DIGEST_INTERVAL);
+    let digest = match current.filter(|d| d.has_verifiable_content()) {
+        Some(previous) => {
+            match wait_for_digest_change(client, &package.id, &previous, &local, &polling).await? {
+                DigestOutcome::Changed(d) | DigestOutcome::UnchangedButContentMatches(d, _) => d,
+                DigestOutcome::Stale(_) => bail!(
+                    "Restored file uploaded, but Jamf's digest did not change within {} seconds",
+                    DIGEST_WAIT.as_secs()
+                ),
+            }
+        }
+        None => wait_for_digest_availability(client, &package.id, &polling).await?,
+    };
+
+    if let Some((algorithm, remote)) = digest.comparable_digest() {
+        let local = local.of(algorithm);
+        if !remote.eq_ignore_ascii_case(local) {
+            bail!(
+                "Jamf's {} of the restored package ({}) does not match the backup ({})",
+                algorithm,
+                remote,
+                local
+            );
+        }
+    }
+    Ok(digest)
+}
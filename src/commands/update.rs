@@ -1,419 +1,2892 @@
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{Context, Result, bail};
-use md5::{Digest, Md5};
-use tokio::io::AsyncReadExt;
+use anyhow::{Context, Result, anyhow, bail};
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::OnceCell;
 use tokio::time::sleep;
+use tracing::debug;
 
-use crate::api::client::JamfClient;
-use crate::api::packages::PackageDigestSnapshot;
-use crate::credentials;
-use crate::models::package::PackageCreateRequest;
+use crate::api::client::{AuthRejected, JamfClient, is_network_error};
+use crate::api::distribution::{ApiUploadBackend, JcdsBackend, UploadBackend};
+use crate::api::packages::{
+    AmbiguousPackage, InventoryRefreshUnavailable, PackageDigestSnapshot, PackageTarget,
+    UploadOptions,
+};
+use crate::api::policies::{
+    PackageMatch, PackageReference, PolicyReadForbidden, ScanOptions, policy_file_name,
+    rewrite_package_configuration,
+};
+use crate::backups;
+use crate::checksum::{
+    ChecksumFile, FileDigests, HashType, compute_file_digest, compute_file_digests,
+    compute_file_md5, read_checksum_files, write_checksum_files,
+};
+use crate::cli::{GlobalArgs, SuccessCriterion, UpdateArgs, VersionField};
+use crate::credentials::{self, Credentials};
+use crate::files::{
+    InstallerExtensions, ZippedBundle, check_installer_signature, collect_all_files, zip_if_bundle,
+};
+use crate::history::{self, HistoryEntry};
+use crate::junit;
+use crate::models::package::{Package, PackageCreateRequest, version_line, with_version_line};
+use crate::models::patch::{PatchPolicyReference, PatchTitleReference};
+use crate::models::policy::AffectedPolicy;
+use crate::naming::{NameTemplate, resolve_package_name};
+use crate::output::{self, outcome, status};
+use crate::pkginfo;
+use crate::progress::{self, Spinner, TransferProgress};
+use crate::prompt;
+use crate::redact;
 
-const DEFAULT_DIGEST_WAIT_TIMEOUT: Duration = Duration::from_secs(300);
-const DIGEST_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Pause between retries when a digest poll hits a network error.
+const NETWORK_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const SEARCH_CONSISTENCY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Consecutive network failures tolerated for a single digest poll before
+/// giving up (two minutes at the retry interval).
+const MAX_POLL_NETWORK_RETRIES: usize = 24;
 
-pub async fn run(
+/// What an update did for one file. Rendered as JSON with `--output json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateOutcome {
+    pub file: PathBuf,
+    pub package_name: String,
+    pub action: UpdateAction,
+    /// The action is what would have happened; nothing was changed.
+    pub dry_run: bool,
+    pub package_id: Option<String>,
+    /// Account behind the API token.
+    pub identity: Option<String>,
+    /// Digest Jamf reported before the update.
+    pub previous_digest: Option<PackageDigestSnapshot>,
+    /// Digest Jamf reported once the upload was processed.
+    pub new_digest: Option<PackageDigestSnapshot>,
+    pub local_md5: Option<String>,
+    pub local_sha512: Option<String>,
+    pub remote_md5: Option<String>,
+    /// The SHA-2/SHA-3 algorithm whose digest, as Jamf computed it after the
+    /// upload, was checked against the uploaded file.
+    pub verified_hash: Option<String>,
+    pub affected_policies: Vec<AffectedPolicy>,
+    /// IDs of the policies whose logs `--flush-policies` flushed.
+    pub flushed_policies: Vec<i64>,
+    /// Patch Management title versions that install the package.
+    pub patch_titles: Vec<PatchTitleReference>,
+    /// Patch policies deploying one of those versions.
+    pub patch_policies: Vec<PatchPolicyReference>,
+    pub error: Option<String>,
+    /// With `--dry-run`, each change that would have been made.
+    pub planned_changes: Vec<String>,
+    /// With `--print-result-record`, the package record as Jamf reports it
+    /// after the run. Printed as its own JSON document, not in this result.
+    #[serde(skip)]
+    pub result_record: Option<Value>,
+    /// Wall-clock time spent on this file, in milliseconds.
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateAction {
+    Created,
+    Updated,
+    /// Jamf already had the same payload.
+    Skipped,
+    Failed,
+}
+
+impl UpdateOutcome {
+    pub(crate) fn new(path: &Path, package_name: &str) -> Self {
+        Self {
+            file: path.to_path_buf(),
+            package_name: package_name.to_string(),
+            action: UpdateAction::Failed,
+            dry_run: false,
+            package_id: None,
+            identity: None,
+            previous_digest: None,
+            new_digest: None,
+            local_md5: None,
+            local_sha512: None,
+            remote_md5: None,
+            verified_hash: None,
+            affected_policies: Vec::new(),
+            flushed_policies: Vec::new(),
+            patch_titles: Vec::new(),
+            patch_policies: Vec::new(),
+            error: None,
+            planned_changes: Vec::new(),
+            result_record: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// Report and record a change `--dry-run` would have made.
+    fn plan(&mut self, change: String) {
+        status!("Would {}.", change);
+        self.planned_changes.push(change);
+    }
+
+    fn fail(&mut self, err: &anyhow::Error) {
+        self.action = UpdateAction::Failed;
+        self.error = Some(redact::scrub(&format!("{:#}", err)));
+    }
+
+    fn label(&self) -> &'static str {
+        self.action.label()
+    }
+}
+
+impl UpdateAction {
+    /// Upper-case name for summaries, e.g. "UPDATED".
+    pub fn label(self) -> &'static str {
+        match self {
+            UpdateAction::Created => "CREATED",
+            UpdateAction::Updated => "UPDATED",
+            UpdateAction::Skipped => "SKIPPED",
+            UpdateAction::Failed => "FAILED",
+        }
+    }
+}
+
+/// Fail when any file in `outcomes` failed; a batch reports every file before
+/// its exit status reflects the failures.
+pub fn ensure_succeeded(outcomes: &[UpdateOutcome], fail_on_unchanged: bool) -> Result<()> {
+    let failed = outcomes
+        .iter()
+        .filter(|o| o.action == UpdateAction::Failed)
+        .count();
+    if failed > 0 {
+        bail!("{} of {} file(s) failed to update", failed, outcomes.len());
+    }
+    if fail_on_unchanged && outcomes.iter().all(|o| o.action == UpdateAction::Skipped) {
+        return Err(Unchanged.into());
+    }
+    Ok(())
+}
+
+/// `--fail-on-unchanged`: every file already matched Jamf, so nothing was
+/// uploaded.
+#[derive(Debug, thiserror::Error)]
+#[error("Nothing was uploaded: Jamf already has every file (--fail-on-unchanged)")]
+pub struct Unchanged;
+
+impl Unchanged {
+    /// Distinct from the failure codes of [`crate::api::error::JamfError`].
+    pub const EXIT_CODE: i32 = 10;
+}
+
+pub async fn run(args: &UpdateArgs, global: &GlobalArgs) -> Result<Vec<UpdateOutcome>> {
+    let result = if args.single_file() {
+        run_single(args, global, &args.paths[0])
+            .await
+            .map(|outcome| vec![outcome])
+    } else {
+        run_batch(args, global).await
+    };
+    if !args.dry_run {
+        record_history(args, global, &result);
+    }
+    result
+}
+
+/// Append the run to the local update history (see [`crate::history`]). A
+/// run that failed before reaching its files is recorded once per path.
+fn record_history(args: &UpdateArgs, global: &GlobalArgs, result: &Result<Vec<UpdateOutcome>>) {
+    let entries: Vec<HistoryEntry> = match result {
+        Ok(outcomes) => outcomes
+            .iter()
+            .map(|o| HistoryEntry::from_outcome(o, &global.profile))
+            .collect(),
+        Err(e) => args
+            .paths
+            .iter()
+            .map(|path| {
+                let name = resolve_package_name(path, args.name.as_deref(), None);
+                let mut outcome = UpdateOutcome::new(path, &name.unwrap_or_default());
+                outcome.fail(e);
+                HistoryEntry::from_outcome(&outcome, &global.profile)
+            })
+            .collect(),
+    };
+    if let Err(e) = history::history_path(global.history_file.as_deref())
+        .and_then(|path| history::append(&path, &entries))
+    {
+        outcome!(
+            "Warning: the update was not recorded in the history: {:#}",
+            e
+        );
+    }
+}
+
+/// One local installer and the package record it updates.
+struct FileJob {
+    /// The path given on the command line.
+    source: PathBuf,
+    /// The file uploaded: the installer itself, or the zip of a bundle.
+    path: PathBuf,
+    file_name: String,
+    package_name: String,
+    /// Keeps a zipped bundle on disk until the job is done.
+    zipped: Option<ZippedBundle>,
+    /// Every digest of `path`, once something needed them all.
+    digests: OnceCell<FileDigests>,
+    /// Set before the first change to Jamf (record, stored file), after
+    /// which the update can no longer simply be run again from the start.
+    wrote: AtomicBool,
+}
+
+impl FileJob {
+    /// A bundle is zipped here; its package name still comes from the bundle
+    /// (`App.app` is uploaded as `App.app.zip` to package `App`).
+    async fn new(path: &Path, args: &UpdateArgs, template: Option<&NameTemplate>) -> Result<Self> {
+        let package_name = resolve_package_name(path, args.name.as_deref(), template)?;
+        let zipped = zip_if_bundle(path).await?;
+        let source = path.to_path_buf();
+        let path = zipped.as_ref().map_or(path, |z| &z.path).to_path_buf();
+        let file_name = path
+            .file_name()
+            .context("Invalid file path")?
+            .to_string_lossy()
+            .to_string();
+        Ok(Self {
+            source,
+            path,
+            file_name,
+            package_name,
+            zipped,
+            digests: OnceCell::new(),
+            wrote: AtomicBool::new(false),
+        })
+    }
+
+    /// [`FileDigests`] of the uploaded file, computed on first use.
+    async fn digests(&self) -> Result<&FileDigests> {
+        self.digests
+            .get_or_try_init(|| compute_file_digests(&self.path))
+            .await
+    }
+
+    /// Note that Jamf is about to be changed for this file.
+    fn start_writing(&self) {
+        self.wrote.store(true, Ordering::Relaxed);
+    }
+
+    fn has_written(&self) -> bool {
+        self.wrote.load(Ordering::Relaxed)
+    }
+
+    /// Where `--write-checksums` keeps the checksum files: next to the
+    /// installer, or next to a zipped bundle as `<bundle>.zip.sha256`.
+    fn checksum_base(&self) -> PathBuf {
+        self.source.with_file_name(&self.file_name)
+    }
+}
+
+fn name_template(args: &UpdateArgs) -> Result<Option<NameTemplate>> {
+    args.name_template
+        .as_deref()
+        .map(|t| NameTemplate::new(t, args.name_pattern.as_deref()))
+        .transpose()
+}
+
+async fn run_single(args: &UpdateArgs, global: &GlobalArgs, path: &Path) -> Result<UpdateOutcome> {
+    let started = Instant::now();
+
+    // 1. Resolve package name
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    let job = match name_template(args) {
+        Ok(template) => FileJob::new(path, args, template.as_ref()).await,
+        Err(e) => Err(e),
+    };
+
+    let result = match &job {
+        Ok(job) => update(args, global, job).await,
+        Err(e) => Err(anyhow!("{:#}", e)),
+    }
+    .map(|mut outcome| {
+        outcome.duration_ms = started.elapsed().as_millis() as u64;
+        outcome
+    });
+
+    let Some(report) = &args.junit else {
+        return result;
+    };
+    let case = junit::TestCase {
+        name: file_name,
+        classname: job.map(|j| j.package_name).unwrap_or_default(),
+        time: started.elapsed(),
+        outcome: match &result {
+            Ok(_) => junit::CaseOutcome::Passed,
+            Err(e) => junit::CaseOutcome::Failed(format!("{:#}", e)),
+        },
+        output: None,
+    };
+    match (result, junit::write_report(report, "update", &[case])) {
+        (Err(e), Err(report_err)) => {
+            outcome!("Warning: {:#}", report_err);
+            Err(e)
+        }
+        (result, report_result) => report_result.and(result),
+    }
+}
+
+async fn update(args: &UpdateArgs, global: &GlobalArgs, job: &FileJob) -> Result<UpdateOutcome> {
+    let path = job.path.as_path();
+
+    // Validate file extension
+    let extensions = InstallerExtensions::new(&args.allow_extension);
+    extensions.validate(path)?;
+
+    check_criterion_compatibility(args)?;
+
+    if !path.exists() {
+        bail!("File not found: {}", path.display());
+    }
+    if !args.skip_validation {
+        check_installer_signature(path)?;
+    }
+    verify_local_digests(job, args).await?;
+
+    status!("Package name: {}", job.package_name);
+    status!("File: {}", path.display());
+    extensions.warn_if_non_standard(&[path.to_path_buf()]);
+
+    if args.check_embedded_version {
+        check_embedded_version(path, &job.package_name).await;
+    }
+
+    // 2. Load credentials
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+
+    match execute(&creds, args, global, job).await {
+        Err(e) if is_auth_rejection(&e) && job.has_written() => {
+            // Starting over would repeat changes against a half-updated
+            // package (a second record, a removed file re-removed).
+            Err(e.context(format!(
+                "Authentication was rejected part-way through the update; package '{}' may be \
+                 left partly updated in Jamf. Check it with `info`, then re-run the update",
+                job.package_name
+            )))
+        }
+        Err(e) if is_auth_rejection(&e) => {
+            // The secret may have been rotated after we loaded it.  Re-read the
+            // credential source and retry once, but only if something changed —
+            // retrying with identical credentials would just fail again.
+            // Nothing was changed in Jamf yet, so the update can start over.
+            let reloaded =
+                credentials::load_credentials(&global.profile, global.config.as_deref())?;
+            if reloaded == creds {
+                return Err(e);
+            }
+            status!("Authentication was rejected; credentials were reloaded from their source.");
+            status!("Retrying update with reloaded credentials...");
+            execute(&reloaded, args, global, job).await
+        }
+        result => result,
+    }
+}
+
+async fn execute(
+    creds: &Credentials,
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    job: &FileJob,
+) -> Result<UpdateOutcome> {
+    let session = Session::connect(creds, args, global).await?;
+    let mut outcome = UpdateOutcome::new(&job.source, &job.package_name);
+    outcome.identity = Some(session.identity.clone());
+    match stage_file(&session, args, global, job, &mut outcome).await? {
+        Staged::Done => {}
+        Staged::Uploaded(pending) => {
+            let refreshed = refresh_inventory(&session).await?;
+            verify_upload(&session, args, job, *pending, refreshed, &mut outcome).await?;
+        }
+    }
+    write_checksums(args, job, &outcome).await;
+    Ok(outcome)
+}
+
+/// With `--write-checksums`, bring the checksum files next to a file Jamf
+/// now holds up to date. A failure only warns: the update itself succeeded.
+async fn write_checksums(args: &UpdateArgs, job: &FileJob, outcome: &UpdateOutcome) {
+    if !args.write_checksums || outcome.dry_run || outcome.action == UpdateAction::Failed {
+        return;
+    }
+    let written = match job.digests().await {
+        Ok(digests) => write_checksum_files(&job.checksum_base(), digests),
+        Err(e) => Err(e),
+    };
+    match written {
+        Ok(paths) => {
+            for path in paths {
+                status!("Wrote {}.", path.display());
+            }
+        }
+        Err(e) => outcome!("Warning: checksum files were not written: {:#}", e),
+    }
+}
+
+/// Process a directory (or several paths) in one authenticated session.
+/// Every file is uploaded first, then a single inventory refresh covers them
+/// all before each upload is verified. Failures are recorded per file.
+async fn run_batch(args: &UpdateArgs, global: &GlobalArgs) -> Result<Vec<UpdateOutcome>> {
+    check_criterion_compatibility(args)?;
+    let extensions = InstallerExtensions::new(&args.allow_extension);
+    let files = collect_all_files(&args.paths, &extensions)?;
+    extensions.warn_if_non_standard(&files);
+    if args.name.is_some() && files.len() > 1 {
+        bail!("--name cannot be used when updating several files; use --name-template");
+    }
+    if args.id.is_some() && files.len() > 1 {
+        bail!("--id names a single package record; it cannot be used when updating several files");
+    }
+    if !args.reassign_from.is_empty() && files.len() > 1 {
+        bail!(
+            "--reassign-from names the records a single file replaces; it cannot be used when updating several files"
+        );
+    }
+    if files.len() > 1 && (args.expected_md5.is_some() || args.expected_sha512.is_some()) {
+        bail!("--expected-md5/--expected-sha512 describe a single file");
+    }
+    if args.jobs > 1 && !args.between_files_delay.is_zero() {
+        bail!("--between-files-delay paces files one at a time; it cannot be used with --jobs");
+    }
+    if args.jobs > 1 && args.flush_policies && !args.yes && !args.dry_run {
+        bail!(
+            "--flush-policies with --jobs needs --yes: prompts are off while files run in parallel"
+        );
+    }
+    let template = name_template(args)?;
+
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+    let session = match Session::connect(&creds, args, global).await {
+        Err(e) if is_auth_rejection(&e) => {
+            let reloaded =
+                credentials::load_credentials(&global.profile, global.config.as_deref())?;
+            if reloaded == creds {
+                return Err(e);
+            }
+            status!("Authentication was rejected; credentials were reloaded from their source.");
+            Session::connect(&reloaded, args, global).await?
+        }
+        result => result?,
+    };
+
+    status!("Updating {} file(s)...", files.len());
+    let jobs = args.jobs.min(files.len()).max(1);
+    if jobs > 1 {
+        status!("Processing up to {} files at once.", jobs);
+    }
+    let done = AtomicUsize::new(0);
+    let total = files.len();
+    let mut results: Vec<(usize, BatchResult)> = stream::iter(files.iter().enumerate())
+        .map(|(i, file)| {
+            let (session, template, done) = (&session, template.as_ref(), &done);
+            async move {
+                let entry = BatchEntry {
+                    index: i,
+                    total,
+                    path: file,
+                };
+                let result = if jobs > 1 {
+                    output::as_worker(
+                        entry.label(),
+                        stage_batch_entry(session, args, global, template, &entry),
+                    )
+                    .await
+                } else {
+                    stage_batch_entry(session, args, global, template, &entry).await
+                };
+                if jobs > 1 {
+                    let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    status!(
+                        "[{}/{}] {}: {}",
+                        finished,
+                        total,
+                        result.file_name(),
+                        result.stage_label()
+                    );
+                }
+                (i, result)
+            }
+        })
+        .buffer_unordered(jobs)
+        .collect()
+        .await;
+    results.sort_by_key(|(i, _)| *i);
+    let mut results: Vec<BatchResult> = results.into_iter().map(|(_, r)| r).collect();
+
+    if results.iter().any(|r| r.pending.is_some()) {
+        status!();
+        // One refresh covers every upload in the batch.
+        let refreshed = match refresh_inventory(&session).await {
+            Ok(refreshed) => refreshed,
+            Err(e) => {
+                for result in &mut results {
+                    if result.pending.take().is_some() {
+                        result
+                            .outcome
+                            .fail(&anyhow!("Inventory refresh failed: {:#}", e));
+                    }
+                }
+                false
+            }
+        };
+        stream::iter(results.iter_mut().filter(|r| r.pending.is_some()))
+            .map(|result| {
+                let session = &session;
+                async move {
+                    let label = result.file_name();
+                    if jobs > 1 {
+                        output::as_worker(
+                            label,
+                            verify_batch_entry(session, args, result, refreshed),
+                        )
+                        .await
+                    } else {
+                        verify_batch_entry(session, args, result, refreshed).await
+                    }
+                }
+            })
+            .buffer_unordered(jobs)
+            .collect::<Vec<()>>()
+            .await;
+    }
+
+    print_batch_summary(&results);
+
+    if let Some(report) = &args.junit {
+        let cases: Vec<junit::TestCase> = results.iter().map(BatchResult::junit_case).collect();
+        junit::write_report(report, "update", &cases)?;
+    }
+
+    Ok(results
+        .into_iter()
+        .map(|r| UpdateOutcome {
+            duration_ms: r.elapsed.as_millis() as u64,
+            ..r.outcome
+        })
+        .collect())
+}
+
+/// One file of a batch and its place in it.
+struct BatchEntry<'a> {
+    index: usize,
+    total: usize,
+    path: &'a Path,
+}
+
+impl BatchEntry<'_> {
+    /// The prefix of this file's lines when files run in parallel.
+    fn label(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+/// Stage one file of a batch, recording a failure in its result rather than
+/// stopping the batch.
+async fn stage_batch_entry(
+    session: &Session,
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    template: Option<&NameTemplate>,
+    entry: &BatchEntry<'_>,
+) -> BatchResult {
+    if entry.index > 0 && !args.between_files_delay.is_zero() {
+        sleep(args.between_files_delay).await;
+    }
+    status!();
+    status!(
+        "==> [{}/{}] {}",
+        entry.index + 1,
+        entry.total,
+        entry.path.display()
+    );
+
+    let started = Instant::now();
+    let job = FileJob::new(entry.path, args, template).await;
+    let package_name = job
+        .as_ref()
+        .map(|j| j.package_name.clone())
+        .unwrap_or_default();
+    let mut outcome = UpdateOutcome::new(entry.path, &package_name);
+    outcome.identity = Some(session.identity.clone());
+    let staged = match &job {
+        Ok(job) => stage_batch_file(session, args, global, job, &mut outcome).await,
+        Err(e) => Err(anyhow!("{:#}", e)),
+    };
+    let pending = match (job, staged) {
+        (Ok(job), Ok(Staged::Uploaded(pending))) => Some(Box::new((job, *pending))),
+        (Ok(job), Ok(Staged::Done)) => {
+            write_checksums(args, &job, &outcome).await;
+            None
+        }
+        (_, Ok(_)) => None,
+        (_, Err(e)) => {
+            outcome!("Error: {}", redact::scrub(&format!("{:#}", e)));
+            outcome.fail(&e);
+            None
+        }
+    };
+    BatchResult {
+        outcome,
+        elapsed: started.elapsed(),
+        pending,
+    }
+}
+
+/// The per-file local checks `update` does for a single file, then staging.
+async fn stage_batch_file(
+    session: &Session,
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    job: &FileJob,
+    outcome: &mut UpdateOutcome,
+) -> Result<Staged> {
+    if !args.skip_validation {
+        check_installer_signature(&job.path)?;
+    }
+    verify_local_digests(job, args).await?;
+    status!("Package name: {}", job.package_name);
+    if args.check_embedded_version {
+        check_embedded_version(&job.path, &job.package_name).await;
+    }
+    stage_file(session, args, global, job, outcome).await
+}
+
+/// Verify one uploaded file of a batch after the shared inventory refresh.
+async fn verify_batch_entry(
+    session: &Session,
+    args: &UpdateArgs,
+    result: &mut BatchResult,
+    refreshed: bool,
+) {
+    let Some(staged) = result.pending.take() else {
+        return;
+    };
+    let (job, pending) = *staged;
+    status!();
+    status!("==> Verifying {}", job.file_name);
+    let started = Instant::now();
+    let verified =
+        verify_upload(session, args, &job, pending, refreshed, &mut result.outcome).await;
+    result.elapsed += started.elapsed();
+    match verified {
+        Ok(()) => write_checksums(args, &job, &result.outcome).await,
+        Err(e) => {
+            outcome!("Error: {}", redact::scrub(&format!("{:#}", e)));
+            result.outcome.fail(&e);
+        }
+    }
+}
+
+struct BatchResult {
+    outcome: UpdateOutcome,
+    elapsed: Duration,
+    /// Uploaded; verification runs after the shared inventory refresh.
+    pending: Option<Box<(FileJob, PendingVerification)>>,
+}
+
+impl BatchResult {
+    fn file_name(&self) -> String {
+        self.outcome
+            .file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Where the file stands once staged, for the parallel progress lines.
+    fn stage_label(&self) -> &'static str {
+        match &self.outcome {
+            _ if self.pending.is_some() => "uploaded, verifying after the inventory refresh",
+            outcome if outcome.error.is_some() => "failed",
+            outcome if outcome.dry_run => "dry run",
+            outcome if outcome.action == UpdateAction::Skipped => "up to date",
+            _ => "done",
+        }
+    }
+
+    fn junit_case(&self) -> junit::TestCase {
+        junit::TestCase {
+            name: self.file_name(),
+            classname: self.outcome.package_name.clone(),
+            time: self.elapsed,
+            outcome: match &self.outcome.error {
+                Some(message) => junit::CaseOutcome::Failed(message.clone()),
+                None => junit::CaseOutcome::Passed,
+            },
+            output: Some(self.outcome.label().to_string()),
+        }
+    }
+}
+
+fn print_batch_summary(results: &[BatchResult]) {
+    let file_width = results
+        .iter()
+        .map(|r| r.file_name().len())
+        .max()
+        .unwrap_or(0)
+        .max("FILE".len());
+
+    outcome!();
+    outcome!("{:<10} {:<file_width$} PACKAGE", "STATUS", "FILE");
+    for result in results {
+        let outcome = &result.outcome;
+        let detail = match &outcome.error {
+            Some(message) => format!("{} ({})", outcome.package_name, message),
+            None if outcome.dry_run => format!("{} (dry run)", outcome.package_name),
+            None => outcome.package_name.clone(),
+        };
+        outcome!(
+            "{:<10} {:<file_width$} {}",
+            outcome.label(),
+            result.file_name(),
+            detail
+        );
+    }
+
+    let count = |action| {
+        results
+            .iter()
+            .filter(|r| r.outcome.action == action)
+            .count()
+    };
+    outcome!();
+    outcome!(
+        "{} created, {} updated, {} skipped, {} failed.",
+        count(UpdateAction::Created),
+        count(UpdateAction::Updated),
+        count(UpdateAction::Skipped),
+        count(UpdateAction::Failed)
+    );
+}
+
+/// Authenticated client plus the per-run lookups every file shares.
+struct Session {
+    client: JamfClient,
+    identity: String,
+    category_id: Option<String>,
+    /// In a dry run, the --category that --create-category would create.
+    new_category: Option<String>,
+    backend: Box<dyn UploadBackend>,
+}
+
+impl Session {
+    async fn connect(creds: &Credentials, args: &UpdateArgs, global: &GlobalArgs) -> Result<Self> {
+        status!("Jamf Pro URL: {}", creds.url);
+
+        // 3. Authenticate
+        let spinner = Spinner::start("Authenticating...", global.quiet);
+        let client = JamfClient::connect(
+            &creds.url,
+            &creds.client_id,
+            &creds.client_secret,
+            &global.connect_options_for(creds),
+        )
+        .await?;
+        let identity = client.identity().await?;
+        spinner.finish();
+        status!("Authenticated as {}.", identity);
+        if let Ok(version) = client.jamf_version().await {
+            status!("Jamf Pro version: {}", version);
+        }
+
+        // Resolve the category up front so a typo fails before anything changes.
+        let mut new_category = None;
+        let category_id = match args.category.as_deref() {
+            Some(name) if args.create_category && args.dry_run => {
+                match client
+                    .list_categories()
+                    .await?
+                    .into_iter()
+                    .find(|c| c.name == name)
+                {
+                    Some(category) => {
+                        status!("Category: {} (ID: {})", category.name, category.id);
+                        Some(category.id)
+                    }
+                    None => {
+                        status!("Would create category '{}'.", name);
+                        new_category = Some(name.to_string());
+                        None
+                    }
+                }
+            }
+            Some(name) if args.create_category => {
+                let (category, created) = client.resolve_or_create_category(name).await?;
+                if created {
+                    outcome!(
+                        "Created category '{}' (ID: {}).",
+                        category.name,
+                        category.id
+                    );
+                } else {
+                    status!("Category: {} (ID: {})", category.name, category.id);
+                }
+                Some(category.id)
+            }
+            Some(name) => {
+                let category = client.resolve_category(name).await?;
+                status!("Category: {} (ID: {})", category.name, category.id);
+                Some(category.id)
+            }
+            None => None,
+        };
+
+        let backend: Box<dyn UploadBackend> = match &args.distribution_point {
+            Some(target) => target.backend()?,
+            None => {
+                let distribution_point = client.detect_distribution_point().await?;
+                if distribution_point.is_jcds() {
+                    Box::new(JcdsBackend)
+                } else {
+                    if args.success_when.is_some() || args.require_strong_hash {
+                        bail!(
+                            "This instance distributes from a {}, where Jamf computes no \
+                             digest; --success-when and --require-strong-hash need JCDS",
+                            distribution_point
+                        );
+                    }
+                    status!(
+                        "Principal distribution point is a {}; skipping the JCDS inventory \
+                         refresh and digest checks.",
+                        distribution_point
+                    );
+                    Box::new(ApiUploadBackend::new(distribution_point))
+                }
+            }
+        };
+        if !backend.is_jcds() {
+            status!("Distributing through a {}.", backend.label());
+        }
+
+        Ok(Self {
+            client,
+            identity,
+            category_id,
+            new_category,
+            backend,
+        })
+    }
+}
+
+/// Where a file ended up after [`stage_file`].
+enum Staged {
+    /// Nothing left to do: already up to date, or a dry run.
+    Done,
+    /// Uploaded; the inventory refresh and verification are still to come.
+    Uploaded(Box<PendingVerification>),
+}
+
+/// What [`verify_upload`] needs to know about an upload.
+struct PendingVerification {
+    pkg_id: String,
+    /// The record's name, which differs from the job's when `--id` picked it.
+    package_name: String,
+    /// Digests of the file as uploaded.
+    local_digests: FileDigests,
+    is_new: bool,
+    /// IDs of the --reassign-from records, for policy reassignment.
+    old_ids: Vec<String>,
+    /// The record's fileName before this run and the names of the
+    /// --reassign-from records, for policy reassignment.
+    old_names: Vec<String>,
+    /// The record's previous fileName when the upload changed it, so a file
+    /// share backend can remove the payload left behind.
+    replaced_file: Option<String>,
+    previous_digest: Option<PackageDigestSnapshot>,
+    history_baseline: Option<i64>,
+    affected_policies: Vec<AffectedPolicy>,
+}
+
+/// Delete the payload a file share update left behind under the record's old
+/// file name, unless another record still points at it. Failing to is only a
+/// warning: the new payload is already in place.
+async fn remove_replaced_payload(session: &Session, pkg_id: &str, previous: &str) {
+    let backend = session.backend.as_ref();
+    match session.client.list_packages().await {
+        Ok(packages)
+            if packages
+                .iter()
+                .any(|p| p.id != pkg_id && p.file_name == previous) =>
+        {
+            status!(
+                "Keeping {} on the {}: another package still uses it.",
+                previous,
+                backend.label()
+            );
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            outcome!(
+                "Warning: could not check whether other packages use {}, so it was left on \
+                 the {}: {}",
+                previous,
+                backend.label(),
+                redact::scrub(&format!("{:#}", e))
+            );
+            return;
+        }
+    }
+    match backend.remove_previous(&session.client, previous).await {
+        Ok(()) => status!("Removed the previous payload {}.", previous),
+        Err(e) => outcome!(
+            "Warning: could not remove the previous payload {}: {}",
+            previous,
+            redact::scrub(&format!("{:#}", e))
+        ),
+    }
+}
+
+/// Ask Jamf to recalculate JCDS checksums. Returns false when the instance
+/// offers no refresh, so [`verify_upload`] knows not to wait for a digest.
+async fn refresh_inventory(session: &Session) -> Result<bool> {
+    if !session.backend.is_jcds() {
+        return Ok(true);
+    }
+    // Refresh JCDS inventory to recalculate checksums
+    status!("Refreshing package inventory (recalculating checksums)...");
+    match session.backend.refresh_inventory(&session.client).await {
+        Ok(()) => status!("Inventory refresh requested."),
+        Err(e) if e.is::<InventoryRefreshUnavailable>() => {
+            outcome!("Warning: {}; skipping the refresh.", e);
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(true)
+}
+
+/// Find or create the package record, compare digests, scan policies, update
+/// metadata and upload the file. Stops short of the inventory refresh so a
+/// batch can share one.
+async fn stage_file(
+    session: &Session,
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    job: &FileJob,
+    outcome: &mut UpdateOutcome,
+) -> Result<Staged> {
+    let client = &session.client;
+    let path = job.path.as_path();
+    let file_name = job.file_name.as_str();
+    let package_name = job.package_name.as_str();
+    let category_id = session.category_id.as_deref();
+    let flag_overrides = args.flags.overrides();
+    let priority = args.priority;
+    let print_record = args.print_result_record;
+    let require_strong_hash = args.require_strong_hash;
+    let version = version_to_record(args, job).await;
+
+    let previous_records = resolve_previous_records(client, args).await?;
+
+    // 4. Find existing package — or create a new record if it doesn't exist yet
+    let existing = match args.id.as_deref() {
+        Some(id) => {
+            let spinner = Spinner::start(&format!("Fetching package ID {}...", id), global.quiet);
+            let found = client.get_package(id).await;
+            spinner.finish();
+            Some(found.with_context(|| format!("Package ID {} could not be read", id))?)
+        }
+        None => {
+            let spinner = Spinner::start(
+                &format!("Searching for package '{}'...", package_name),
+                global.quiet,
+            );
+            let found = client.find_package(package_name).await;
+            spinner.finish();
+            match found {
+                Ok(Some(pkg)) => Some(pkg),
+                Ok(None) => resolve_near_match(client, package_name).await?,
+                Err(e) => Some(choose_ambiguous(e)?),
+            }
+        }
+    };
+    let (package, is_new) = match existing {
+        Some(pkg) => {
+            status!(
+                "Found package '{}' (ID: {}, file: {})",
+                pkg.package_name,
+                pkg.id,
+                pkg.file_name
+            );
+            (pkg, false)
+        }
+        None if args.dry_run => {
+            let req = record_version(
+                PackageCreateRequest::new_default(package_name, file_name, priority, category_id)
+                    .with_flag_overrides(&flag_overrides),
+                version.as_ref(),
+            );
+            let category = match &session.new_category {
+                Some(name) => {
+                    outcome.plan(format!("create category '{}'", name));
+                    format!("category: '{}'", name)
+                }
+                None => format!("category ID: {}", req.category_id),
+            };
+            outcome.plan(format!(
+                "create package record '{}' (file: {}, {}, priority: {})",
+                req.package_name, req.file_name, category, req.priority
+            ));
+            let local_md5 = compute_file_md5(path).await?;
+            status!("Local file MD5: {}", local_md5);
+            let backend = session.backend.as_ref();
+            outcome.plan(format!("upload {} to the {}", file_name, backend.label()));
+            if backend.is_jcds() {
+                outcome.plan("refresh package inventory".to_string());
+            }
+            if !previous_records.is_empty() {
+                let matches: Vec<_> = previous_records.iter().map(package_match).collect();
+                let affected = scan_policies(client, args, global, &matches, outcome).await?;
+                plan_policy_changes(args, &affected, outcome);
+            }
+            outcome!("Dry run: no changes were made.");
+            outcome.action = UpdateAction::Created;
+            outcome.dry_run = true;
+            outcome.local_md5 = Some(local_md5);
+            return Ok(Staged::Done);
+        }
+        None => {
+            status!("Package not found — creating new package record...");
+            let req = record_version(
+                PackageCreateRequest::new_default(package_name, file_name, priority, category_id)
+                    .with_flag_overrides(&flag_overrides),
+                version.as_ref(),
+            );
+            job.start_writing();
+            let created = client.create_package(&req).await?;
+            status!("Created package '{}' (ID: {}).", package_name, created.id);
+            // Make sure a retry (or the next run) finds this record by name
+            // instead of creating a duplicate while the search index catches up.
+            if !client
+                .wait_until_searchable(package_name, &created.id, SEARCH_CONSISTENCY_TIMEOUT)
+                .await?
+            {
+                status!(
+                    "Note: the new package is not searchable by name yet; re-running immediately may create a duplicate."
+                );
+            }
+            let pkg_id = created.id;
+            // The create endpoint only returns an id+href; build a minimal
+            // Package from the request data so the rest of the flow works.
+            let pkg = Package {
+                id: pkg_id,
+                package_name: req.package_name,
+                file_name: req.file_name,
+                category_id: req.category_id,
+                priority: req.priority,
+                fill_user_template: req.fill_user_template,
+                fill_existing_users: req.fill_existing_users,
+                reboot_required: req.reboot_required,
+                os_install: req.os_install,
+                suppress_updates: req.suppress_updates,
+                suppress_from_dock: req.suppress_from_dock,
+                suppress_eula: req.suppress_eula,
+                suppress_registration: req.suppress_registration,
+                info: req.info,
+                notes: req.notes,
+            };
+            (pkg, true)
+        }
+    };
+
+    let pkg_id = package.id.clone();
+    let package_name = package.package_name.as_str();
+    outcome.package_id = Some(pkg_id.clone());
+    outcome.package_name = package.package_name.clone();
+
+    // For existing packages: check digest, skip if unchanged, scan policies, update metadata.
+    // For new packages: skip all of this — there is no existing payload or policy reference.
+    // An existing record with no digest at all usually means its JCDS object
+    // was deleted out from under it, which the JCDS file list confirms. There
+    // is nothing to compare or remove then; the upload simply repopulates the
+    // payload.
+    let mut payload_missing = false;
+    let mut affected_policies: Vec<AffectedPolicy> = Vec::new();
+    let previous_digest: Option<PackageDigestSnapshot> = if !is_new {
+        let digest = client.get_package_digest_snapshot(&pkg_id).await?;
+        outcome.previous_digest = digest.clone();
+        outcome.remote_md5 = digest.as_ref().and_then(|d| d.md5_hash.clone());
+        match &digest {
+            Some(d) if d.has_verifiable_content() => {
+                status!("Current package digest: {}", d.display_line())
+            }
+            _ if !session.backend.is_jcds() => {
+                payload_missing = true;
+                status!("Jamf reports no digest for this package; the file will be copied.");
+            }
+            _ => match jcds_holds(client, &package.file_name).await {
+                Some(true) => status!(
+                    "Jamf reports no digest for this package, but JCDS still holds {}; \
+                     it will be replaced.",
+                    package.file_name
+                ),
+                Some(false) => {
+                    payload_missing = true;
+                    status!(
+                        "Package record exists but its payload is missing from JCDS; \
+                         the upload will populate it."
+                    );
+                }
+                None => {
+                    payload_missing = true;
+                    status!(
+                        "Package record exists but Jamf reports no digest and the JCDS file \
+                         list is unavailable; treating the payload as missing."
+                    );
+                }
+            },
+        }
+
+        // Exit early when Jamf already has the same payload, compared with
+        // the strongest algorithm Jamf reports. --require-strong-hash does
+        // not let an MD5 match decide that, so it is checked first.
+        let comparable = digest.as_ref().and_then(|d| d.comparable_digest());
+        if require_strong_hash
+            && let Some(d) = digest.as_ref()
+            && let Some((HashType::Md5, _)) = comparable
+        {
+            ensure_strong_hash(d)?;
+            bail!(
+                "Jamf reports a {} digest this tool cannot compute, leaving only MD5 to compare; \
+                 --require-strong-hash does not accept MD5 as proof of integrity.",
+                d.hash_type.as_deref().unwrap_or("unknown")
+            );
+        }
+        if let Some((algorithm, remote)) = comparable {
+            // --write-checksums needs every digest anyway; take them in one pass.
+            let local = if args.write_checksums {
+                job.digests().await?.of(algorithm).to_string()
+            } else {
+                compute_file_digest(path, algorithm).await?
+            };
+            status!("Local file {}: {}", algorithm, local);
+            if algorithm == HashType::Md5 {
+                outcome.local_md5 = Some(local.clone());
+            }
+            if remote.eq_ignore_ascii_case(&local) {
+                status!(
+                    "Package payload already matches Jamf ({} unchanged).",
+                    algorithm
+                );
+                verify_remote_digests(client, &pkg_id, path, args).await?;
+                outcome!(
+                    "Package '{}' (ID: {}) is already up to date. Skipping update.",
+                    package_name,
+                    pkg_id
+                );
+                if print_record {
+                    outcome.result_record = read_result_record(client, &pkg_id).await;
+                }
+                outcome.action = UpdateAction::Skipped;
+                return Ok(Staged::Done);
+            }
+        }
+
+        // Scan policies for references to this package (and to the records
+        // --reassign-from moves policies off)
+        let mut matches = vec![PackageMatch {
+            id: Some(&pkg_id),
+            package_name,
+            file_name: &package.file_name,
+        }];
+        matches.extend(previous_records.iter().map(package_match));
+        affected_policies = scan_policies(client, args, global, &matches, outcome).await?;
+        if let Err(e) = scan_patch_titles(client, &pkg_id, outcome).await {
+            outcome!(
+                "Warning: patch title scan failed: {}",
+                redact::scrub(&format!("{:#}", e))
+            );
+        }
+
+        // Keep the outgoing file and record for `rollback` while they still exist.
+        if args.keep_previous && !payload_missing {
+            if args.dry_run {
+                outcome.plan(format!(
+                    "save {} and the package record for rollback",
+                    package.file_name
+                ));
+            } else {
+                status!("Saving {} for rollback...", package.file_name);
+                let root = backups::backup_root(args.backup_dir.as_deref())?;
+                let dir = backups::save(client, &root, &package, digest.as_ref(), global.quiet)
+                    .await
+                    .context("Failed to save the previous package version (--keep-previous)")?;
+                status!("Previous version saved to {}.", dir.display());
+            }
+        }
+
+        // Update package metadata in-place (keep same ID, update fileName)
+        let mut update_req =
+            PackageCreateRequest::from_old(&package, file_name, priority, category_id);
+        if args.reset_flags {
+            status!("Resetting package flags to defaults (--reset-flags).");
+            update_req = update_req.with_default_flags();
+        }
+        if !flag_overrides.is_empty() {
+            update_req = update_req.with_flag_overrides(&flag_overrides);
+        }
+        update_req = record_version(update_req, version.as_ref());
+        if args.dry_run {
+            let mut changes = update_req.changes_from(&package);
+            if let Some(name) = &session.new_category {
+                outcome.plan(format!("create category '{}'", name));
+                changes.insert(
+                    0,
+                    format!("categoryId: {} -> '{}' (new)", package.category_id, name),
+                );
+            }
+            if changes.is_empty() {
+                status!("Package metadata would stay as it is.");
+            }
+            for change in changes {
+                outcome.plan(format!("update package metadata ({})", change));
+            }
+        } else {
+            let spinner = Spinner::start("Updating package metadata...", global.quiet);
+            job.start_writing();
+            let updated = client.update_package(&pkg_id, &update_req).await;
+            spinner.finish();
+            updated?;
+            status!("Metadata updated.");
+        }
+
+        // With no previous payload there is nothing to "change" from; wait for
+        // the digest to become available instead, as for a new package.
+        if payload_missing { None } else { digest }
+    } else {
+        // A new record has no policies of its own yet, only those it takes
+        // over from --reassign-from.
+        if !previous_records.is_empty() {
+            let matches: Vec<_> = previous_records.iter().map(package_match).collect();
+            affected_policies = scan_policies(client, args, global, &matches, outcome).await?;
+        }
+        None
+    };
+
+    let backend = session.backend.as_ref();
+    if backend.is_jcds() {
+        check_jcds_capacity(client, path, previous_digest.as_ref()).await?;
+    }
+
+    if args.dry_run {
+        if backend.is_jcds() && !is_new && !payload_missing {
+            outcome.plan(format!(
+                "remove old JCDS file {} before re-upload",
+                file_name
+            ));
+        }
+        outcome.plan(format!("upload {} to the {}", file_name, backend.label()));
+        if !backend.is_jcds() && !is_new && package.file_name != file_name {
+            outcome.plan(format!(
+                "remove the previous payload {} from the {} unless another package uses it",
+                package.file_name,
+                backend.label()
+            ));
+        }
+        if backend.is_jcds() {
+            outcome.plan("refresh package inventory".to_string());
+        }
+        plan_policy_changes(args, &affected_policies, outcome);
+        outcome!("Dry run: no changes were made.");
+        outcome.action = if is_new {
+            UpdateAction::Created
+        } else {
+            UpdateAction::Updated
+        };
+        outcome.dry_run = true;
+        return Ok(Staged::Done);
+    }
+
+    // For existing packages, remove the old payload first.
+    if backend.is_jcds() && !is_new && !payload_missing {
+        status!("Removing old JCDS file before re-upload...");
+        job.start_writing();
+        backend.remove_previous(client, file_name).await?;
+        status!("Old file removed.");
+    }
+
+    let history_baseline = if args.success_when == Some(SuccessCriterion::HistoryEntry) {
+        Some(client.get_package_history_count(&pkg_id).await?)
+    } else {
+        None
+    };
+
+    // Upload the file
+    status!("Uploading {}...", file_name);
+    // Same content + same record => same key, so a retried upload that
+    // already landed can be recognised by servers that honor the header.
+    // Every digest Jamf may report is taken now, in one pass, so the one it
+    // computes after the refresh can be checked against the bytes sent.
+    let local_digests = job.digests().await?.clone();
+    let upload_md5 = local_digests.md5.clone();
+    outcome.local_md5 = Some(upload_md5.clone());
+    outcome.local_sha512 = Some(local_digests.sha512.clone());
+    let upload_options = UploadOptions {
+        file_open_attempts: args.file_open_attempts,
+        field_name: args.upload_field_name.clone(),
+        idempotency_key: Some(format!("jamf-package-updater-{}-{}", pkg_id, upload_md5)),
+        progress: TransferProgress::upload(global.quiet),
+        chunk_size: args.chunk_size,
+    };
+    job.start_writing();
+    backend
+        .upload(client, &pkg_id, path, &upload_options)
+        .await?;
+    status!("Upload complete.");
+
+    let replaced_file =
+        (!is_new && package.file_name != file_name).then(|| package.file_name.clone());
+    Ok(Staged::Uploaded(Box::new(PendingVerification {
+        pkg_id,
+        package_name: package_name.to_string(),
+        local_digests,
+        is_new,
+        old_ids: previous_records.iter().map(|p| p.id.clone()).collect(),
+        old_names: std::iter::once(package.file_name)
+            .chain(
+                previous_records
+                    .into_iter()
+                    .flat_map(|p| [p.package_name, p.file_name]),
+            )
+            .collect(),
+        replaced_file,
+        previous_digest,
+        history_baseline,
+        affected_policies,
+    })))
+}
+
+/// After the inventory refresh: wait for Jamf's digest to reflect the upload,
+/// check it, reassign policies and report. When the refresh was skipped
+/// (`refreshed` is false) Jamf may never recalculate the digest, so the wait
+/// is skipped and the upload is reported as unverified.
+async fn verify_upload(
+    session: &Session,
+    args: &UpdateArgs,
+    job: &FileJob,
+    pending: PendingVerification,
+    refreshed: bool,
+    outcome: &mut UpdateOutcome,
+) -> Result<()> {
+    let client = &session.client;
+    let path = job.path.as_path();
+    let file_name = job.file_name.as_str();
+    let package_name = pending.package_name.as_str();
+    let pkg_id = pending.pkg_id.as_str();
+    let require_strong_hash = args.require_strong_hash;
+
+    let polling = DigestPolling::from_args(args);
+
+    if !session.backend.is_jcds() {
+        // Jamf computes no digest for file share payloads; check the copy.
+        session
+            .backend
+            .verify(file_name, &pending.local_digests.md5)
+            .await?;
+        if let Some(previous) = pending.replaced_file.as_deref() {
+            remove_replaced_payload(session, pkg_id, previous).await;
+        }
+    } else if !refreshed {
+        if require_strong_hash {
+            bail!(
+                "The inventory refresh was skipped, so Jamf has no new digest to check \
+                 against --require-strong-hash. Check the package later with `verify`."
+            );
+        }
+        outcome!(
+            "Warning: the inventory refresh was skipped; the upload was not verified. \
+             Check the package later with `verify`."
+        );
+    } else if let Some(criterion) = args.success_when {
+        status!(
+            "Waiting for success criterion: {}...",
+            criterion_label(criterion)
+        );
+        let expected = ExpectedUpload {
+            previous: pending.previous_digest.as_ref(),
+            local_md5: pending.local_digests.md5.clone(),
+            local_size: tokio::fs::metadata(path)
+                .await
+                .with_context(|| format!("Failed to read file metadata: {}", path.display()))?
+                .len(),
+            history_baseline: pending.history_baseline,
+        };
+        let detail =
+            wait_for_success_criterion(client, pkg_id, criterion, &expected, &polling).await?;
+        status!("Success criterion met: {}", detail);
+        if require_strong_hash {
+            match fetch_snapshot_tolerant(client, pkg_id).await? {
+                Some(digest) => ensure_strong_hash(&digest)?,
+                None => ensure_strong_hash(&PackageDigestSnapshot::default())?,
+            }
+        }
+    } else if let Some(previous) = pending.previous_digest.as_ref() {
+        status!("Waiting for Jamf digest metadata to update...");
+        match wait_for_digest_change(client, pkg_id, previous, &pending.local_digests, &polling)
+            .await?
+        {
+            DigestOutcome::Changed(refreshed_digest) => {
+                status!("Digest updated: {}", refreshed_digest.display_line());
+                if require_strong_hash {
+                    ensure_strong_hash(&refreshed_digest)?;
+                }
+            }
+            DigestOutcome::UnchangedButContentMatches(digest, algorithm) => {
+                // Rebuilds from identical source produce the same payload, so
+                // Jamf's stored digest legitimately stays the same.
+                if require_strong_hash {
+                    ensure_strong_match(&digest, &pending.local_digests)?;
+                }
+                status!(
+                    "Digest unchanged but remote {} matches the uploaded file — content is identical.",
+                    algorithm
+                );
+            }
+            DigestOutcome::Stale(latest) => {
+                let diagnosis =
+                    diagnose_inventory_refresh(client, file_name, &pending.local_digests.md5).await;
+                let mismatch = match latest.as_ref().and_then(|d| d.comparable_digest()) {
+                    Some((algorithm, remote)) => format!(
+                        "the remote {} ({}) does not match the local file ({})",
+                        algorithm,
+                        remote,
+                        pending.local_digests.of(algorithm)
+                    ),
+                    None => "Jamf reports no digest to compare with the local file".to_string(),
+                };
+                bail!(
+                    "Upload completed but Jamf digest metadata did not update \
+                     after {} seconds and {}. Previous digest: {}. {}",
+                    polling.timeout.as_secs(),
+                    mismatch,
+                    previous.display_line(),
+                    diagnosis
+                );
+            }
+        }
+    } else {
+        status!("Waiting for Jamf digest metadata to become available...");
+        let digest = match wait_for_digest_availability(client, pkg_id, &polling).await {
+            Ok(digest) => digest,
+            Err(e) => {
+                let diagnosis =
+                    diagnose_inventory_refresh(client, file_name, &pending.local_digests.md5).await;
+                bail!("{:#} {}", e, diagnosis);
+            }
+        };
+        status!("Digest updated: {}", digest.display_line());
+        if require_strong_hash {
+            ensure_strong_hash(&digest)?;
+        }
+    }
+
+    if session.backend.is_jcds() && refreshed {
+        status!("Inventory refreshed.");
+        outcome.verified_hash = verify_strong_digest(
+            client,
+            pkg_id,
+            pending.previous_digest.as_ref(),
+            &pending.local_digests,
+            &polling,
+        )
+        .await?
+        .map(|algorithm| algorithm.to_string());
+        verify_remote_digests(client, pkg_id, path, args).await?;
+    }
+
+    let affected_policies = &pending.affected_policies;
+    let mut reassign_failures = 0;
+    if args.reassign_policies && !affected_policies.is_empty() {
+        let old_ids: Vec<&str> = pending.old_ids.iter().map(String::as_str).collect();
+        let old_names: Vec<&str> = pending
+            .old_names
+            .iter()
+            .map(String::as_str)
+            .chain([package_name])
+            .collect();
+        let target = PackageReference {
+            id: pkg_id,
+            name: package_name,
+        };
+        reassign_failures =
+            reassign_policies(client, affected_policies, &old_ids, &old_names, &target).await;
+    }
+    let mut flush_failures = 0;
+    if args.flush_policies && !affected_policies.is_empty() {
+        let (flushed, failures) = flush_policies(client, affected_policies, args.yes).await?;
+        outcome.flushed_policies = flushed;
+        flush_failures = failures;
+    }
+
+    if pending.is_new {
+        outcome!(
+            "Package '{}' (ID: {}) created and uploaded successfully by {}.",
+            package_name,
+            pkg_id,
+            session.identity
+        );
+    } else {
+        outcome!(
+            "Package '{}' (ID: {}) updated successfully by {}.",
+            package_name,
+            pkg_id,
+            session.identity
+        );
+    }
+
+    // The record keeps its ID, so patch definitions still point at it, but
+    // the version they list for it is only as current as the last edit.
+    for title in &outcome.patch_titles {
+        outcome!(
+            "Note: patch title '{}' (ID: {}) lists this package as version {}; update its \
+             definition in Jamf Pro if the version changed.",
+            title.name,
+            title.id,
+            title.version
+        );
+    }
+
+    if args.print_result_record {
+        outcome.result_record = read_result_record(client, pkg_id).await;
+    }
+
+    outcome.action = if pending.is_new {
+        UpdateAction::Created
+    } else {
+        UpdateAction::Updated
+    };
+    outcome.new_digest = fetch_snapshot_tolerant(client, pkg_id).await?;
+
+    if reassign_failures > 0 {
+        bail!(
+            "Package updated, but {} of {} policies could not be reassigned",
+            reassign_failures,
+            affected_policies.len()
+        );
+    }
+    if flush_failures > 0 {
+        bail!(
+            "Package updated, but {} of {} policies could not be flushed",
+            flush_failures,
+            affected_policies.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Fetch the package record for `--print-result-record`. The package is
+/// already in place by now, so a failed read is only a warning.
+async fn read_result_record(client: &JamfClient, pkg_id: &str) -> Option<Value> {
+    match client.get_package_record(pkg_id).await {
+        Ok(record) => Some(record),
+        Err(e) => {
+            outcome!(
+                "Warning: could not read package {} for --print-result-record: {}",
+                pkg_id,
+                redact::scrub(&format!("{:#}", e))
+            );
+            None
+        }
+    }
+}
+
+/// Reject option combinations that cannot work, before anything changes:
+/// `--success-when md5-match` proves nothing about a strong hash, and
+/// `--flush-policies` needs a terminal to ask on unless `--yes` is given.
+fn check_criterion_compatibility(args: &UpdateArgs) -> Result<()> {
+    if args.flush_policies && !args.yes && !args.dry_run && !prompt::is_interactive() {
+        bail!(
+            "--flush-policies asks before flushing each policy; pass --yes to flush non-interactively"
+        );
+    }
+    if args.require_strong_hash && args.success_when == Some(SuccessCriterion::Md5Match) {
+        bail!("--success-when md5-match cannot be combined with --require-strong-hash");
+    }
+    if args.distribution_point.is_some()
+        && (args.success_when.is_some() || args.require_strong_hash)
+    {
+        bail!(
+            "--success-when and --require-strong-hash check the digest Jamf computes for JCDS uploads; \
+             they cannot be used with --distribution-point"
+        );
+    }
+    // Clap rejects these on the command line; a `[defaults]` distribution
+    // point is only known here.
+    if args.distribution_point.is_some() && (args.chunk_size.is_some() || args.keep_previous) {
+        bail!(
+            "--chunk-size and --keep-previous need JCDS; they cannot be used with a distribution point"
+        );
+    }
+    Ok(())
+}
+
+/// Warn when the installer's embedded version disagrees with the version in the
+/// package name (or, failing that, the file name). Never fails the update.
+async fn check_embedded_version(path: &Path, package_name: &str) {
+    let Some(embedded) = read_embedded_version(path).await else {
+        return;
+    };
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let named = pkginfo::version_in_name(package_name).or_else(|| pkginfo::version_in_name(&stem));
+
+    match named {
+        Some(named) if pkginfo::versions_agree(&embedded, &named) => {
+            status!("Embedded version: {} (matches name)", embedded);
+        }
+        Some(named) => {
+            outcome!(
+                "Warning: installer reports version {} but the name says {}.",
+                embedded,
+                named
+            );
+        }
+        None => status!(
+            "Embedded version: {} (no version found in the name to compare)",
+            embedded
+        ),
+    }
+}
+
+/// The version embedded in the installer, or None after saying why it could
+/// not be read.
+async fn read_embedded_version(path: &Path) -> Option<String> {
+    let owned = path.to_path_buf();
+    match tokio::task::spawn_blocking(move || pkginfo::read_installer_version(&owned)).await {
+        Ok(Ok(Some(version))) => Some(version),
+        Ok(Ok(None)) => {
+            status!("Embedded version: could not be determined for this file type.");
+            None
+        }
+        Ok(Err(e)) => {
+            outcome!("Warning: could not read embedded version: {:#}", e);
+            None
+        }
+        Err(e) => {
+            outcome!("Warning: could not read embedded version: {}", e);
+            None
+        }
+    }
+}
+
+/// The `--record-version` line for `path`, with the record field it goes in.
+async fn version_to_record(args: &UpdateArgs, job: &FileJob) -> Option<(VersionField, String)> {
+    let field = args.record_version?;
+    let autopkg_version = args
+        .autopkg_versions
+        .iter()
+        .find(|(path, _)| *path == job.source)
+        .and_then(|(_, version)| version.clone());
+    let version = match autopkg_version {
+        Some(version) => Some(version),
+        None => read_embedded_version(&job.path).await,
+    };
+    if version.is_none() {
+        outcome!("Warning: no version to record in the package {}.", field);
+    }
+    Some((field, version_line(&version?, &job.file_name)))
+}
+
+/// Put the `--record-version` line into the request's info or notes.
+fn record_version(
+    req: PackageCreateRequest,
+    version: Option<&(VersionField, String)>,
+) -> PackageCreateRequest {
+    match version {
+        Some((VersionField::Info, line)) => PackageCreateRequest {
+            info: Some(with_version_line(req.info.as_deref(), line)),
+            ..req
+        },
+        Some((VersionField::Notes, line)) => PackageCreateRequest {
+            notes: Some(with_version_line(req.notes.as_deref(), line)),
+            ..req
+        },
+        None => req,
+    }
+}
+
+/// Find the Patch Management titles and patch policies that install the
+/// package, recording them on `outcome`. Like the policy scan this is
+/// informational: a token that cannot read patch titles skips it, and the
+/// caller only warns when the scan fails.
+async fn scan_patch_titles(
+    client: &JamfClient,
+    package_id: &str,
+    outcome: &mut UpdateOutcome,
+) -> Result<()> {
+    status!("Scanning patch titles...");
+    let Some((titles, policies)) = client.find_patch_references(package_id).await? else {
+        status!("Patch title scan skipped: token lacks Patch Management read access.");
+        return Ok(());
+    };
+    if titles.is_empty() {
+        status!("No patch titles reference this package.");
+        return Ok(());
+    }
+
+    status!(
+        "Found {} patch title {} referencing this package.",
+        titles.len(),
+        if titles.len() == 1 {
+            "version"
+        } else {
+            "versions"
+        }
+    );
+    for t in &titles {
+        status!("  - {} {} (ID: {})", t.name, t.version, t.id);
+    }
+    let Some(policies) = policies else {
+        status!("Patch policy scan skipped: token lacks read access to patch policies.");
+        outcome.patch_titles = titles;
+        return Ok(());
+    };
+    for p in &policies {
+        status!(
+            "  - patch policy {} (ID: {}, version {})",
+            p.name,
+            p.id,
+            p.version
+        );
+    }
+    outcome.patch_titles = titles;
+    outcome.patch_policies = policies;
+    Ok(())
+}
+
+/// Look up the --reassign-from records before anything changes, so a typo
+/// fails the run instead of leaving policies behind.
+async fn resolve_previous_records(client: &JamfClient, args: &UpdateArgs) -> Result<Vec<Package>> {
+    let mut records: Vec<Package> = Vec::new();
+    for target in &args.reassign_from {
+        let package = client
+            .resolve_package(PackageTarget::NameOrId(target))
+            .await
+            .with_context(|| format!("--reassign-from '{}'", target))?;
+        status!(
+            "Policies will move off '{}' (ID: {}, file: {}).",
+            package.package_name,
+            package.id,
+            package.file_name
+        );
+        if !records.iter().any(|r| r.id == package.id) {
+            records.push(package);
+        }
+    }
+    Ok(records)
+}
+
+fn package_match(package: &Package) -> PackageMatch<'_> {
+    PackageMatch {
+        id: Some(&package.id),
+        package_name: &package.package_name,
+        file_name: &package.file_name,
+    }
+}
+
+/// Scan policies for references to any of `matches`, list them and export
+/// them when asked. A token that cannot read classic policies skips the scan.
+async fn scan_policies(
+    client: &JamfClient,
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    matches: &[PackageMatch<'_>],
+    outcome: &mut UpdateOutcome,
+) -> Result<Vec<AffectedPolicy>> {
+    status!("Scanning policies...");
+    let scan_options = ScanOptions {
+        source_dir: args.policy_source.clone(),
+        dump_dir: args.dump_policy_xml.clone(),
+        concurrency: global.scan_concurrency,
+        progress: progress::policy_scan(),
+    };
+    // Package-only API clients often cannot read classic policies; the
+    // scan is informational, so a 403 skips it rather than the update.
+    match client
+        .find_policies_with_packages(matches, &scan_options)
+        .await
+    {
+        Ok(found) => {
+            let affected_policies = found;
+            status!(
+                "Found {} {} referencing this package.",
+                affected_policies.len(),
+                if affected_policies.len() == 1 {
+                    "policy"
+                } else {
+                    "policies"
+                }
+            );
+            for p in &affected_policies {
+                status!("  - {} (ID: {})", p.name, p.id);
+            }
+            outcome.affected_policies = affected_policies.clone();
+
+            if let Some(dir) = args.export_affected_policies.as_deref() {
+                export_policies(client, &affected_policies, dir).await?;
+            }
+            Ok(affected_policies)
+        }
+        Err(e) if e.chain().any(|cause| cause.is::<PolicyReadForbidden>()) => {
+            status!("Policy scan skipped: token lacks classic API read access.");
+            if args.export_affected_policies.is_some() {
+                status!("Affected policies were not exported.");
+            }
+            if args.reassign_policies {
+                status!("Policies will not be reassigned.");
+            }
+            if args.flush_policies {
+                status!("Policies will not be flushed.");
+            }
+            Ok(Vec::new())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The dry-run plan lines for --reassign-policies and --flush-policies.
+fn plan_policy_changes(
+    args: &UpdateArgs,
+    policies: &[AffectedPolicy],
+    outcome: &mut UpdateOutcome,
+) {
+    if args.reassign_policies {
+        for p in policies {
+            outcome.plan(format!("reassign policy '{}' (ID: {})", p.name, p.id));
+        }
+    }
+    if args.flush_policies {
+        for p in policies {
+            outcome.plan(format!(
+                "flush the logs of policy '{}' (ID: {})",
+                p.name, p.id
+            ));
+        }
+    }
+}
+
+/// PUT each affected policy back with its package entry pointing at `target`.
+/// Prints what was rewritten or skipped and returns the number of failures.
+async fn reassign_policies(
+    client: &JamfClient,
+    policies: &[AffectedPolicy],
+    old_ids: &[&str],
+    old_names: &[&str],
+    target: &PackageReference<'_>,
+) -> usize {
+    status!("Reassigning {} policies...", policies.len());
+    let mut rewritten = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+
+    for policy in policies {
+        let result = async {
+            let xml = client.get_policy_xml(policy.id).await?;
+            match rewrite_package_configuration(&xml, old_ids, old_names, target)? {
+                Some(update) => client
+                    .update_policy_xml(policy.id, &update)
+                    .await
+                    .map(|_| true),
+                None => Ok(false),
+            }
+        }
+        .await;
+
+        match result {
+            Ok(true) => {
+                rewritten += 1;
+                status!("  - rewritten: {} (ID: {})", policy.name, policy.id);
+            }
+            Ok(false) => {
+                skipped += 1;
+                status!(
+                    "  - skipped: {} (ID: {}) already references this package",
+                    policy.name,
+                    policy.id
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                status!("  - failed: {} (ID: {}): {:#}", policy.name, policy.id, e);
+            }
+        }
+    }
+
+    status!(
+        "{} rewritten, {} skipped, {} failed.",
+        rewritten,
+        skipped,
+        failed
+    );
+    failed
+}
+
+/// Flush the logs of each affected policy, asking first for each one unless
+/// `yes`. Returns the IDs flushed and the number of failures.
+async fn flush_policies(
+    client: &JamfClient,
+    policies: &[AffectedPolicy],
+    yes: bool,
+) -> Result<(Vec<i64>, usize)> {
+    status!("Flushing {} policies...", policies.len());
+    let mut flushed = Vec::new();
+    let mut declined = 0;
+    let mut failed = 0;
+
+    for policy in policies {
+        if !yes
+            && !prompt::confirm(&format!(
+                "Flush the logs of policy '{}' (ID: {}) so it runs again on every computer?",
+                policy.name, policy.id
+            ))?
+        {
+            declined += 1;
+            status!("  - not flushed: {} (ID: {})", policy.name, policy.id);
+            continue;
+        }
+        match client.flush_policy_logs(policy.id).await {
+            Ok(()) => {
+                flushed.push(policy.id);
+                status!("  - flushed: {} (ID: {})", policy.name, policy.id);
+            }
+            Err(e) => {
+                failed += 1;
+                outcome!(
+                    "  - failed to flush: {} (ID: {}): {}",
+                    policy.name,
+                    policy.id,
+                    redact::scrub(&format!("{:#}", e))
+                );
+            }
+        }
+    }
+
+    let summary = format!(
+        "{} flushed, {} declined, {} failed.",
+        flushed.len(),
+        declined,
+        failed
+    );
+    if failed > 0 {
+        outcome!("{}", summary);
+    } else {
+        status!("{}", summary);
+    }
+    Ok((flushed, failed))
+}
+
+/// Check the local file against `--expected-md5`/`--expected-sha512`, and
+/// with `--write-checksums` against the checksum files next to it, before
+/// anything is changed in Jamf.
+async fn verify_local_digests(job: &FileJob, args: &UpdateArgs) -> Result<()> {
+    let path = job.path.as_path();
+    if args.write_checksums {
+        let checksum_files = read_checksum_files(&job.checksum_base())?;
+        if !checksum_files.is_empty() {
+            let digests = job.digests().await?;
+            let file_modified = std::fs::metadata(&job.source)
+                .and_then(|m| m.modified())
+                .ok();
+            for checksum_file in &checksum_files {
+                let local = digests.of(checksum_file.hash_type);
+                let name = checksum_file
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                if local != checksum_file.digest
+                    && is_stale_checksum_file(checksum_file, file_modified, job.zipped.is_some())
+                {
+                    status!(
+                        "{} does not describe this build of the file; it will be rewritten.",
+                        name
+                    );
+                    continue;
+                }
+                if local != checksum_file.digest {
+                    bail!(
+                        "Local file does not match {}: expected {} {}, computed {}. \
+                         Nothing was uploaded; replace or delete the checksum file if it is stale.",
+                        checksum_file.path.display(),
+                        checksum_file.hash_type,
+                        checksum_file.digest,
+                        local
+                    );
+                }
+                status!("Local file matches {}.", name);
+            }
+        }
+    }
+    if let Some(expected) = args.expected_md5.as_deref() {
+        let local = compute_file_md5(path).await?;
+        if local != expected {
+            bail!(
+                "Local file does not match --expected-md5: expected {}, computed {}. Nothing was uploaded.",
+                expected,
+                local
+            );
+        }
+        status!("Local file matches the expected MD5.");
+    }
+    if let Some(expected) = args.expected_sha512.as_deref() {
+        let local = compute_file_digest(path, HashType::Sha512).await?;
+        if local != expected {
+            bail!(
+                "Local file does not match --expected-sha512: expected {}, computed {}. Nothing was uploaded.",
+                expected,
+                local
+            );
+        }
+        status!("Local file matches the expected SHA-512.");
+    }
+    Ok(())
+}
+
+/// Whether a checksum file that disagrees with the file may be replaced
+/// rather than fail the run. A bundle is zipped afresh on every run, and a
+/// checksum file older than the installer is left over from an earlier
+/// upload of a file since rebuilt in place; neither describes this file.
+/// One at least as new as the installer (an artifact store's) must match.
+fn is_stale_checksum_file(
+    checksum_file: &ChecksumFile,
+    file_modified: Option<SystemTime>,
+    zipped: bool,
+) -> bool {
+    zipped
+        || matches!(
+            (checksum_file.modified, file_modified),
+            (Some(written), Some(built)) if written < built
+        )
+}
+
+/// Confirm that the digest Jamf reports for the package matches the expected
+/// file. The local file was pinned by [`verify_local_digests`], so whichever
+/// digest Jamf offers (MD5 or SHA-512) is compared against it.
+async fn verify_remote_digests(
+    client: &JamfClient,
+    pkg_id: &str,
     path: &Path,
-    name: Option<&str>,
-    priority: Option<i32>,
-    digest_wait_seconds: u64,
+    args: &UpdateArgs,
 ) -> Result<()> {
-    // 1. Resolve package name
-    let file_name = path
-        .file_name()
-        .context("Invalid file path")?
-        .to_string_lossy()
-        .to_string();
-
-    let package_name = match name {
-        Some(n) => n.to_string(),
-        None => path
-            .file_stem()
-            .context("Cannot determine package name from file path")?
-            .to_string_lossy()
-            .to_string(),
-    };
+    if args.expected_md5.is_none() && args.expected_sha512.is_none() {
+        return Ok(());
+    }
 
-    // Validate file extension
-    let ext = path
-        .extension()
-        .map(|e| e.to_string_lossy().to_lowercase())
+    let digest = fetch_snapshot_tolerant(client, pkg_id)
+        .await?
         .unwrap_or_default();
-    if ext != "pkg" && ext != "dmg" {
-        bail!("File must be a .pkg or .dmg (got .{})", ext);
+    let mut verified = Vec::new();
+
+    if let Some(remote) = digest.md5_hash.as_deref() {
+        let expected = match args.expected_md5.clone() {
+            Some(expected) => expected,
+            None => compute_file_md5(path).await?,
+        };
+        if !remote.eq_ignore_ascii_case(&expected) {
+            bail!(
+                "Jamf reports MD5 {} for package ID {} but the expected file has {}; \
+                 the content in Jamf is not the expected build output.",
+                remote,
+                pkg_id,
+                expected
+            );
+        }
+        verified.push("MD5");
     }
 
-    if !path.exists() {
-        bail!("File not found: {}", path.display());
+    if let Some(remote) = digest.hash_of(HashType::Sha512) {
+        let expected = match args.expected_sha512.clone() {
+            Some(expected) => expected,
+            None => compute_file_digest(path, HashType::Sha512).await?,
+        };
+        if !remote.eq_ignore_ascii_case(&expected) {
+            bail!(
+                "Jamf reports SHA-512 {} for package ID {} but the expected file has {}; \
+                 the content in Jamf is not the expected build output.",
+                remote,
+                pkg_id,
+                expected
+            );
+        }
+        verified.push("SHA-512");
+    }
+
+    if verified.is_empty() {
+        bail!(
+            "Jamf reports no MD5 or SHA-512 digest for package ID {}; the upload could not be \
+             verified against the expected digest.",
+            pkg_id
+        );
     }
+    status!(
+        "Jamf digest matches the expected file ({}).",
+        verified.join(", ")
+    );
+    Ok(())
+}
 
-    println!("Package name: {}", package_name);
-    println!("File: {}", path.display());
+/// Check the SHA-2/SHA-3 digest Jamf computed after the refresh against the
+/// uploaded file, failing on a mismatch. A strong digest still equal to the
+/// previous one has not been recalculated yet and is polled until it changes.
+/// Returns the algorithm checked, or None when Jamf reports only MD5.
+async fn verify_strong_digest(
+    client: &JamfClient,
+    pkg_id: &str,
+    previous: Option<&PackageDigestSnapshot>,
+    local: &FileDigests,
+    polling: &DigestPolling,
+) -> Result<Option<HashType>> {
+    let deadline = Instant::now() + polling.timeout;
+    loop {
+        let digest = fetch_snapshot_tolerant(client, pkg_id)
+            .await?
+            .unwrap_or_default();
+        let Some((algorithm, remote)) = digest
+            .comparable_digest()
+            .filter(|(algorithm, _)| *algorithm != HashType::Md5)
+        else {
+            status!(
+                "Jamf reports no SHA-2/SHA-3 digest for this package; the upload is verified by MD5 only."
+            );
+            return Ok(None);
+        };
 
-    // 2. Load credentials
-    let creds = credentials::load_credentials()?;
-    println!("Jamf Pro URL: {}", creds.url);
+        let expected = local.of(algorithm);
+        if remote.eq_ignore_ascii_case(expected) {
+            status!("Jamf {} matches the uploaded file.", algorithm);
+            return Ok(Some(algorithm));
+        }
+        let not_recalculated = previous
+            .and_then(|p| p.hash_of(algorithm))
+            .is_some_and(|old| old.eq_ignore_ascii_case(remote));
+        if !not_recalculated || Instant::now() >= deadline {
+            bail!(
+                "Jamf reports {} {} for package ID {} after the upload, but the uploaded file has {}. \
+                 The stored payload does not match the bytes that were sent.",
+                algorithm,
+                remote,
+                pkg_id,
+                expected
+            );
+        }
+        debug!("{} not recalculated yet; polling again", algorithm);
+        sleep(polling.interval).await;
+    }
+}
 
-    // 3. Authenticate
-    println!("Authenticating...");
-    let client = JamfClient::connect(&creds.url, &creds.client_id, &creds.client_secret).await?;
-    println!("Authenticated.");
+/// Explain a digest timeout by looking at what JCDS itself holds. If JCDS
+/// already has the new bytes but the package record wasn't updated, the
+/// inventory refresh is stalled rather than slow.
+async fn diagnose_inventory_refresh(
+    client: &JamfClient,
+    file_name: &str,
+    local_md5: &str,
+) -> String {
+    const SLOW_HINT: &str = "If your Jamf instance is slow to recalculate digests, retry with --digest-wait-seconds 600.";
 
-    let digest_wait_timeout = if digest_wait_seconds == 0 {
-        DEFAULT_DIGEST_WAIT_TIMEOUT
-    } else {
-        Duration::from_secs(digest_wait_seconds)
+    let files = match client.list_jcds_files().await {
+        Ok(Some(files)) => files,
+        _ => return SLOW_HINT.to_string(),
     };
-    let digest_poll_attempts = digest_poll_attempts(digest_wait_timeout);
 
-    // 4. Find existing package — or create a new record if it doesn't exist yet
-    println!("Searching for package '{}'...", package_name);
-    let (package, is_new) = match client.find_package(&package_name).await? {
-        Some(pkg) => {
-            println!(
-                "Found package '{}' (ID: {}, file: {})",
-                package_name, pkg.id, pkg.file_name
-            );
-            (pkg, false)
+    match files.iter().find(|f| f.file_name == file_name) {
+        Some(f)
+            if f.md5
+                .as_deref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(local_md5)) =>
+        {
+            "JCDS already holds the uploaded file (MD5 matches), but the package record was not \
+             refreshed: the inventory refresh appears stalled rather than slow. Trigger it again \
+             or contact Jamf support."
+                .to_string()
         }
-        None => {
-            println!("Package not found — creating new package record...");
-            let req = PackageCreateRequest::new_default(&package_name, &file_name, priority);
-            let created = client.create_package(&req).await?;
-            println!("Created package '{}' (ID: {}).", package_name, created.id);
-            let pkg_id = created.id;
-            // The create endpoint only returns an id+href; build a minimal
-            // Package from the request data so the rest of the flow works.
-            let pkg = crate::models::package::Package {
-                id: pkg_id,
-                package_name: req.package_name,
-                file_name: req.file_name,
-                category_id: req.category_id,
-                priority: req.priority,
-                fill_user_template: req.fill_user_template,
-                fill_existing_users: req.fill_existing_users,
-                reboot_required: req.reboot_required,
-                os_install: req.os_install,
-                suppress_updates: req.suppress_updates,
-                suppress_from_dock: req.suppress_from_dock,
-                suppress_eula: req.suppress_eula,
-                suppress_registration: req.suppress_registration,
-            };
-            (pkg, true)
+        Some(f) => format!(
+            "JCDS still reports a different copy of {} (md5={}); the upload may still be processing. {}",
+            file_name,
+            f.md5.as_deref().unwrap_or("unknown"),
+            SLOW_HINT
+        ),
+        None => format!(
+            "{} is not listed in JCDS yet; the upload is still being processed. {}",
+            file_name, SLOW_HINT
+        ),
+    }
+}
+
+/// Fail when the only digest Jamf offers is MD5 (or none at all).
+fn ensure_strong_hash(digest: &PackageDigestSnapshot) -> Result<()> {
+    if !digest.has_strong_hash() {
+        bail!(
+            "Jamf does not report a SHA-2/SHA-3 digest for this package ({}); \
+             --require-strong-hash does not accept MD5 as proof of integrity.",
+            digest.display_line()
+        );
+    }
+    Ok(())
+}
+
+/// With `--require-strong-hash`, an unchanged digest only proves identical
+/// content if Jamf reports a SHA-2/SHA-3 digest that matches the local file.
+fn ensure_strong_match(digest: &PackageDigestSnapshot, local: &FileDigests) -> Result<()> {
+    ensure_strong_hash(digest)?;
+    if let Some((algorithm, remote)) = digest.comparable_digest() {
+        let local = local.of(algorithm);
+        if !remote.eq_ignore_ascii_case(local) {
+            bail!(
+                "Jamf digest did not change and its {} ({}) does not match the uploaded file ({})",
+                algorithm,
+                remote,
+                local
+            );
         }
-    };
+    }
+    Ok(())
+}
 
-    let pkg_id = package.id.clone();
+/// Write the current XML of each affected policy to `dir` as a pre-change
+/// snapshot for change control and rollback.
+async fn export_policies(
+    client: &JamfClient,
+    policies: &[AffectedPolicy],
+    dir: &Path,
+) -> Result<()> {
+    if policies.is_empty() {
+        return Ok(());
+    }
 
-    // For existing packages: check digest, skip if unchanged, scan policies, update metadata.
-    // For new packages: skip all of this — there is no existing payload or policy reference.
-    let previous_digest: Option<PackageDigestSnapshot> = if !is_new {
-        let digest = client.get_package_digest_snapshot(&pkg_id).await?;
-        match &digest {
-            Some(d) => println!("Current package digest: {}", d.display_line()),
-            None => println!("Current package digest metadata is unavailable via API."),
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create export directory: {}", dir.display()))?;
+
+    for policy in policies {
+        let xml = client.get_policy_xml(policy.id).await?;
+        let target = dir.join(policy_file_name(policy.id));
+        tokio::fs::write(&target, xml)
+            .await
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    status!(
+        "Exported {} policy XML file(s) to {}.",
+        policies.len(),
+        dir.display()
+    );
+
+    Ok(())
+}
+
+/// Whether JCDS holds `file_name`; None when its file list cannot be read.
+async fn jcds_holds(client: &JamfClient, file_name: &str) -> Option<bool> {
+    match client.list_jcds_files().await {
+        Ok(files) => files.map(|files| files.iter().any(|f| f.file_name == file_name)),
+        Err(e) => {
+            debug!(
+                "Could not list JCDS files: {}",
+                redact::scrub(&format!("{:#}", e))
+            );
+            None
         }
+    }
+}
 
-        // Exit early when Jamf already has the same payload (MD5 match).
-        if let Some(remote_md5) = digest.as_ref().and_then(|d| d.md5_hash.as_deref()) {
-            let local_md5 = compute_file_md5(path).await?;
-            println!("Local file MD5: {}", local_md5);
-            if remote_md5.eq_ignore_ascii_case(&local_md5) {
-                println!("Package payload already matches Jamf (MD5 unchanged).");
-                println!(
-                    "Package '{}' (ID: {}) is already up to date. Skipping update.",
-                    package_name, pkg_id
-                );
-                return Ok(());
-            }
+/// Abort before the upload when JCDS reports too little free space for the
+/// file. Instances that don't expose capacity, or fail to report it, are
+/// skipped silently: the check is only a preflight.
+async fn check_jcds_capacity(
+    client: &JamfClient,
+    path: &Path,
+    previous: Option<&PackageDigestSnapshot>,
+) -> Result<()> {
+    let info = match client.get_jcds_storage_info().await {
+        Ok(info) => info,
+        Err(e) => {
+            debug!(
+                "Could not read JCDS capacity, skipping the check: {}",
+                redact::scrub(&format!("{:#}", e))
+            );
+            return Ok(());
         }
+    };
 
-        // Scan policies for references to this package
-        println!("Scanning policies...");
-        let affected_policies = client
-            .find_policies_with_package(&package_name, &package.file_name)
-            .await?;
-        println!(
-            "Found {} {} referencing this package.",
-            affected_policies.len(),
-            if affected_policies.len() == 1 {
-                "policy"
+    if info.upload_capable == Some(false) {
+        outcome!("Warning: Jamf reports the cloud distribution point is not upload-capable.");
+    }
+
+    let Some(available) = info.available_bytes else {
+        return Ok(());
+    };
+
+    let file_size = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to read file metadata: {}", path.display()))?
+        .len();
+    // The old payload is deleted before uploading, so its space is reclaimed.
+    let reclaimed = previous.and_then(|d| d.file_size).unwrap_or(0);
+    if available.saturating_add(reclaimed) < file_size {
+        bail!(
+            "Not enough space on the distribution point: {} bytes needed, {} bytes available{}.",
+            file_size,
+            available,
+            if reclaimed > 0 {
+                format!(" (plus {} reclaimed from the old file)", reclaimed)
             } else {
-                "policies"
+                String::new()
             }
         );
-        for p in &affected_policies {
-            println!("  - {} (ID: {})", p.name, p.id);
-        }
+    }
 
-        // Update package metadata in-place (keep same ID, update fileName)
-        println!("Updating package metadata...");
-        let update_req = PackageCreateRequest::from_old(&package, &file_name, priority);
-        client.update_package(&pkg_id, &update_req).await?;
-        println!("Metadata updated.");
+    Ok(())
+}
 
-        digest
-    } else {
-        None
-    };
+/// When the exact name search misses, look for a record that differs only by
+/// surrounding whitespace or case and ask before creating a near-duplicate.
+async fn resolve_near_match(client: &JamfClient, package_name: &str) -> Result<Option<Package>> {
+    let candidates = client.find_similar_packages(package_name).await?;
+    if candidates.is_empty() {
+        return Ok(None);
+    }
 
-    // For existing packages, delete the old JCDS file first.  The v1 upload
-    // endpoint silently no-ops when a backing file already exists in JCDS.
-    if !is_new {
-        println!("Removing old JCDS file before re-upload...");
-        client.delete_jcds_file(&file_name).await?;
-        println!("Old file removed.");
+    if !prompt::is_interactive() {
+        let names: Vec<String> = candidates
+            .iter()
+            .map(|p| format!("'{}' (ID: {})", p.package_name, p.id))
+            .collect();
+        bail!(
+            "No package named exactly '{}', but found similar: {}. \
+             Pass --name with the exact name to update it, or rename the record in Jamf Pro.",
+            package_name,
+            names.join(", ")
+        );
     }
 
-    // Upload the file
-    println!("Uploading {}...", file_name);
-    client.upload_package(&pkg_id, path).await?;
-    println!("Upload complete.");
+    for candidate in candidates {
+        let question = format!(
+            "Package '{}' not found. Did you mean '{}' (ID: {}, file: {})?",
+            package_name, candidate.package_name, candidate.id, candidate.file_name
+        );
+        if prompt::confirm(&question)? {
+            return Ok(Some(candidate));
+        }
+    }
 
-    // Refresh JCDS inventory to recalculate checksums
-    println!("Refreshing package inventory (recalculating checksums)...");
-    client.refresh_jcds_inventory().await?;
-    println!("Inventory refresh requested.");
-
-    if let Some(previous) = previous_digest.as_ref() {
-        println!("Waiting for Jamf digest metadata to update...");
-        match wait_for_digest_change(
-            &client,
-            &pkg_id,
-            previous,
-            digest_poll_attempts,
-            digest_wait_timeout,
-        )
-        .await
-        {
-            Ok(refreshed_digest) => {
-                println!("Digest updated: {}", refreshed_digest.display_line());
-            }
-            Err(_) => {
-                // Digest didn't change — check whether the remote now matches
-                // the local file.  Rebuilds from identical source often produce
-                // files with different outer MD5s but identical payload content,
-                // so Jamf's stored digest stays the same.  Treat this as
-                // success when the remote MD5 matches the file we just uploaded.
-                let local_md5 = compute_file_md5(path).await?;
-                let remote_md5 = client
-                    .get_package_digest_snapshot(&pkg_id)
-                    .await?
-                    .and_then(|d| d.md5_hash);
+    Ok(None)
+}
 
-                if remote_md5
-                    .as_deref()
-                    .is_some_and(|r| r.eq_ignore_ascii_case(&local_md5))
-                {
-                    println!(
-                        "Digest unchanged but remote MD5 matches the uploaded file — content is identical."
-                    );
-                } else {
-                    bail!(
-                        "Upload completed but Jamf digest metadata did not update \
-                         after {} seconds and the remote MD5 ({}) does not match the \
-                         local file MD5 ({}). Previous digest: {}. \
-                         If your Jamf instance is slow to recalculate digests, \
-                         retry with --digest-wait-seconds 600.",
-                        digest_wait_timeout.as_secs(),
-                        remote_md5.as_deref().unwrap_or("unavailable"),
-                        local_md5,
-                        previous.display_line()
-                    );
-                }
+/// When the name search failed because several records share the name, let
+/// an interactive user pick one; otherwise (or for any other error) fail
+/// with the candidates listed so `--id` can be passed.
+fn choose_ambiguous(err: anyhow::Error) -> Result<Package> {
+    let ambiguous = match err.downcast::<AmbiguousPackage>() {
+        Ok(ambiguous) if prompt::is_interactive() => ambiguous,
+        Ok(ambiguous) => return Err(ambiguous.into()),
+        Err(err) => return Err(err),
+    };
+
+    let options: Vec<String> = ambiguous
+        .candidates
+        .iter()
+        .map(|p| {
+            format!(
+                "ID {}: file {}, category ID {}, priority {}",
+                p.id, p.file_name, p.category_id, p.priority
+            )
+        })
+        .collect();
+    let question = format!(
+        "{} packages are named '{}'. Which one should be updated?",
+        options.len(),
+        ambiguous.name
+    );
+    match prompt::choose(&question, &options)? {
+        Some(i) => Ok(ambiguous.candidates[i].clone()),
+        None => bail!(
+            "No package chosen; pass --id <ID> to pick one of the records named '{}'",
+            ambiguous.name
+        ),
+    }
+}
+
+/// Read the digest snapshot, riding out transient network loss. The upload has
+/// already succeeded by the time we poll, so a dropped connection should not
+/// abort the run; HTTP errors from Jamf still fail immediately.
+async fn fetch_snapshot_tolerant(
+    client: &JamfClient,
+    package_id: &str,
+) -> Result<Option<PackageDigestSnapshot>> {
+    let mut failures = 0;
+    loop {
+        match client.get_package_digest_snapshot(package_id).await {
+            Ok(snapshot) => {
+                debug!(
+                    "Digest poll for package {}: {}",
+                    package_id,
+                    snapshot
+                        .as_ref()
+                        .map(PackageDigestSnapshot::display_line)
+                        .unwrap_or_else(|| "no digest reported".to_string())
+                );
+                return Ok(snapshot);
+            }
+            Err(e) if failures < MAX_POLL_NETWORK_RETRIES && is_network_error(&e) => {
+                failures += 1;
+                status!(
+                    "  Network error while polling digest ({}); retry {}/{} in {}s...",
+                    e.root_cause(),
+                    failures,
+                    MAX_POLL_NETWORK_RETRIES,
+                    NETWORK_RETRY_INTERVAL.as_secs()
+                );
+                sleep(NETWORK_RETRY_INTERVAL).await;
             }
+            Err(e) => return Err(e),
         }
-    } else {
-        println!("Waiting for Jamf digest metadata to become available...");
-        let digest = wait_for_digest_availability(
-            &client,
-            &pkg_id,
-            digest_poll_attempts,
-            digest_wait_timeout,
-        )
-        .await?;
-        println!("Digest updated: {}", digest.display_line());
     }
+}
 
-    println!("Inventory refreshed.");
+/// How Jamf's digest settled after an upload to a package that had one.
+#[derive(Debug)]
+pub(crate) enum DigestOutcome {
+    /// The digest moved away from its pre-upload value.
+    Changed(PackageDigestSnapshot),
+    /// The digest did not change, but in the strongest algorithm Jamf reports
+    /// it equals the uploaded file's: an identical rebuild, where there is
+    /// nothing for Jamf to change.
+    UnchangedButContentMatches(PackageDigestSnapshot, HashType),
+    /// Neither by the end of the wait; the latest snapshot, if any.
+    Stale(Option<PackageDigestSnapshot>),
+}
 
-    if is_new {
-        println!(
-            "Package '{}' (ID: {}) created and uploaded successfully.",
-            package_name, pkg_id
-        );
-    } else {
-        println!(
-            "Package '{}' (ID: {}) updated successfully.",
-            package_name, pkg_id
-        );
-    }
+/// One digest poll, classified by [`settled_digest`].
+#[derive(Debug)]
+enum DigestPoll {
+    /// The wait can stop.
+    Settled(DigestOutcome),
+    /// Not settled yet; the snapshot polled, to report if the wait runs out.
+    Pending(PackageDigestSnapshot),
+}
 
-    Ok(())
+/// Classify one digest poll against the digests of the uploaded file.
+fn settled_digest(
+    current: PackageDigestSnapshot,
+    previous: &PackageDigestSnapshot,
+    local: &FileDigests,
+) -> DigestPoll {
+    if current.content_updated_from(previous) {
+        return DigestPoll::Settled(DigestOutcome::Changed(current));
+    }
+    match current.comparable_digest() {
+        Some((algorithm, remote)) if remote.eq_ignore_ascii_case(local.of(algorithm)) => {
+            DigestPoll::Settled(DigestOutcome::UnchangedButContentMatches(
+                current, algorithm,
+            ))
+        }
+        _ => DigestPoll::Pending(current),
+    }
 }
 
-async fn wait_for_digest_change(
+/// Poll until the digest changes from `previous` or already matches `local`,
+/// the digests of the file just uploaded.
+pub(crate) async fn wait_for_digest_change(
     client: &JamfClient,
     package_id: &str,
     previous: &PackageDigestSnapshot,
-    digest_poll_attempts: usize,
-    digest_wait_timeout: Duration,
-) -> Result<PackageDigestSnapshot> {
+    local: &FileDigests,
+    polling: &DigestPolling,
+) -> Result<DigestOutcome> {
     let mut latest_snapshot: Option<PackageDigestSnapshot> = None;
 
-    for attempt in 1..=digest_poll_attempts {
-        match client.get_package_digest_snapshot(package_id).await? {
+    for attempt in 1..=polling.attempts {
+        match fetch_snapshot_tolerant(client, package_id).await? {
             Some(current) => {
-                if current.content_updated_from(previous) {
-                    return Ok(current);
+                match settled_digest(current, previous, local) {
+                    DigestPoll::Settled(outcome) => return Ok(outcome),
+                    DigestPoll::Pending(current) => latest_snapshot = Some(current),
                 }
-
-                latest_snapshot = Some(current);
-                if attempt < digest_poll_attempts {
-                    println!(
+                if attempt < polling.attempts {
+                    status!(
                         "  Attempt {}/{}: digest value not updated yet, waiting {}s...",
                         attempt,
-                        digest_poll_attempts,
-                        DIGEST_POLL_INTERVAL.as_secs()
+                        polling.attempts,
+                        polling.interval.as_secs_f64()
                     );
                 } else {
-                    println!(
+                    status!(
                         "  Attempt {}/{}: digest value not updated yet (final check).",
-                        attempt, digest_poll_attempts
+                        attempt,
+                        polling.attempts
                     );
                 }
             }
             None => {
-                if attempt < digest_poll_attempts {
-                    println!(
+                if attempt < polling.attempts {
+                    status!(
                         "  Attempt {}/{}: digest metadata unavailable, waiting {}s...",
                         attempt,
-                        digest_poll_attempts,
-                        DIGEST_POLL_INTERVAL.as_secs()
+                        polling.attempts,
+                        polling.interval.as_secs_f64()
                     );
                 } else {
-                    println!(
+                    status!(
                         "  Attempt {}/{}: digest metadata unavailable (final check).",
-                        attempt, digest_poll_attempts
+                        attempt,
+                        polling.attempts
                     );
                 }
             }
         }
 
-        if attempt < digest_poll_attempts {
-            sleep(DIGEST_POLL_INTERVAL).await;
+        if attempt < polling.attempts {
+            sleep(polling.interval).await;
         }
     }
 
-    let previous_line = previous.display_line();
-    if let Some(latest) = latest_snapshot {
-        bail!(
-            "Upload completed but Jamf digest metadata did not change after {} seconds. Previous digest: {}. Latest digest: {}. If you intentionally uploaded an identical file, this can be expected.",
-            digest_wait_timeout.as_secs(),
-            previous_line,
-            latest.display_line()
-        );
-    }
-
-    bail!(
-        "Upload completed but Jamf digest metadata remained unavailable after {} seconds. Previous digest: {}.",
-        digest_wait_timeout.as_secs(),
-        previous_line
-    );
+    Ok(DigestOutcome::Stale(latest_snapshot))
 }
 
-async fn wait_for_digest_availability(
+pub(crate) async fn wait_for_digest_availability(
     client: &JamfClient,
     package_id: &str,
-    digest_poll_attempts: usize,
-    digest_wait_timeout: Duration,
+    polling: &DigestPolling,
 ) -> Result<PackageDigestSnapshot> {
     let mut latest_snapshot: Option<PackageDigestSnapshot> = None;
 
-    for attempt in 1..=digest_poll_attempts {
-        match client.get_package_digest_snapshot(package_id).await? {
+    for attempt in 1..=polling.attempts {
+        match fetch_snapshot_tolerant(client, package_id).await? {
             Some(current) => {
                 if current.has_verifiable_content() {
                     return Ok(current);
                 }
 
                 latest_snapshot = Some(current);
-                if attempt < digest_poll_attempts {
-                    println!(
+                if attempt < polling.attempts {
+                    status!(
                         "  Attempt {}/{}: digest fields incomplete, waiting {}s...",
                         attempt,
-                        digest_poll_attempts,
-                        DIGEST_POLL_INTERVAL.as_secs()
+                        polling.attempts,
+                        polling.interval.as_secs_f64()
                     );
                 } else {
-                    println!(
+                    status!(
                         "  Attempt {}/{}: digest fields incomplete (final check).",
-                        attempt, digest_poll_attempts
+                        attempt,
+                        polling.attempts
                     );
                 }
             }
             None => {
-                if attempt < digest_poll_attempts {
-                    println!(
+                if attempt < polling.attempts {
+                    status!(
                         "  Attempt {}/{}: digest metadata unavailable, waiting {}s...",
                         attempt,
-                        digest_poll_attempts,
-                        DIGEST_POLL_INTERVAL.as_secs()
+                        polling.attempts,
+                        polling.interval.as_secs_f64()
                     );
                 } else {
-                    println!(
+                    status!(
                         "  Attempt {}/{}: digest metadata unavailable (final check).",
-                        attempt, digest_poll_attempts
+                        attempt,
+                        polling.attempts
                     );
                 }
             }
         }
 
-        if attempt < digest_poll_attempts {
-            sleep(DIGEST_POLL_INTERVAL).await;
+        if attempt < polling.attempts {
+            sleep(polling.interval).await;
         }
     }
 
     if let Some(latest) = latest_snapshot {
         bail!(
             "Upload completed but Jamf digest fields remained incomplete after {} seconds. Latest digest: {}.",
-            digest_wait_timeout.as_secs(),
+            polling.timeout.as_secs(),
             latest.display_line()
         );
     }
 
     bail!(
         "Upload completed but Jamf digest metadata remained unavailable after {} seconds.",
-        digest_wait_timeout.as_secs()
+        polling.timeout.as_secs()
     );
 }
 
-fn digest_poll_attempts(wait_timeout: Duration) -> usize {
-    let wait_secs = wait_timeout.as_secs().max(1);
-    let interval_secs = DIGEST_POLL_INTERVAL.as_secs().max(1);
-    let attempts = wait_secs.div_ceil(interval_secs);
-    attempts as usize
+/// Whether the error chain contains a 401 from the token endpoint.
+fn is_auth_rejection(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<AuthRejected>())
 }
 
-async fn compute_file_md5(path: &Path) -> Result<String> {
-    let mut file = tokio::fs::File::open(path)
-        .await
-        .with_context(|| format!("Failed to open file for MD5: {}", path.display()))?;
-    let mut hasher = Md5::new();
-    let mut buf = [0_u8; 8192];
+/// What the uploaded payload should look like once Jamf has processed it.
+struct ExpectedUpload<'a> {
+    previous: Option<&'a PackageDigestSnapshot>,
+    local_md5: String,
+    local_size: u64,
+    history_baseline: Option<i64>,
+}
 
-    loop {
-        let n = file
-            .read(&mut buf)
-            .await
-            .with_context(|| format!("Failed reading file for MD5: {}", path.display()))?;
-        if n == 0 {
-            break;
+fn criterion_label(criterion: SuccessCriterion) -> &'static str {
+    match criterion {
+        SuccessCriterion::DigestChanged => "digest changed",
+        SuccessCriterion::Md5Match => "MD5 matches local file",
+        SuccessCriterion::SizeMatch => "file size matches local file",
+        SuccessCriterion::HistoryEntry => "new package history entry",
+    }
+}
+
+/// Evaluate a digest-based criterion against the current snapshot.
+fn snapshot_meets(
+    criterion: SuccessCriterion,
+    current: &PackageDigestSnapshot,
+    expected: &ExpectedUpload<'_>,
+) -> bool {
+    match criterion {
+        SuccessCriterion::DigestChanged => match expected.previous {
+            Some(previous) => current.content_updated_from(previous),
+            None => current.has_verifiable_content(),
+        },
+        SuccessCriterion::Md5Match => current
+            .md5_hash
+            .as_deref()
+            .is_some_and(|m| m.eq_ignore_ascii_case(&expected.local_md5)),
+        SuccessCriterion::SizeMatch => current.file_size == Some(expected.local_size),
+        SuccessCriterion::HistoryEntry => false,
+    }
+}
+
+/// Poll until the user-selected success criterion holds, returning a short
+/// description of the state that satisfied it.
+async fn wait_for_success_criterion(
+    client: &JamfClient,
+    package_id: &str,
+    criterion: SuccessCriterion,
+    expected: &ExpectedUpload<'_>,
+    polling: &DigestPolling,
+) -> Result<String> {
+    let mut last_state = "no data".to_string();
+
+    for attempt in 1..=polling.attempts {
+        let met = if criterion == SuccessCriterion::HistoryEntry {
+            let count = client.get_package_history_count(package_id).await?;
+            last_state = format!("{} history entries", count);
+            count > expected.history_baseline.unwrap_or(0)
+        } else {
+            match fetch_snapshot_tolerant(client, package_id).await? {
+                Some(current) => {
+                    last_state = current.display_line();
+                    snapshot_meets(criterion, &current, expected)
+                }
+                None => {
+                    last_state = "digest metadata unavailable".to_string();
+                    false
+                }
+            }
+        };
+
+        if met {
+            return Ok(last_state);
+        }
+
+        if attempt < polling.attempts {
+            status!(
+                "  Attempt {}/{}: not met yet ({}), waiting {}s...",
+                attempt,
+                polling.attempts,
+                last_state,
+                polling.interval.as_secs_f64()
+            );
+            sleep(polling.interval).await;
+        } else {
+            status!(
+                "  Attempt {}/{}: not met yet ({}) (final check).",
+                attempt,
+                polling.attempts,
+                last_state
+            );
+        }
+    }
+
+    bail!(
+        "Upload completed but the success criterion '{}' was not met after {} seconds. Latest state: {}.",
+        criterion_label(criterion),
+        polling.timeout.as_secs(),
+        last_state
+    );
+}
+
+/// How long and how often to poll Jamf for the processed upload.
+pub(crate) struct DigestPolling {
+    attempts: usize,
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl DigestPolling {
+    fn from_args(args: &UpdateArgs) -> Self {
+        Self::new(
+            Duration::from_secs(args.digest_wait_seconds),
+            args.digest_interval,
+        )
+    }
+
+    pub(crate) fn new(timeout: Duration, interval: Duration) -> Self {
+        let interval = interval.max(Duration::from_millis(1));
+        let attempts = timeout.as_millis().div_ceil(interval.as_millis()).max(1);
+        Self {
+            attempts: attempts as usize,
+            interval,
+            timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCAL_MD5: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn only_older_checksum_files_and_zipped_bundles_may_be_rewritten() {
+        let built = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let written = |secs: u64| ChecksumFile {
+            path: "App.pkg.sha256".into(),
+            hash_type: HashType::Sha256,
+            digest: "00".repeat(32),
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        };
+        assert!(is_stale_checksum_file(
+            &written(1_600_000_000),
+            Some(built),
+            false
+        ));
+        assert!(!is_stale_checksum_file(
+            &written(1_700_000_000),
+            Some(built),
+            false
+        ));
+        assert!(!is_stale_checksum_file(
+            &written(1_800_000_000),
+            None,
+            false
+        ));
+        assert!(is_stale_checksum_file(
+            &written(1_800_000_000),
+            Some(built),
+            true
+        ));
+    }
+
+    fn snapshot(md5: &str, size: u64) -> PackageDigestSnapshot {
+        PackageDigestSnapshot {
+            md5_hash: Some(md5.to_string()),
+            file_size: Some(size),
+            ..PackageDigestSnapshot::default()
+        }
+    }
+
+    fn local_digests() -> FileDigests {
+        FileDigests {
+            md5: LOCAL_MD5.to_string(),
+            sha256: String::new(),
+            sha512: "ab".repeat(64),
+            sha3_512: String::new(),
         }
-        hasher.update(&buf[..n]);
     }
 
-    Ok(format!("{:x}", hasher.finalize()))
+    #[test]
+    fn changed_digest_settles_as_changed() {
+        let previous = snapshot("ffffffffffffffffffffffffffffffff", 10);
+        let outcome = settled_digest(snapshot(LOCAL_MD5, 12), &previous, &local_digests());
+        assert!(matches!(
+            outcome,
+            DigestPoll::Settled(DigestOutcome::Changed(_))
+        ));
+    }
+
+    #[test]
+    fn unchanged_digest_matching_local_md5_settles_immediately() {
+        let previous = snapshot(&LOCAL_MD5.to_uppercase(), 10);
+        let outcome = settled_digest(previous.clone(), &previous, &local_digests());
+        assert!(matches!(
+            outcome,
+            DigestPoll::Settled(DigestOutcome::UnchangedButContentMatches(_, HashType::Md5))
+        ));
+    }
+
+    #[test]
+    fn unchanged_digest_is_compared_in_the_strongest_algorithm() {
+        // A matching MD5 does not settle it when Jamf reports a SHA-512 that
+        // differs from the uploaded file's.
+        let mut previous = snapshot(LOCAL_MD5, 10);
+        previous.hash_type = Some("SHA_512".to_string());
+        previous.hash_value = Some("cd".repeat(64));
+        let outcome = settled_digest(previous.clone(), &previous, &local_digests());
+        assert!(matches!(outcome, DigestPoll::Pending(_)));
+
+        previous.hash_value = Some("AB".repeat(64));
+        let outcome = settled_digest(previous.clone(), &previous, &local_digests());
+        assert!(matches!(
+            outcome,
+            DigestPoll::Settled(DigestOutcome::UnchangedButContentMatches(
+                _,
+                HashType::Sha512
+            ))
+        ));
+    }
+
+    #[test]
+    fn unchanged_digest_of_other_content_keeps_polling() {
+        let previous = snapshot("ffffffffffffffffffffffffffffffff", 10);
+        let outcome = settled_digest(previous.clone(), &previous, &local_digests());
+        assert!(matches!(outcome, DigestPoll::Pending(_)));
+    }
+
+    #[test]
+    fn digest_polling_spreads_the_timeout_over_the_interval() {
+        let attempts =
+            |timeout, interval| DigestPolling::new(Duration::from_secs(timeout), interval).attempts;
+        assert_eq!(attempts(300, Duration::from_secs(5)), 60);
+        assert_eq!(attempts(10, Duration::from_secs(3)), 4);
+        assert_eq!(attempts(1, Duration::from_secs(30)), 1);
+    }
+
+    #[tokio::test]
+    async fn digest_availability_polls_at_the_configured_interval() {
+        use std::sync::Arc;
+        use std::sync::atomic::Ordering;
+
+        use crate::mock::{self, MockState, package_record};
+
+        let state = Arc::new(MockState {
+            records: vec![package_record("1", "App", "App.pkg")],
+            ..MockState::default()
+        });
+        let client = mock::connect(state.clone()).await;
+
+        let polling = DigestPolling::new(Duration::from_millis(300), Duration::from_millis(100));
+        let started = std::time::Instant::now();
+        let err = wait_for_digest_availability(&client, "1", &polling)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("remained unavailable"), "{}", err);
+        assert_eq!(state.record_hits.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
 }
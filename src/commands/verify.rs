@@ -0,0 +1,488 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::api::client::JamfClient;
+use crate::checksum::compute_file_digest;
+use crate::cli::{GlobalArgs, VerifyArgs};
+use crate::credentials;
+use crate::files::{InstallerExtensions, collect_all_files, zip_if_bundle};
+use crate::junit;
+use crate::models::package::Package;
+use crate::naming::{NameTemplate, resolve_package_name};
+use crate::output::{self, status};
+use crate::progress::Spinner;
+use crate::redact;
+
+/// Outcome of checking one local file against its package in Jamf Pro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum VerifyStatus {
+    /// Jamf's strongest digest matches the file.
+    Verified,
+    Mismatch,
+    /// No package record to compare with.
+    Missing,
+    /// Several records share the name; pin one with `id` in a manifest.
+    Ambiguous,
+    /// Jamf reports no digest usable for the comparison.
+    Unverifiable,
+    Error,
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            VerifyStatus::Verified => "VERIFIED",
+            VerifyStatus::Mismatch => "MISMATCH",
+            VerifyStatus::Missing => "MISSING",
+            VerifyStatus::Ambiguous => "AMBIGUOUS",
+            VerifyStatus::Unverifiable => "UNVERIFIABLE",
+            VerifyStatus::Error => "ERROR",
+        };
+        f.pad(label)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyRow {
+    status: VerifyStatus,
+    file: PathBuf,
+    package_name: String,
+    package_id: Option<String>,
+    /// The algorithm compared: the strongest one Jamf reports.
+    algorithm: Option<String>,
+    local_digest: Option<String>,
+    remote_digest: Option<String>,
+    detail: String,
+    #[serde(skip)]
+    elapsed: std::time::Duration,
+}
+
+impl VerifyRow {
+    fn file_name(&self) -> String {
+        self.file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn junit_case(&self) -> junit::TestCase {
+        junit::TestCase {
+            name: self.file_name(),
+            classname: self.package_name.clone(),
+            time: self.elapsed,
+            outcome: match self.status {
+                VerifyStatus::Verified => junit::CaseOutcome::Passed,
+                _ => junit::CaseOutcome::Failed(format!("{}: {}", self.status, self.detail)),
+            },
+            output: Some(format!("{}: {}", self.status, self.detail)),
+        }
+    }
+}
+
+/// A `verify` manifest: the files to check and the package each should match.
+///
+/// ```toml
+/// [[packages]]
+/// path = "dist/App-2.3.0.pkg"   # relative to the manifest
+/// name = "App"                  # optional; default from the file name
+/// id = "42"                     # optional; skips the name search
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    packages: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    path: PathBuf,
+    name: Option<String>,
+    id: Option<String>,
+}
+
+/// A local file and how to find its package record.
+struct Target {
+    path: PathBuf,
+    name: Option<String>,
+    id: Option<String>,
+}
+
+/// Read-only audit: hash each local file and compare it with the strongest
+/// digest Jamf stores for its package. Fails unless every file is verified.
+pub async fn run(args: &VerifyArgs, global: &GlobalArgs) -> Result<()> {
+    let targets = collect_targets(args)?;
+    if args.name.is_some() && targets.len() > 1 {
+        bail!("--name cannot be used when verifying several files");
+    }
+    let template = args
+        .name_template
+        .as_deref()
+        .map(|t| NameTemplate::new(t, args.name_pattern.as_deref()))
+        .transpose()?;
+
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+    status!("Jamf Pro URL: {}", creds.url);
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await?;
+    let identity = client.identity().await?;
+    spinner.finish();
+    status!("Authenticated as {}.", identity);
+
+    status!("Verifying {} file(s)...", targets.len());
+    let mut rows = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let started = Instant::now();
+        let name = target.name.as_deref().or(args.name.as_deref());
+        let mut row = VerifyRow {
+            status: VerifyStatus::Error,
+            file: target.path.clone(),
+            package_name: String::new(),
+            package_id: None,
+            algorithm: None,
+            local_digest: None,
+            remote_digest: None,
+            detail: String::new(),
+            elapsed: Default::default(),
+        };
+        let checked = match resolve_package_name(&target.path, name, template.as_ref()) {
+            Ok(package_name) => {
+                row.package_name = package_name;
+                verify_file(&client, target, args.require_strong_hash, &mut row).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = checked {
+            row.status = VerifyStatus::Error;
+            row.detail = redact::scrub(&format!("{:#}", e));
+        }
+        row.elapsed = started.elapsed();
+        status!("{}: {}", row.status, row.file.display());
+        rows.push(row);
+    }
+
+    if global.json() {
+        output::print_json(&rows, "verification")?;
+    } else {
+        print_report(&rows);
+    }
+
+    if let Some(report) = &args.junit {
+        let cases: Vec<junit::TestCase> = rows.iter().map(VerifyRow::junit_case).collect();
+        junit::write_report(report, "verify", &cases)?;
+    }
+
+    let failed = rows
+        .iter()
+        .filter(|r| r.status != VerifyStatus::Verified)
+        .count();
+    if failed > 0 {
+        bail!(
+            "{} of {} file(s) could not be verified against Jamf Pro",
+            failed,
+            rows.len()
+        );
+    }
+    Ok(())
+}
+
+/// The files named on the command line, expanding directories and reading
+/// any `.toml` argument as a manifest.
+fn collect_targets(args: &VerifyArgs) -> Result<Vec<Target>> {
+    let extensions = InstallerExtensions::new(&args.allow_extension);
+    let mut targets = Vec::new();
+    for path in &args.paths {
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+        {
+            targets.extend(read_manifest(path)?);
+            continue;
+        }
+        let files = collect_all_files(std::slice::from_ref(path), &extensions)?;
+        extensions.warn_if_non_standard(&files);
+        targets.extend(files.into_iter().map(|path| Target {
+            path,
+            name: None,
+            id: None,
+        }));
+    }
+    Ok(targets)
+}
+
+fn read_manifest(path: &Path) -> Result<Vec<Target>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+    let manifest = parse_manifest(&contents)
+        .with_context(|| format!("Invalid manifest {}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    Ok(manifest
+        .packages
+        .into_iter()
+        .map(|entry| Target {
+            path: base.join(entry.path),
+            name: entry.name,
+            id: entry.id,
+        })
+        .collect())
+}
+
+fn parse_manifest(contents: &str) -> Result<Manifest> {
+    let manifest: Manifest = toml::from_str(contents).context("Failed to parse manifest")?;
+    if manifest.packages.is_empty() {
+        bail!("The manifest lists no [[packages]]");
+    }
+    Ok(manifest)
+}
+
+/// Find the file's package and compare digests, filling in `row`.
+async fn verify_file(
+    client: &JamfClient,
+    target: &Target,
+    require_strong_hash: bool,
+    row: &mut VerifyRow,
+) -> Result<()> {
+    if !target.path.is_file() {
+        bail!("File not found: {}", target.path.display());
+    }
+    let Some(package) = find_package(client, target, row).await? else {
+        return Ok(());
+    };
+    row.package_id = Some(package.id.clone());
+
+    let digest = client.get_package_digest_snapshot(&package.id).await?;
+    if require_strong_hash && !digest.as_ref().is_some_and(|d| d.has_strong_hash()) {
+        row.status = VerifyStatus::Unverifiable;
+        row.detail = format!(
+            "ID {}, Jamf reports no SHA-2/SHA-3 digest and --require-strong-hash rejects MD5",
+            package.id
+        );
+        return Ok(());
+    }
+    let Some((algorithm, remote)) = digest.as_ref().and_then(|d| d.comparable_digest()) else {
+        row.status = VerifyStatus::Unverifiable;
+        row.detail = format!("ID {}, Jamf reports no digest", package.id);
+        return Ok(());
+    };
+
+    let zipped = zip_if_bundle(&target.path).await?;
+    let local =
+        compute_file_digest(zipped.as_ref().map_or(&target.path, |z| &z.path), algorithm).await?;
+    row.status = if remote.eq_ignore_ascii_case(&local) {
+        VerifyStatus::Verified
+    } else {
+        VerifyStatus::Mismatch
+    };
+    row.detail = match row.status {
+        VerifyStatus::Verified => format!("ID {}, {} {}", package.id, algorithm, local),
+        _ => format!(
+            "ID {}, {} local {} but Jamf has {}",
+            package.id, algorithm, local, remote
+        ),
+    };
+    row.algorithm = Some(algorithm.to_string());
+    row.local_digest = Some(local);
+    row.remote_digest = Some(remote.to_string());
+    Ok(())
+}
+
+/// The target's package record by ID or exact name. None (with the row's
+/// status set) when there is no single record to compare with.
+async fn find_package(
+    client: &JamfClient,
+    target: &Target,
+    row: &mut VerifyRow,
+) -> Result<Option<Package>> {
+    if let Some(id) = target.id.as_deref() {
+        let package = client
+            .get_package(id)
+            .await
+            .with_context(|| format!("Package ID {} could not be read", id))?;
+        row.package_name = package.package_name.clone();
+        return Ok(Some(package));
+    }
+
+    let mut matches = client.find_packages(&row.package_name).await?;
+    match matches.len() {
+        0 => {
+            row.status = VerifyStatus::Missing;
+            row.detail = "no package record with this name".to_string();
+            Ok(None)
+        }
+        1 => Ok(matches.pop()),
+        n => {
+            let ids: Vec<&str> = matches.iter().map(|p| p.id.as_str()).collect();
+            row.status = VerifyStatus::Ambiguous;
+            row.detail = format!("{} records share this name (IDs: {})", n, ids.join(", "));
+            Ok(None)
+        }
+    }
+}
+
+fn print_report(rows: &[VerifyRow]) {
+    let package_width = rows
+        .iter()
+        .map(|r| r.package_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("PACKAGE".len());
+    let file_width = rows
+        .iter()
+        .map(|r| r.file_name().len())
+        .max()
+        .unwrap_or(0)
+        .max("FILE".len());
+
+    println!();
+    println!(
+        "{:<12} {:<package_width$} {:<file_width$} DETAIL",
+        "STATUS", "PACKAGE", "FILE"
+    );
+    for row in rows {
+        println!(
+            "{:<12} {:<package_width$} {:<file_width$} {}",
+            row.status,
+            row.package_name,
+            row.file_name(),
+            row.detail
+        );
+    }
+
+    let verified = rows
+        .iter()
+        .filter(|r| r.status == VerifyStatus::Verified)
+        .count();
+    println!();
+    println!(
+        "{} of {} file(s) verified, {} failed.",
+        verified,
+        rows.len(),
+        rows.len() - verified
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{Target, VerifyRow, VerifyStatus, parse_manifest, verify_file};
+    use crate::api::client::JamfClient;
+    use crate::mock::{self, MockState, package_record};
+
+    const ABC_SHA512: &str = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                              2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+    async fn verify(
+        client: &JamfClient,
+        path: &Path,
+        name: &str,
+        id: Option<&str>,
+        require_strong_hash: bool,
+    ) -> VerifyRow {
+        let target = Target {
+            path: path.to_path_buf(),
+            name: None,
+            id: id.map(str::to_string),
+        };
+        let mut row = VerifyRow {
+            status: VerifyStatus::Error,
+            file: target.path.clone(),
+            package_name: name.to_string(),
+            package_id: None,
+            algorithm: None,
+            local_digest: None,
+            remote_digest: None,
+            detail: String::new(),
+            elapsed: Default::default(),
+        };
+        verify_file(client, &target, require_strong_hash, &mut row)
+            .await
+            .unwrap();
+        row
+    }
+
+    #[tokio::test]
+    async fn verifies_matching_files_and_flags_mismatches() {
+        let mut app = package_record("1", "App", "App.pkg");
+        app["md5Hash"] = json!("00000000000000000000000000000000");
+        app["hashType"] = json!("SHA_512");
+        app["hashValue"] = json!(ABC_SHA512);
+        let mut old = package_record("2", "Old", "Old.pkg");
+        old["md5Hash"] = json!("00000000000000000000000000000000");
+        let state = Arc::new(MockState {
+            records: vec![
+                app,
+                old,
+                package_record("3", "Twin", "Twin.pkg"),
+                package_record("4", "Twin", "Twin-copy.pkg"),
+            ],
+            ..MockState::default()
+        });
+        let client = mock::connect(state).await;
+        let path = std::env::temp_dir().join(format!("jpu-verify-{}.pkg", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+
+        // The strongest digest decides, even though the MD5 differs.
+        let row = verify(&client, &path, "App", None, false).await;
+        assert_eq!(row.status, VerifyStatus::Verified, "{}", row.detail);
+        assert_eq!(row.algorithm.as_deref(), Some("SHA-512"));
+        assert_eq!(row.package_id.as_deref(), Some("1"));
+
+        let row = verify(&client, &path, "Old", None, false).await;
+        assert_eq!(row.status, VerifyStatus::Mismatch);
+        assert_eq!(
+            row.local_digest.as_deref(),
+            Some("900150983cd24fb0d6963f7d28e17f72")
+        );
+        let row = verify(&client, &path, "Old", None, true).await;
+        assert_eq!(row.status, VerifyStatus::Unverifiable);
+
+        let row = verify(&client, &path, "Twin", None, false).await;
+        assert_eq!(row.status, VerifyStatus::Ambiguous);
+        let row = verify(&client, &path, "Twin", Some("3"), false).await;
+        assert_eq!(row.status, VerifyStatus::Unverifiable);
+        assert_eq!(row.package_id.as_deref(), Some("3"));
+
+        let row = verify(&client, &path, "Missing", None, false).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(row.status, VerifyStatus::Missing);
+    }
+
+    #[test]
+    fn manifest_entries_take_optional_name_and_id() {
+        let manifest = parse_manifest(
+            r#"
+            [[packages]]
+            path = "App-2.3.0.pkg"
+
+            [[packages]]
+            path = "tools/Tool.pkg"
+            name = "Tool"
+            id = "42"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.packages.len(), 2);
+        assert_eq!(manifest.packages[1].name.as_deref(), Some("Tool"));
+        assert_eq!(manifest.packages[1].id.as_deref(), Some("42"));
+
+        assert!(parse_manifest("[[packages]]\npath = \"a.pkg\"\nsha = \"x\"\n").is_err());
+        assert!(parse_manifest("").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::api::client::{AuthMode, JamfClient};
+use crate::cli::GlobalArgs;
+use crate::credentials;
+use crate::output::{self, outcome};
+use crate::progress::Spinner;
+
+/// What `whoami` found, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WhoamiReport {
+    url: String,
+    credential_source: String,
+    auth_mode: AuthMode,
+    identity: String,
+    jamf_version: Option<String>,
+}
+
+/// Check that the stored credentials authenticate and can read packages,
+/// without changing anything.
+pub async fn run(global: &GlobalArgs) -> Result<()> {
+    let creds = credentials::load_credentials(&global.profile, global.config.as_deref())?;
+    outcome!("Jamf Pro URL: {}", creds.url);
+    outcome!("Credentials from: {}", creds.source);
+    if creds.auth_mode == AuthMode::Basic {
+        outcome!("Auth mode: basic (user {})", creds.client_id);
+    }
+
+    let spinner = Spinner::start("Authenticating...", global.quiet);
+    let client = JamfClient::connect(
+        &creds.url,
+        &creds.client_id,
+        &creds.client_secret,
+        &global.connect_options_for(&creds),
+    )
+    .await;
+    spinner.finish();
+    let client = client?;
+    outcome!("Access token obtained.");
+
+    let identity = client.identity().await?;
+    outcome!("Authenticated as {}.", identity);
+    let jamf_version = client.jamf_version().await.ok().map(str::to_string);
+    if let Some(version) = &jamf_version {
+        outcome!("Jamf Pro version: {}", version);
+    }
+
+    client
+        .check_auth()
+        .await
+        .context("Authenticated, but the token cannot read packages")?;
+    outcome!("Package read access: OK");
+
+    if global.json() {
+        output::print_json(
+            &WhoamiReport {
+                url: creds.url,
+                credential_source: creds.source.to_string(),
+                auth_mode: creds.auth_mode,
+                identity,
+                jamf_version,
+            },
+            "whoami result",
+        )?;
+    }
+    Ok(())
+}
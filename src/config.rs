@@ -0,0 +1,261 @@
+//! Defaults from the config file's `[defaults]` table, and the `[notify]`
+//! table for update notifications.
+//!
+//! The same `config.toml` that can hold credentials may set defaults for
+//! common options. A value given on the command line or through an
+//! environment variable always wins; a default only replaces clap's built-in
+//! one.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::api::distribution::DistributionTarget;
+use crate::cli::{
+    Cli, Commands, OutputFormat, parse_concurrency, parse_duration, parse_positive_duration,
+    parse_upload_rate,
+};
+use crate::credentials::default_config_path;
+
+/// The `[defaults]` table. Keys are named after the long options they stand
+/// in for, with underscores.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// Jamf Pro URL for `auth`, and for credentials from the environment
+    /// when `JAMF_URL` is not set.
+    pub url: Option<String>,
+    pub category: Option<String>,
+    pub priority: Option<i32>,
+    pub scan_concurrency: Option<usize>,
+    pub jobs: Option<usize>,
+    pub retries: Option<u32>,
+    pub retry_delay: Option<String>,
+    pub connect_timeout: Option<String>,
+    pub request_timeout: Option<String>,
+    /// Bytes per second, e.g. `"1MiB"`, as for `--min-upload-rate`.
+    pub min_upload_rate: Option<String>,
+    pub digest_wait_seconds: Option<u64>,
+    pub digest_interval: Option<String>,
+    /// Mounted share directory or `smb://` URL, as for `--distribution-point`.
+    pub distribution_point: Option<String>,
+    pub output: Option<String>,
+}
+
+/// The `[notify]` table: where `update` posts a summary of each run (see
+/// [`crate::notify`]).
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notify {
+    /// Slack incoming-webhook URL.
+    pub slack_webhook: Option<String>,
+    /// URL that receives the run as a JSON document.
+    pub generic_webhook: Option<String>,
+}
+
+/// The tables of the config file this module reads. Credentials under
+/// `[profiles]` are read by [`crate::credentials`].
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub defaults: Defaults,
+    #[serde(default)]
+    pub notify: Notify,
+}
+
+/// Read `[defaults]` and `[notify]` from `config` (or the default location).
+/// A missing file or table means no defaults and no notifications, as does
+/// having no config directory at all (no `HOME`, e.g. in a container).
+pub fn load(config: Option<&Path>) -> Result<Settings> {
+    let path = match config {
+        Some(path) => path.to_path_buf(),
+        None => match default_config_path() {
+            Ok(path) => path,
+            Err(_) => return Ok(Settings::default()),
+        },
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read config file {}", path.display()));
+        }
+    };
+    parse_settings(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+fn parse_settings(contents: &str) -> Result<Settings> {
+    toml::from_str(contents).context("Failed to parse [defaults] and [notify]")
+}
+
+/// Fill every option the user did not set with its config-file default.
+pub fn apply_defaults(cli: &mut Cli, matches: &ArgMatches, defaults: &Defaults) -> Result<()> {
+    let unset = |matches: &ArgMatches, id: &str| {
+        matches!(
+            matches.value_source(id),
+            None | Some(ValueSource::DefaultValue)
+        )
+    };
+
+    let global = &mut cli.global;
+    if let Some(output) = defaults.output.as_deref()
+        && unset(matches, "output")
+    {
+        global.output = OutputFormat::from_str(output, true)
+            .map_err(|_| anyhow!("Invalid output '{}' in [defaults]", output))?;
+    }
+    if let Some(n) = defaults.scan_concurrency
+        && unset(matches, "scan_concurrency")
+    {
+        global.scan_concurrency = parse_concurrency(&n.to_string())
+            .map_err(|e| anyhow!("Invalid scan_concurrency in [defaults]: {}", e))?;
+    }
+    if let Some(retries) = defaults.retries
+        && unset(matches, "retries")
+    {
+        global.retries = retries;
+    }
+    if let Some(delay) = defaults.retry_delay.as_deref()
+        && unset(matches, "retry_delay")
+    {
+        global.retry_delay = duration_default("retry_delay", delay, parse_duration)?;
+    }
+    if let Some(timeout) = defaults.connect_timeout.as_deref()
+        && unset(matches, "connect_timeout")
+    {
+        global.connect_timeout =
+            duration_default("connect_timeout", timeout, parse_positive_duration)?;
+    }
+    if let Some(timeout) = defaults.request_timeout.as_deref()
+        && unset(matches, "request_timeout")
+    {
+        global.request_timeout =
+            duration_default("request_timeout", timeout, parse_positive_duration)?;
+    }
+    if let Some(rate) = defaults.min_upload_rate.as_deref()
+        && unset(matches, "min_upload_rate")
+    {
+        global.min_upload_rate = parse_upload_rate(rate)
+            .map_err(|e| anyhow!("Invalid min_upload_rate in [defaults]: {}", e))?;
+    }
+
+    match &mut cli.command {
+        Commands::Auth { url, .. } if url.is_none() => url.clone_from(&defaults.url),
+        Commands::Update(args) => {
+            let Some(matches) = matches.subcommand_matches("update") else {
+                return Ok(());
+            };
+            if args.category.is_none() {
+                args.category.clone_from(&defaults.category);
+            }
+            if args.priority.is_none() {
+                args.priority = defaults.priority;
+            }
+            if let Some(n) = defaults.jobs
+                && unset(matches, "jobs")
+            {
+                args.jobs = parse_concurrency(&n.to_string())
+                    .map_err(|e| anyhow!("Invalid jobs in [defaults]: {}", e))?;
+            }
+            if let Some(seconds) = defaults.digest_wait_seconds
+                && unset(matches, "digest_wait_seconds")
+            {
+                if seconds == 0 {
+                    return Err(anyhow!(
+                        "Invalid digest_wait_seconds in [defaults]: must be at least 1"
+                    ));
+                }
+                args.digest_wait_seconds = seconds;
+            }
+            if let Some(interval) = defaults.digest_interval.as_deref()
+                && unset(matches, "digest_interval")
+            {
+                args.digest_interval =
+                    duration_default("digest_interval", interval, parse_positive_duration)?;
+            }
+            if let Some(target) = defaults.distribution_point.as_deref()
+                && unset(matches, "distribution_point")
+            {
+                args.distribution_point = Some(DistributionTarget::parse(target).map_err(|e| {
+                    anyhow!(
+                        "Invalid distribution_point '{}' in [defaults]: {}",
+                        target,
+                        e
+                    )
+                })?);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn duration_default(
+    key: &str,
+    value: &str,
+    parse: fn(&str) -> Result<Duration, String>,
+) -> Result<Duration> {
+    parse(value).map_err(|e| anyhow!("Invalid {} '{}' in [defaults]: {}", key, value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::{apply_defaults, parse_settings};
+    use crate::cli::{Cli, Commands, OutputFormat};
+
+    #[test]
+    fn defaults_fill_only_options_left_unset() {
+        let settings = parse_settings(
+            r#"
+            [defaults]
+            category = "Apps"
+            priority = 5
+            retries = 6
+            digest_interval = "30s"
+            output = "json"
+
+            [notify]
+            slack_webhook = "https://hooks.slack.com/services/T0/B0/x"
+
+            [profiles.default]
+            client_id = "id"
+            "#,
+        )
+        .unwrap();
+        let defaults = settings.defaults;
+        assert!(settings.notify.slack_webhook.is_some());
+        assert!(settings.notify.generic_webhook.is_none());
+        let matches = Cli::command()
+            .try_get_matches_from([
+                "jamf-package-updater",
+                "--retries",
+                "1",
+                "update",
+                "App.pkg",
+                "--priority",
+                "9",
+            ])
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+
+        apply_defaults(&mut cli, &matches, &defaults).unwrap();
+
+        assert_eq!(cli.global.output, OutputFormat::Json);
+        assert_eq!(cli.global.retries, 1);
+        let Commands::Update(args) = &cli.command else {
+            panic!("expected update");
+        };
+        assert_eq!(args.category.as_deref(), Some("Apps"));
+        assert_eq!(args.priority, Some(9));
+        assert_eq!(args.digest_interval, Duration::from_secs(30));
+    }
+}
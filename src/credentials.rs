@@ -1,68 +1,674 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::api::client::AuthMode;
+use crate::events::outcome;
+use crate::redact;
 
 const SERVICE: &str = "jamf-package-updater";
 
+/// Profile used when `--profile` / `JAMF_PROFILE` is not given. Its keyring
+/// entries and environment variables keep their original, unprefixed names.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(PartialEq, Eq)]
 pub struct Credentials {
+    /// API client ID, or the username with [`AuthMode::Basic`].
     pub client_id: String,
+    /// API client secret, or the password with [`AuthMode::Basic`].
     pub client_secret: String,
     pub url: String,
+    pub auth_mode: AuthMode,
+    pub source: CredentialSource,
+}
+
+/// Where a set of credentials was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    Environment,
+    ConfigFile(PathBuf),
+    Keyring,
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Environment => f.write_str("environment variables"),
+            Self::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            Self::Keyring => f.write_str("keyring"),
+        }
+    }
 }
 
-pub fn store_credentials(client_id: &str, client_secret: &str, url: &str) -> Result<()> {
+/// Store all four credential entries, or none of them.
+///
+/// Keyring writes are independent, so a failure part-way through would leave
+/// a mix of old and new values. Previous values are read first and restored
+/// if any write fails.
+pub fn store_credentials(
+    profile: &str,
+    client_id: &str,
+    client_secret: &str,
+    url: &str,
+    auth_mode: AuthMode,
+) -> Result<()> {
+    validate_profile(profile)?;
     let url = url.trim_end_matches('/');
+    let values = [
+        (account_name(profile, "client_id"), client_id),
+        (account_name(profile, "client_secret"), client_secret),
+        (account_name(profile, "url"), url),
+        (
+            account_name(profile, "auth_mode"),
+            auth_mode_name(auth_mode),
+        ),
+    ];
 
-    keyring::Entry::new(SERVICE, "client_id")
-        .context("Failed to create keyring entry for client_id")?
-        .set_password(client_id)
-        .context("Failed to store client_id in keyring")?;
+    let mut previous: Vec<(&str, Option<String>)> = Vec::with_capacity(values.len());
+    for (account, _) in &values {
+        let entry = keyring::Entry::new(SERVICE, account)
+            .with_context(|| format!("Failed to create keyring entry for {}", account))?;
+        previous.push((account.as_str(), entry.get_password().ok()));
+    }
 
-    keyring::Entry::new(SERVICE, "client_secret")
-        .context("Failed to create keyring entry for client_secret")?
-        .set_password(client_secret)
-        .context("Failed to store client_secret in keyring")?;
+    for (i, (account, value)) in values.iter().enumerate() {
+        let result = keyring::Entry::new(SERVICE, account)
+            .with_context(|| format!("Failed to create keyring entry for {}", account))
+            .and_then(|entry| {
+                entry
+                    .set_password(value)
+                    .with_context(|| format!("Failed to store {} in keyring", account))
+            });
 
-    keyring::Entry::new(SERVICE, "url")
-        .context("Failed to create keyring entry for url")?
-        .set_password(url)
-        .context("Failed to store url in keyring")?;
+        if let Err(e) = result {
+            restore_entries(&previous[..i]);
+            return Err(e);
+        }
+    }
 
     Ok(())
 }
 
-pub fn load_credentials() -> Result<Credentials> {
-    // Try environment variables first (for CI / GitHub Actions)
-    if let (Ok(client_id), Ok(client_secret), Ok(url)) = (
-        env::var("JAMF_CLIENT_ID"),
-        env::var("JAMF_CLIENT_SECRET"),
-        env::var("JAMF_URL"),
-    ) {
+/// Best-effort rollback of keyring entries to their earlier values.
+fn restore_entries(previous: &[(&str, Option<String>)]) {
+    for (account, value) in previous {
+        let Ok(entry) = keyring::Entry::new(SERVICE, account) else {
+            continue;
+        };
+        let restored = match value {
+            Some(v) => entry.set_password(v),
+            None => entry.delete_credential(),
+        };
+        if restored.is_err() {
+            outcome!(
+                "Warning: could not restore previous keyring value for {}",
+                account
+            );
+        }
+    }
+}
+
+/// Load the profile's credentials from environment variables, then the
+/// config file (`config` or the default location), then the keyring. The
+/// source used is logged (`-v`) and recorded in [`Credentials::source`].
+pub fn load_credentials(profile: &str, config: Option<&Path>) -> Result<Credentials> {
+    let creds = find_credentials(profile, config)?;
+    debug!(
+        "Using credentials for profile '{}' from {}",
+        profile, creds.source
+    );
+    Ok(creds)
+}
+
+fn find_credentials(profile: &str, config: Option<&Path>) -> Result<Credentials> {
+    validate_profile(profile)?;
+
+    // Try environment variables first (for CI / GitHub Actions). The URL
+    // may come from `[defaults]` in the config file instead.
+    if let (Ok(client_id), Ok(client_secret)) = (
+        env::var(env_var_name(profile, "CLIENT_ID")),
+        env::var(env_var_name(profile, "CLIENT_SECRET")),
+    ) && let Some(url) = match env::var(env_var_name(profile, "URL")) {
+        Ok(url) => Some(url),
+        Err(_) => default_url(config)?,
+    } {
+        redact::register(&client_secret);
+        let auth_mode = match env::var(env_var_name(profile, "AUTH_MODE")) {
+            Ok(mode) => parse_auth_mode(&mode)
+                .with_context(|| format!("Invalid {}", env_var_name(profile, "AUTH_MODE")))?,
+            Err(_) => AuthMode::default(),
+        };
         return Ok(Credentials {
             client_id,
             client_secret,
             url: url.trim_end_matches('/').to_string(),
+            auth_mode,
+            source: CredentialSource::Environment,
         });
     }
 
+    // Then the config file, for machines without a usable keyring
+    if let Some(creds) = load_file_credentials(profile, config)? {
+        return Ok(creds);
+    }
+
     // Fall back to keyring
-    let client_id = keyring::Entry::new(SERVICE, "client_id")
+    load_keyring_credentials(profile)
+}
+
+/// Read the profile's credentials from the keyring only, ignoring any
+/// environment variables.
+pub fn load_keyring_credentials(profile: &str) -> Result<Credentials> {
+    let client_id = keyring::Entry::new(SERVICE, &account_name(profile, "client_id"))
         .context("Failed to access keyring")?
         .get_password()
-        .context("No credentials found. Run `jamf-package-updater auth` first or set JAMF_CLIENT_ID, JAMF_CLIENT_SECRET, JAMF_URL environment variables.")?;
+        .with_context(|| missing_credentials_message(profile))?;
 
-    let client_secret = keyring::Entry::new(SERVICE, "client_secret")
+    let client_secret = keyring::Entry::new(SERVICE, &account_name(profile, "client_secret"))
         .context("Failed to access keyring")?
         .get_password()
         .context("client_secret not found in keyring")?;
 
-    let url = keyring::Entry::new(SERVICE, "url")
+    let url = keyring::Entry::new(SERVICE, &account_name(profile, "url"))
         .context("Failed to access keyring")?
         .get_password()
         .context("url not found in keyring")?;
 
+    // Stored before basic auth existed: OAuth client credentials.
+    let auth_mode = match keyring::Entry::new(SERVICE, &account_name(profile, "auth_mode"))
+        .context("Failed to access keyring")?
+        .get_password()
+    {
+        Ok(mode) => parse_auth_mode(&mode).context("Invalid auth_mode in keyring")?,
+        Err(_) => AuthMode::default(),
+    };
+
+    redact::register(&client_secret);
     Ok(Credentials {
         client_id,
         client_secret,
         url,
+        auth_mode,
+        source: CredentialSource::Keyring,
     })
 }
+
+/// `config.toml` in the platform config directory (e.g.
+/// `~/.config/jamf-package-updater/` on Linux).
+pub fn default_config_path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Cannot determine a config directory for the config file")?
+        .join(SERVICE)
+        .join("config.toml"))
+}
+
+/// Read the profile's `[profiles.<name>]` table from the config file.
+///
+/// A missing file at the default location is not an error (`Ok(None)`), but
+/// one given explicitly must exist. A file without this profile is `Ok(None)`.
+pub fn load_file_credentials(profile: &str, config: Option<&Path>) -> Result<Option<Credentials>> {
+    let Some((path, table)) = read_config_table(config)? else {
+        return Ok(None);
+    };
+    let Some(entry) = profile_table(&table, profile) else {
+        return Ok(None);
+    };
+    let field = |key: &str| -> Result<String> {
+        entry
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .with_context(|| {
+                format!(
+                    "{} is missing from [profiles.{}] in {}",
+                    key,
+                    profile,
+                    path.display()
+                )
+            })
+    };
+    let client_id = field("client_id")?;
+    let client_secret = match entry.get("client_secret_sealed").and_then(|v| v.as_str()) {
+        Some(sealed) => {
+            let passphrase = env::var(env_var_name(profile, "CREDENTIALS_PASSPHRASE")).ok();
+            crate::sealed::open(sealed, passphrase.as_deref()).with_context(|| {
+                format!(
+                    "Failed to decrypt client_secret_sealed in [profiles.{}] in {} (passphrase from {})",
+                    profile,
+                    path.display(),
+                    env_var_name(profile, "CREDENTIALS_PASSPHRASE")
+                )
+            })?
+        }
+        None => field("client_secret")?,
+    };
+    let url = field("url")?;
+    let auth_mode = match entry.get("auth_mode").and_then(|v| v.as_str()) {
+        Some(mode) => parse_auth_mode(mode).with_context(|| {
+            format!(
+                "Invalid auth_mode in [profiles.{}] in {}",
+                profile,
+                path.display()
+            )
+        })?,
+        None => AuthMode::default(),
+    };
+
+    redact::register(&client_secret);
+    Ok(Some(Credentials {
+        client_id,
+        client_secret,
+        url: url.trim_end_matches('/').to_string(),
+        auth_mode,
+        source: CredentialSource::ConfigFile(path),
+    }))
+}
+
+/// `url` from `[defaults]` in the config file, for environment credentials
+/// given without a URL. A config file that cannot be read is an error, not a
+/// reason to fall back to the keyring.
+fn default_url(config: Option<&Path>) -> Result<Option<String>> {
+    let Some((path, table)) = read_config_table(config)? else {
+        return Ok(None);
+    };
+    match table
+        .get("defaults")
+        .and_then(|defaults| defaults.get("url"))
+    {
+        Some(url) => url
+            .as_str()
+            .map(|url| Some(url.to_string()))
+            .with_context(|| format!("url in [defaults] in {} is not a string", path.display())),
+        None => Ok(None),
+    }
+}
+
+/// The config file (`config` or the default location) and its path. A
+/// missing file at the default location is `Ok(None)`, but one given
+/// explicitly must exist.
+fn read_config_table(config: Option<&Path>) -> Result<Option<(PathBuf, toml::Table)>> {
+    let path = match config {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path()?,
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && config.is_none() => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read config file {}", path.display()));
+        }
+    };
+    let table = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+    Ok(Some((path, table)))
+}
+
+fn profile_table<'a>(table: &'a toml::Table, profile: &str) -> Option<&'a toml::Table> {
+    table.get("profiles")?.as_table()?.get(profile)?.as_table()
+}
+
+/// Write the profile's credentials to the config file, keeping any other
+/// profiles and settings in it, with their comments and layout. The file is
+/// only readable by the owner.
+///
+/// With `encrypt`, the secret is stored as `client_secret_sealed`, encrypted
+/// with the profile's `JAMF_CREDENTIALS_PASSPHRASE` (prefixed like the other
+/// variables for named profiles) or, when that is unset, this machine's ID.
+pub fn store_file_credentials(
+    profile: &str,
+    config: Option<&Path>,
+    client_id: &str,
+    client_secret: &str,
+    url: &str,
+    auth_mode: AuthMode,
+    encrypt: bool,
+) -> Result<PathBuf> {
+    validate_profile(profile)?;
+    let path = match config {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path()?,
+    };
+    let mut document = read_config_document(&path)?;
+
+    let profiles = document.entry("profiles").or_insert_with(|| {
+        let mut profiles = toml_edit::Table::new();
+        profiles.set_implicit(true);
+        profiles.into()
+    });
+    let Some(profiles) = profiles.as_table_like_mut() else {
+        bail!("'profiles' in {} is not a table", path.display());
+    };
+    let entry = profiles
+        .entry(profile)
+        .or_insert_with(|| toml_edit::Table::new().into());
+    let Some(entry) = entry.as_table_like_mut() else {
+        bail!(
+            "'profiles.{}' in {} is not a table",
+            profile,
+            path.display()
+        );
+    };
+    entry.insert("client_id", toml_edit::value(client_id));
+    if encrypt {
+        let passphrase = env::var(env_var_name(profile, "CREDENTIALS_PASSPHRASE")).ok();
+        let sealed = crate::sealed::seal(client_secret, passphrase.as_deref())?;
+        entry.insert("client_secret_sealed", toml_edit::value(sealed));
+        entry.remove("client_secret");
+    } else {
+        entry.insert("client_secret", toml_edit::value(client_secret));
+        entry.remove("client_secret_sealed");
+    }
+    entry.insert("url", toml_edit::value(url.trim_end_matches('/')));
+    entry.insert("auth_mode", toml_edit::value(auth_mode_name(auth_mode)));
+
+    write_config_document(&path, &document)?;
+    Ok(path)
+}
+
+/// Remove the profile's entry from the config file. Returns the file's path
+/// if there was an entry to remove.
+pub fn clear_file_credentials(profile: &str, config: Option<&Path>) -> Result<Option<PathBuf>> {
+    validate_profile(profile)?;
+    let path = match config {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path()?,
+    };
+    let mut document = read_config_document(&path)?;
+    let removed = document
+        .get_mut("profiles")
+        .and_then(|profiles| profiles.as_table_like_mut())
+        .and_then(|profiles| profiles.remove(profile))
+        .is_some();
+    if !removed {
+        return Ok(None);
+    }
+    write_config_document(&path, &document)?;
+    Ok(Some(path))
+}
+
+/// The config file as an editable document, or an empty one if it does not
+/// exist. Editing it keeps the comments and formatting of everything else.
+fn read_config_document(path: &Path) -> Result<toml_edit::DocumentMut> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse()
+            .with_context(|| format!("Failed to parse config file {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(toml_edit::DocumentMut::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read config file {}", path.display())),
+    }
+}
+
+/// Replace the config file with `document`. It is written to a new 0600 file
+/// in the same directory, synced, and renamed over the original, so a crash
+/// or full disk never leaves a truncated config behind. A symlinked config
+/// is replaced at its target.
+fn write_config_document(path: &Path, document: &toml_edit::DocumentMut) -> Result<()> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create config directory {}", dir.display()))?;
+
+    let (temp, mut file) = create_config_temp(&path)?;
+    let written = file
+        .write_all(document.to_string().as_bytes())
+        .and_then(|()| file.sync_all());
+    drop(file);
+    let replaced = written
+        .with_context(|| format!("Failed to write config file {}", temp.display()))
+        .and_then(|()| {
+            std::fs::rename(&temp, &path)
+                .with_context(|| format!("Failed to replace config file {}", path.display()))
+        });
+    if replaced.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    replaced?;
+    // Make the rename itself durable; not every platform can sync a directory.
+    if let Ok(dir) = std::fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// A new file only the current user can read, next to the config file at
+/// `path`. An existing file is never reused.
+fn create_config_temp(path: &Path) -> Result<(PathBuf, std::fs::File)> {
+    let name = path
+        .file_name()
+        .map_or_else(|| "config.toml".into(), |name| name.to_string_lossy());
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    for attempt in 0..16 {
+        let temp = path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), attempt));
+        match options.open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", temp.display()));
+            }
+        }
+    }
+    bail!(
+        "Failed to create a temporary file next to {}",
+        path.display()
+    )
+}
+
+/// Delete the profile's keyring entries. Returns the accounts that were
+/// removed; entries that were already missing are skipped.
+pub fn clear_credentials(profile: &str) -> Result<Vec<String>> {
+    validate_profile(profile)?;
+    let mut removed = Vec::new();
+    for key in ["client_id", "client_secret", "url", "auth_mode"] {
+        let account = account_name(profile, key);
+        let entry = keyring::Entry::new(SERVICE, &account)
+            .with_context(|| format!("Failed to create keyring entry for {}", account))?;
+        match entry.delete_credential() {
+            Ok(()) => removed.push(account),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to remove {} from keyring", account));
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// The environment variables that supply the profile's credentials, if all
+/// three are set (in which case they take precedence over the keyring).
+pub fn env_credentials_in_effect(profile: &str) -> Option<[String; 3]> {
+    let names = [
+        env_var_name(profile, "CLIENT_ID"),
+        env_var_name(profile, "CLIENT_SECRET"),
+        env_var_name(profile, "URL"),
+    ];
+    names
+        .iter()
+        .all(|name| env::var_os(name).is_some())
+        .then_some(names)
+}
+
+/// How an auth mode is written in the keyring, config file and environment.
+fn auth_mode_name(mode: AuthMode) -> &'static str {
+    match mode {
+        AuthMode::OAuth => "oauth",
+        AuthMode::Basic => "basic",
+    }
+}
+
+fn parse_auth_mode(value: &str) -> Result<AuthMode> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "oauth" => Ok(AuthMode::OAuth),
+        "basic" => Ok(AuthMode::Basic),
+        other => bail!("unknown auth mode '{}' (use oauth or basic)", other),
+    }
+}
+
+fn missing_credentials_message(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        "No credentials found. Run `jamf-package-updater auth` first (add `--store file` where no keyring is available) or set JAMF_CLIENT_ID, JAMF_CLIENT_SECRET, JAMF_URL environment variables.".to_string()
+    } else {
+        format!(
+            "No credentials found for profile '{}'. Run `jamf-package-updater --profile {} auth` first or set {}, {}, {} environment variables.",
+            profile,
+            profile,
+            env_var_name(profile, "CLIENT_ID"),
+            env_var_name(profile, "CLIENT_SECRET"),
+            env_var_name(profile, "URL"),
+        )
+    }
+}
+
+/// Profile names end up in keyring account names and environment variable
+/// names, so keep them to a safe character set.
+fn validate_profile(profile: &str) -> Result<()> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Invalid profile name '{}': use letters, digits, '-' or '_'",
+            profile
+        );
+    }
+    Ok(())
+}
+
+/// Keyring account for `key` in `profile`, e.g. `prod.client_id`.
+fn account_name(profile: &str, key: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        key.to_string()
+    } else {
+        format!("{}.{}", profile, key)
+    }
+}
+
+/// Environment variable for `key` in `profile`, e.g. `JAMF_PROD_CLIENT_ID`.
+fn env_var_name(profile: &str, key: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        format!("JAMF_{}", key)
+    } else {
+        let profile = profile.to_ascii_uppercase().replace('-', "_");
+        format!("JAMF_{}_{}", profile, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_keeps_unprefixed_names() {
+        assert_eq!(account_name(DEFAULT_PROFILE, "url"), "url");
+        assert_eq!(env_var_name(DEFAULT_PROFILE, "URL"), "JAMF_URL");
+        assert_eq!(account_name("prod", "client_id"), "prod.client_id");
+        assert_eq!(
+            env_var_name("eu-prod", "CLIENT_ID"),
+            "JAMF_EU_PROD_CLIENT_ID"
+        );
+        assert!(validate_profile("prod.eu").is_err());
+    }
+
+    #[test]
+    fn config_file_round_trip_keeps_other_profiles() {
+        let dir = std::env::temp_dir().join(format!("jpu-config-test-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = "# Shared settings\n[defaults]\nretries = 5 # flaky network\n";
+        std::fs::write(&path, settings).unwrap();
+
+        store_file_credentials(
+            "default",
+            Some(&path),
+            "id-1",
+            "secret-1",
+            "https://a.test/",
+            AuthMode::OAuth,
+            false,
+        )
+        .unwrap();
+        store_file_credentials(
+            "prod",
+            Some(&path),
+            "admin",
+            "password",
+            "https://b.test",
+            AuthMode::Basic,
+            false,
+        )
+        .unwrap();
+        let creds = load_file_credentials("default", Some(&path))
+            .unwrap()
+            .unwrap();
+        assert_eq!(creds.client_id, "id-1");
+        assert_eq!(creds.url, "https://a.test");
+        assert_eq!(creds.source, CredentialSource::ConfigFile(path.clone()));
+
+        assert!(
+            clear_file_credentials("default", Some(&path))
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            load_file_credentials("default", Some(&path))
+                .unwrap()
+                .is_none()
+        );
+        let prod = load_file_credentials("prod", Some(&path)).unwrap().unwrap();
+        assert_eq!(prod.auth_mode, AuthMode::Basic);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(settings), "{}", contents);
+        // Each write renamed its temporary file into place.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn default_url_reports_an_unreadable_config() {
+        let dir = std::env::temp_dir().join(format!("jpu-default-url-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(&path, "[defaults]\nurl = \"https://a.test\"\n").unwrap();
+        assert_eq!(
+            default_url(Some(&path)).unwrap().as_deref(),
+            Some("https://a.test")
+        );
+        std::fs::write(&path, "[defaults\nurl = ").unwrap();
+        let err = default_url(Some(&path)).unwrap_err();
+        assert!(
+            err.to_string().starts_with("Failed to parse config file"),
+            "{}",
+            err
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
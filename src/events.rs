@@ -0,0 +1,52 @@
+//! How the library reports progress without printing anything.
+//!
+//! Progress lines and warnings are `tracing` events with target [`TARGET`]:
+//! info level for progress, warn level for warnings and results a caller
+//! should see. Transfers and policy scans also tell a caller-supplied
+//! [`ProgressCallback`] how far along they are. The CLI renders both the way
+//! it prints its own lines.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// `tracing` target of the library's progress and warning events.
+pub const TARGET: &str = "jamf_package_updater::status";
+
+/// Receives `(done, total)` as work moves along: bytes for uploads and
+/// downloads, policies for a policy scan. A retried upload starts again
+/// from 0.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(u64, u64) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, done: u64, total: u64) {
+        (self.0)(done, total);
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A progress line, as an info-level event.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        tracing::info!(target: $crate::events::TARGET, $($arg)*)
+    };
+}
+
+/// A warning or result a caller should see, as a warn-level event.
+macro_rules! outcome {
+    ($($arg:tt)*) => {
+        tracing::warn!(target: $crate::events::TARGET, $($arg)*)
+    };
+}
+
+pub(crate) use outcome;
+pub(crate) use status;
@@ -0,0 +1,372 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result, bail};
+
+use crate::events::{outcome, status};
+
+/// Extensions Jamf treats as installers out of the box: flat packages and
+/// metapackages, disk images, and zipped bundles.
+pub const STANDARD_EXTENSIONS: &[&str] = &["pkg", "dmg", "mpkg", "zip"];
+
+/// Bundles (directories) Jamf only takes zipped: applications and non-flat
+/// packages.
+pub const BUNDLE_EXTENSIONS: &[&str] = &["app", "pkg", "mpkg"];
+
+/// The set of file extensions accepted for upload: the standard installer
+/// formats plus anything added with `--allow-extension`.
+pub struct InstallerExtensions {
+    extra: Vec<String>,
+}
+
+impl InstallerExtensions {
+    pub fn new(allowed: &[String]) -> Self {
+        let extra = allowed
+            .iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty() && !STANDARD_EXTENSIONS.contains(&e.as_str()))
+            .collect();
+        Self { extra }
+    }
+
+    pub fn allows(&self, path: &Path) -> bool {
+        if is_bundle(path) {
+            return true;
+        }
+        let ext = extension_of(path);
+        STANDARD_EXTENSIONS.contains(&ext.as_str()) || self.extra.contains(&ext)
+    }
+
+    /// Human-readable list, e.g. ".pkg, .dmg, .mpkg or .zip".
+    pub fn describe(&self) -> String {
+        let all: Vec<String> = STANDARD_EXTENSIONS
+            .iter()
+            .map(|e| e.to_string())
+            .chain(self.extra.iter().cloned())
+            .map(|e| format!(".{}", e))
+            .collect();
+        match all.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+            None => String::new(),
+        }
+    }
+
+    /// Fail unless the path has an accepted extension.
+    pub fn validate(&self, path: &Path) -> Result<()> {
+        if !self.allows(path) {
+            bail!(
+                "File must be a {} (got .{}). Use --allow-extension to permit other types.",
+                self.describe(),
+                extension_of(path)
+            );
+        }
+        Ok(())
+    }
+
+    /// Print a warning for files that aren't standard installers.
+    pub fn warn_if_non_standard(&self, files: &[PathBuf]) {
+        for file in files {
+            if !is_standard_installer(file) {
+                outcome!(
+                    "Warning: {} is not a .pkg/.dmg/.mpkg/.zip; Jamf policies may not install it like a package.",
+                    file.display()
+                );
+            }
+        }
+    }
+}
+
+pub fn is_standard_installer(path: &Path) -> bool {
+    STANDARD_EXTENSIONS.contains(&extension_of(path).as_str())
+}
+
+/// Whether `path` is an `.app`, `.pkg` or `.mpkg` bundle directory, which is
+/// zipped before upload (see [`zip_bundle`]).
+pub fn is_bundle(path: &Path) -> bool {
+    path.is_dir() && BUNDLE_EXTENSIONS.contains(&extension_of(path).as_str())
+}
+
+/// A bundle zipped into a temporary directory, removed again on drop.
+#[derive(Debug)]
+pub struct ZippedBundle {
+    dir: PathBuf,
+    /// The archive, named after the bundle: `App.app` becomes `App.app.zip`.
+    pub path: PathBuf,
+}
+
+impl Drop for ZippedBundle {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A new directory under the system temp dir that only the current user can
+/// enter. A path that already exists is never reused, so another local user
+/// cannot plant a symlink where the tool is about to write.
+pub fn private_temp_dir(prefix: &str) -> Result<PathBuf> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    for _ in 0..16 {
+        let dir = std::env::temp_dir().join(format!(
+            "{}-{}-{}-{}",
+            prefix,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", dir.display()));
+            }
+        }
+    }
+    bail!(
+        "Failed to create a temporary directory in {}",
+        std::env::temp_dir().display()
+    )
+}
+
+/// Zip a bundle the way Jamf expects it, with the bundle itself at the top
+/// of the archive. macOS uses `ditto`, which keeps resource forks, extended
+/// attributes and symlinks inside the bundle; elsewhere `zip` is used.
+pub async fn zip_bundle(bundle: &Path) -> Result<ZippedBundle> {
+    let name = bundle
+        .file_name()
+        .context("Invalid bundle path")?
+        .to_string_lossy()
+        .to_string();
+    let dir = private_temp_dir("jamf-package-updater-zip")?;
+    let zipped = ZippedBundle {
+        path: dir.join(format!("{}.zip", name)),
+        dir,
+    };
+
+    status!("Zipping {}...", bundle.display());
+    let mut command = if cfg!(target_os = "macos") {
+        let mut ditto = tokio::process::Command::new("ditto");
+        ditto
+            .args(["-c", "-k", "--sequesterRsrc", "--keepParent"])
+            .arg(bundle)
+            .arg(&zipped.path);
+        ditto
+    } else {
+        let parent = bundle
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut zip = tokio::process::Command::new("zip");
+        // -X leaves out the uid/gid and access-time extra fields, so zipping
+        // the same bundle again gives the same archive and digest.
+        zip.args(["-q", "-r", "-y", "-X"])
+            .arg(&zipped.path)
+            .arg(&name)
+            .current_dir(parent);
+        zip
+    };
+    let tool = if cfg!(target_os = "macos") {
+        "ditto"
+    } else {
+        "zip"
+    };
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {} to zip {}", tool, bundle.display()))?;
+    if !output.status.success() {
+        bail!(
+            "{} could not zip {}: {}",
+            tool,
+            bundle.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    status!("Zipped to {}.", zipped.path.display());
+    Ok(zipped)
+}
+
+/// [`zip_bundle`] when `path` is a bundle; None for a plain file.
+pub async fn zip_if_bundle(path: &Path) -> Result<Option<ZippedBundle>> {
+    if is_bundle(path) {
+        zip_bundle(path).await.map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Flat `.pkg` files are xar archives, which start with this magic.
+const XAR_MAGIC: &[u8] = b"xar!";
+/// `.dmg` images end with a 512-byte "koly" trailer.
+const KOLY_MAGIC: &[u8] = b"koly";
+const KOLY_TRAILER_LEN: u64 = 512;
+/// Zip archives start with a local file header, or with the end-of-central-
+/// directory record when empty.
+const ZIP_MAGIC: &[u8] = b"PK";
+
+/// Check that a `.pkg`, `.mpkg`, `.dmg` or `.zip` really is one, by its magic
+/// bytes, so a renamed file or a truncated download fails before a long
+/// upload. Other extensions are not checked.
+pub fn check_installer_signature(path: &Path) -> Result<()> {
+    let ext = extension_of(path);
+    if !is_standard_installer(path) {
+        return Ok(());
+    }
+
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to read file metadata: {}", path.display()))?
+        .len();
+
+    let mut head = [0u8; 4];
+    let mut trailer = [0u8; 4];
+    let read_at = |file: &mut File, offset: u64, buf: &mut [u8]| -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)
+    };
+    if len >= head.len() as u64 {
+        read_at(&mut file, 0, &mut head)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+    if len >= KOLY_TRAILER_LEN {
+        read_at(&mut file, len - KOLY_TRAILER_LEN, &mut trailer)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+
+    if let Some(problem) = signature_problem(&ext, &head, &trailer) {
+        bail!(
+            "{} does not look like a .{} file: {}. It may be renamed or truncated; \
+             use --skip-validation to upload it anyway.",
+            path.display(),
+            ext,
+            problem
+        );
+    }
+    Ok(())
+}
+
+/// Why `head` (the first bytes) and `trailer` (the start of the last 512
+/// bytes) don't fit the extension, if they don't.
+fn signature_problem(ext: &str, head: &[u8], trailer: &[u8]) -> Option<&'static str> {
+    match ext {
+        "pkg" | "mpkg" if !head.starts_with(XAR_MAGIC) => Some("missing the xar! header"),
+        "zip" if !head.starts_with(ZIP_MAGIC) => Some("missing the PK header"),
+        "dmg" if !trailer.starts_with(KOLY_MAGIC) => Some("missing the koly trailer"),
+        _ => None,
+    }
+}
+
+/// Lowercased extension without the dot, or an empty string.
+pub fn extension_of(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Expand a path into the list of installer files to process. Directories are
+/// scanned one level deep and sorted by file name for stable output; bundles
+/// count as files, not directories to scan.
+pub fn collect_files(path: &Path, extensions: &InstallerExtensions) -> Result<Vec<PathBuf>> {
+    if !path.exists() {
+        bail!("File not found: {}", path.display());
+    }
+
+    if !path.is_dir() || is_bundle(path) {
+        extensions.validate(path)?;
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read directory: {}", path.display()))?
+    {
+        let entry_path = entry
+            .with_context(|| format!("Failed to read directory: {}", path.display()))?
+            .path();
+        if (entry_path.is_file() || is_bundle(&entry_path)) && extensions.allows(&entry_path) {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// [`collect_files`] over several inputs, keeping command-line order and
+/// dropping duplicates.
+pub fn collect_all_files(
+    paths: &[PathBuf],
+    extensions: &InstallerExtensions,
+) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let found = collect_files(path, extensions)?;
+        if found.is_empty() {
+            bail!(
+                "No {} files found in {}",
+                extensions.describe(),
+                path.display()
+            );
+        }
+        for file in found {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::compute_file_md5;
+
+    #[test]
+    fn checks_magic_bytes_against_extension() {
+        assert_eq!(signature_problem("pkg", b"xar!", b""), None);
+        assert!(signature_problem("pkg", b"PK\x03\x04", b"").is_some());
+        assert_eq!(signature_problem("dmg", b"\0\0\0\0", b"koly"), None);
+        assert!(signature_problem("dmg", b"xar!", b"\0\0\0\0").is_some());
+        assert_eq!(signature_problem("mpkg", b"xar!", b""), None);
+        assert_eq!(signature_problem("zip", b"PK\x03\x04", b""), None);
+        assert!(signature_problem("zip", b"xar!", b"").is_some());
+        assert_eq!(signature_problem("mobileconfig", b"<?xm", b""), None);
+    }
+
+    // macOS zips with ditto.
+    #[cfg(not(target_os = "macos"))]
+    #[tokio::test]
+    async fn zipping_a_bundle_twice_gives_the_same_digest() {
+        let dir = private_temp_dir("files-zip-test").unwrap();
+        let bundle = dir.join("App.app");
+        std::fs::create_dir_all(bundle.join("Contents")).unwrap();
+        let plist = bundle.join("Contents/Info.plist");
+        std::fs::write(&plist, b"<plist/>").unwrap();
+
+        let first = zip_bundle(&bundle).await.unwrap();
+        let first_md5 = compute_file_md5(&first.path).await.unwrap();
+        // Reading the bundle moves access times, which must not matter.
+        let earlier = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::open(&plist)
+            .unwrap()
+            .set_times(std::fs::FileTimes::new().set_accessed(earlier))
+            .unwrap();
+        let second = zip_bundle(&bundle).await.unwrap();
+        let second_md5 = compute_file_md5(&second.path).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first.path.file_name(), second.path.file_name());
+        assert_eq!(first_md5, second_md5);
+    }
+}
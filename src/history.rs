@@ -0,0 +1,190 @@
+//! A local record of every `update` run, read back by `history`.
+//!
+//! Each file an update touches appends one JSON line to `history.jsonl` in
+//! the user data directory (or `--history-file`): when, who, which package,
+//! the digests before and after, the affected policies and the result. Jamf's
+//! own change log says little about package payloads; this is the audit
+//! trail of who pushed what.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::api::jcds_upload::civil_from_days;
+use crate::api::packages::PackageDigestSnapshot;
+use crate::commands::update::{UpdateAction, UpdateOutcome};
+use crate::models::policy::AffectedPolicy;
+
+/// One file of one `update` run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Unix time the run finished.
+    pub timestamp: u64,
+    /// Local account that ran the tool.
+    pub user: Option<String>,
+    /// Account behind the API token.
+    pub identity: Option<String>,
+    /// Credential profile, telling instances apart.
+    pub profile: String,
+    pub file: PathBuf,
+    pub package_name: String,
+    pub package_id: Option<String>,
+    pub action: UpdateAction,
+    pub previous_digest: Option<PackageDigestSnapshot>,
+    pub new_digest: Option<PackageDigestSnapshot>,
+    pub local_md5: Option<String>,
+    /// Size of the uploaded file in bytes.
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub affected_policies: Vec<AffectedPolicy>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl HistoryEntry {
+    pub fn from_outcome(outcome: &UpdateOutcome, profile: &str) -> Self {
+        let file_size = outcome
+            .new_digest
+            .as_ref()
+            .and_then(|d| d.file_size)
+            .or_else(|| {
+                std::fs::metadata(&outcome.file)
+                    .ok()
+                    .filter(|m| m.is_file())
+                    .map(|m| m.len())
+            });
+        Self {
+            timestamp: now(),
+            user: local_user(),
+            identity: outcome.identity.clone(),
+            profile: profile.to_string(),
+            file: outcome.file.clone(),
+            package_name: outcome.package_name.clone(),
+            package_id: outcome.package_id.clone(),
+            action: outcome.action,
+            previous_digest: outcome.previous_digest.clone(),
+            new_digest: outcome.new_digest.clone(),
+            local_md5: outcome.local_md5.clone(),
+            file_size,
+            affected_policies: outcome.affected_policies.clone(),
+            error: outcome.error.clone(),
+            duration_ms: outcome.duration_ms,
+        }
+    }
+
+    /// `YYYY-MM-DD HH:MM:SSZ`, in UTC.
+    pub fn time(&self) -> String {
+        let (year, month, day) = civil_from_days((self.timestamp / 86_400) as i64);
+        let rem = self.timestamp % 86_400;
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60
+        )
+    }
+}
+
+/// `path`, or `history.jsonl` in the user data directory.
+pub fn history_path(path: Option<&Path>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path.to_path_buf()),
+        None => Ok(dirs::data_local_dir()
+            .context(
+                "Cannot determine a data directory for the update history; pass --history-file",
+            )?
+            .join("jamf-package-updater")
+            .join("history.jsonl")),
+    }
+}
+
+/// Append `entries`, one line each. Each line goes out in a single write, so
+/// concurrent runs appending to the same file don't interleave.
+pub fn append(path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for entry in entries {
+        let mut line = serde_json::to_string(entry).context("Failed to serialize history")?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Every entry in the file, oldest first; none when it doesn't exist yet.
+/// Lines that don't parse (e.g. cut short by a crash) are skipped.
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn local_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|u| !u.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_and_reads_back_entries() {
+        let dir = std::env::temp_dir().join(format!("jpu-history-test-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut outcome = UpdateOutcome::new("Chrome.pkg".as_ref(), "Chrome");
+        outcome.action = UpdateAction::Updated;
+        outcome.package_id = Some("12".to_string());
+        let mut entry = HistoryEntry::from_outcome(&outcome, "default");
+        entry.timestamp = 1_709_210_096;
+        append(&path, std::slice::from_ref(&entry)).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"truncat\n")
+            .unwrap();
+        append(&path, &[entry]).unwrap();
+
+        let entries = read(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].package_id.as_deref(), Some("12"));
+        assert_eq!(entries[0].action, UpdateAction::Updated);
+        assert_eq!(entries[0].time(), "2024-02-29 12:34:56Z");
+    }
+}
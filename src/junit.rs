@@ -0,0 +1,133 @@
+//! Minimal JUnit XML report writer so CI dashboards can show per-package
+//! outcomes.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use quick_xml::escape::escape;
+
+use crate::output::status;
+use crate::redact;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaseOutcome {
+    Passed,
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// Shown as the test name, normally the file being processed.
+    pub name: String,
+    /// Grouping key, normally the Jamf package name.
+    pub classname: String,
+    pub time: Duration,
+    pub outcome: CaseOutcome,
+    /// Free-form detail written to `<system-out>`.
+    pub output: Option<String>,
+}
+
+/// Render `cases` as a single `<testsuite>` document.
+pub fn render(suite: &str, cases: &[TestCase]) -> String {
+    let failures = cases
+        .iter()
+        .filter(|c| matches!(c.outcome, CaseOutcome::Failed(_)))
+        .count();
+    let skipped = cases
+        .iter()
+        .filter(|c| matches!(c.outcome, CaseOutcome::Skipped(_)))
+        .count();
+    let total: f64 = cases.iter().map(|c| c.time.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites>\n  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape(suite),
+        cases.len(),
+        failures,
+        skipped,
+        total
+    ));
+    for case in cases {
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape(&case.name),
+            escape(&case.classname),
+            case.time.as_secs_f64()
+        ));
+        if case.outcome == CaseOutcome::Passed && case.output.is_none() {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        match &case.outcome {
+            CaseOutcome::Passed => {}
+            CaseOutcome::Skipped(message) => xml.push_str(&format!(
+                "      <skipped message=\"{}\"/>\n",
+                escape(redact::scrub(message))
+            )),
+            CaseOutcome::Failed(message) => {
+                let message = redact::scrub(message);
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    escape(&message),
+                    escape(&message)
+                ));
+            }
+        }
+        if let Some(output) = &case.output {
+            xml.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                escape(redact::scrub(output))
+            ));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Write the report to `path`, creating parent directories as needed.
+pub fn write_report(path: &Path, suite: &str, cases: &[TestCase]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, render(suite, cases))
+        .with_context(|| format!("Failed to write JUnit report to {}", path.display()))?;
+    status!("JUnit report written to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CaseOutcome, TestCase, render};
+
+    #[test]
+    fn renders_counts_and_escapes_messages() {
+        let case = |name: &str, outcome| TestCase {
+            name: name.to_string(),
+            classname: "Pkg".to_string(),
+            time: Duration::from_millis(1500),
+            outcome,
+            output: None,
+        };
+        let xml = render(
+            "update",
+            &[
+                case("a.pkg", CaseOutcome::Passed),
+                case("b.pkg", CaseOutcome::Failed("bad <digest> & more".into())),
+                case("c.pkg", CaseOutcome::Skipped("ambiguous".into())),
+            ],
+        );
+
+        assert!(xml.contains("tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\""));
+        assert!(xml.contains("<testcase name=\"a.pkg\" classname=\"Pkg\" time=\"1.500\"/>"));
+        assert!(xml.contains("message=\"bad &lt;digest&gt; &amp; more\""));
+        assert!(xml.contains("<skipped message=\"ambiguous\"/>"));
+    }
+}
@@ -0,0 +1,46 @@
+//! Jamf Pro package updates as a library.
+//!
+//! The `jamf-package-updater` binary's commands are built on this crate;
+//! other tools can drive the same API client directly:
+//!
+//! ```no_run
+//! use jamf_package_updater::{ConnectOptions, JamfClient, UploadOptions};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = JamfClient::connect(
+//!     "https://example.jamfcloud.com",
+//!     "client-id",
+//!     "client-secret",
+//!     &ConnectOptions::default(),
+//! )
+//! .await?;
+//! if let Some(package) = client.find_package("Google Chrome").await? {
+//!     client.delete_jcds_file(&package.file_name).await?;
+//!     client
+//!         .upload_package(&package.id, "Chrome.pkg".as_ref(), &UploadOptions::default())
+//!         .await?;
+//!     client.refresh_jcds_inventory().await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Nothing is printed. Client methods report progress lines and warnings as
+//! `tracing` events with target [`STATUS_TARGET`] (info and warn level), and
+//! uploads, downloads and policy scans also report how far along they are to
+//! a [`ProgressCallback`] given in their options.
+
+pub mod api;
+pub mod checksum;
+pub mod credentials;
+pub mod files;
+pub mod models;
+pub mod redact;
+
+mod events;
+mod sealed;
+
+pub use api::client::{ConnectOptions, JamfClient, RetryPolicy, Timeouts};
+pub use api::error::JamfError;
+pub use api::packages::UploadOptions;
+pub use events::{ProgressCallback, TARGET as STATUS_TARGET};
@@ -1,32 +1,223 @@
-mod api;
+mod autopkg;
+mod backups;
 mod cli;
 mod commands;
-mod credentials;
-mod models;
+mod config;
+mod history;
+mod junit;
+mod naming;
+mod notify;
+mod output;
+mod pkginfo;
+mod progress;
+mod prompt;
 
-use clap::Parser;
-use cli::{Cli, Commands};
+// The library's mock Jamf Pro server, for the command tests.
+#[cfg(test)]
+#[path = "api/mock.rs"]
+mod mock;
+
+use std::time::Instant;
+
+use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches};
+use jamf_package_updater::{api, checksum, credentials, files, models, redact};
+use serde::Serialize;
+
+use crate::api::client::AuthRejected;
+use crate::api::error::JamfError;
+use crate::api::policies::PolicyReadForbidden;
+use crate::cli::{AuthAction, Cli, Commands};
+use crate::commands::update::{Unchanged, UpdateOutcome};
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-
-    let result = match &cli.command {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let settings = match config::load(cli.global.config.as_deref()).and_then(|settings| {
+        config::apply_defaults(&mut cli, &matches, &settings.defaults).map(|()| settings)
+    }) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let json = cli.global.json();
+    // With --print-result-record stdout carries the record documents.
+    let records_on_stdout =
+        matches!(&cli.command, Commands::Update(args) if args.print_result_record);
+    output::set_status_to_stderr(json || records_on_stdout);
+    output::set_quiet(cli.global.quiet);
+    if let Err(e) = output::init_logging(cli.global.verbose, cli.global.log_file.as_deref()) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+    tracing::info!(
+        target: output::TRANSCRIPT,
+        "jamf-package-updater {}",
+        env!("CARGO_PKG_VERSION")
+    );
+    let result = match &mut cli.command {
+        Commands::Auth {
+            action: Some(AuthAction::Test),
+            ..
+        } => commands::auth::test(&cli.global).await,
         Commands::Auth {
+            action: None,
             client_id,
             client_secret,
             url,
-        } => commands::auth::run(client_id, client_secret, url),
-        Commands::Update {
-            path,
+            auth_mode,
+            store,
+        } => {
+            commands::auth::run(
+                client_id.as_deref().unwrap_or_default(),
+                client_secret.as_deref().unwrap_or_default(),
+                url.as_deref(),
+                *auth_mode,
+                *store,
+                &cli.global,
+            )
+            .await
+        }
+        Commands::Logout => commands::logout::run(&cli.global),
+        Commands::Whoami => commands::whoami::run(&cli.global).await,
+        Commands::Update(args) => {
+            if let Err(e) = autopkg::apply(args) {
+                Err(e)
+            } else if args.compare_only {
+                commands::compare::run(args, &cli.global).await
+            } else {
+                let started = Instant::now();
+                let result = commands::update::run(args, &cli.global).await;
+                let elapsed = started.elapsed();
+                // The result comes first; a slow webhook must not hold it up.
+                let printed = match &result {
+                    Ok(outcomes) => print_update_outcomes(outcomes, args.single_file(), json)
+                        .and_then(|()| print_result_records(outcomes)),
+                    Err(_) => Ok(()),
+                };
+                let options = cli.global.connect_options();
+                notify::after_update(&settings.notify, args, &options, &result, elapsed).await;
+                printed.and(result).and_then(|outcomes| {
+                    commands::update::ensure_succeeded(&outcomes, args.fail_on_unchanged)
+                })
+            }
+        }
+        Commands::Delete {
+            target,
+            yes,
+            force,
+            keep_file,
+        } => commands::delete::run(target.target(), *yes, *force, *keep_file, &cli.global).await,
+        Commands::Info {
+            target,
+            file,
+            no_references,
+            json: as_json,
+        } => {
+            commands::info::run(
+                target.target(),
+                file.as_deref(),
+                !*no_references,
+                json || *as_json,
+                &cli.global,
+            )
+            .await
+        }
+        Commands::List {
+            filter,
+            json: as_json,
+        } => commands::list::run(filter.as_deref(), json || *as_json, &cli.global).await,
+        Commands::Policies { json: as_json } => {
+            commands::policies::run(json || *as_json, &cli.global).await
+        }
+        Commands::Reconcile { target, fix } => {
+            commands::reconcile::run(target.target(), *fix, &cli.global).await
+        }
+        Commands::Rollback {
+            target,
+            backup_dir,
+            yes,
+        } => {
+            commands::rollback::run(target.target(), backup_dir.as_deref(), *yes, &cli.global).await
+        }
+        Commands::Download { target, out, force } => {
+            commands::download::run(target.target(), out.as_deref(), *force, &cli.global).await
+        }
+        Commands::Verify(args) => commands::verify::run(args, &cli.global).await,
+        Commands::History {
             name,
-            priority,
-            digest_wait_seconds,
-        } => commands::update::run(path, name.as_deref(), *priority, *digest_wait_seconds).await,
+            limit,
+            json: as_json,
+        } => commands::history::run(name.as_deref(), *limit, json || *as_json, &cli.global),
     };
 
     if let Err(e) = result {
-        eprintln!("Error: {:#}", e);
-        std::process::exit(1);
+        let message = redact::scrub(&format!("{:#}", e));
+        let code = exit_code(&e);
+        if json && !output::json_printed() {
+            let _ = output::print_json(
+                &ErrorReport {
+                    error: &message,
+                    exit_code: code,
+                },
+                "error",
+            );
+        }
+        tracing::info!(target: output::TRANSCRIPT, "Error: {}", message);
+        eprintln!("Error: {}", message);
+        std::process::exit(code);
     }
 }
+
+/// The JSON document for a command that failed before printing its result.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorReport<'a> {
+    error: &'a str,
+    exit_code: i32,
+}
+
+/// The exit code for a failed run, from the first Jamf API failure in the
+/// error chain (see [`JamfError::exit_code`]); 1 for anything else. Clap
+/// exits with 2 for invalid arguments.
+fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<JamfError>() {
+                Some(e.exit_code())
+            } else if cause.is::<Unchanged>() {
+                Some(Unchanged::EXIT_CODE)
+            } else if cause.is::<AuthRejected>() || cause.is::<PolicyReadForbidden>() {
+                Some(3)
+            } else if cause.is::<reqwest::Error>() {
+                Some(8)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1)
+}
+
+/// With `--output json`, print the update result: one object for a single
+/// file, an array when a directory or several paths were given.
+fn print_update_outcomes(outcomes: &[UpdateOutcome], single_file: bool, json: bool) -> Result<()> {
+    if !json {
+        return Ok(());
+    }
+    match outcomes {
+        [outcome] if single_file => output::print_json(outcome, "update result"),
+        _ => output::print_json(outcomes, "update result"),
+    }
+}
+
+/// With `--print-result-record`, print each package record Jamf reported
+/// after the run as its own JSON document, separate from the update result.
+fn print_result_records(outcomes: &[UpdateOutcome]) -> Result<()> {
+    outcomes
+        .iter()
+        .filter_map(|outcome| outcome.result_record.as_ref())
+        .try_for_each(|record| output::print_json(record, "package record"))
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// A Jamf Pro category as returned by `GET /api/v1/categories`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategorySearchResponse {
+    pub total_count: i64,
+    pub results: Vec<Category>,
+}
+
+/// Body of `POST /api/v1/categories`.
+#[derive(Debug, Serialize)]
+pub struct CategoryCreateRequest<'a> {
+    pub name: &'a str,
+    pub priority: i32,
+}
@@ -0,0 +1,36 @@
+use serde::Deserialize;
+
+/// A file stored in the Jamf Cloud Distribution Service, as listed by
+/// `GET /api/v1/jcds/files`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct JcdsFile {
+    pub file_name: String,
+    pub length: Option<u64>,
+    pub md5: Option<String>,
+    pub sha3: Option<String>,
+    pub region: Option<String>,
+}
+
+/// Temporary S3 credentials for uploading straight to JCDS storage, as
+/// returned by `POST /api/v1/jcds/files`. The object key is `path` followed by
+/// the file name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JcdsUploadCredentials {
+    #[serde(rename = "accessKeyID")]
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub region: String,
+    pub bucket_name: String,
+    pub path: String,
+}
+
+/// A time-limited download link for a JCDS file, as returned by
+/// `GET /api/v1/jcds/files/{fileName}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JcdsDownloadLink {
+    pub uri: String,
+}
@@ -1,2 +1,6 @@
+pub mod category;
+pub mod jcds;
 pub mod package;
+pub mod patch;
 pub mod policy;
+pub mod prestage;
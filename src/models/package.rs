@@ -17,7 +17,27 @@ pub struct PackageSearchResponse {
     pub results: Vec<Package>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// One page of `GET /api/v1/packages/{id}/history`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct PackageHistoryResponse {
+    pub total_count: i64,
+    pub results: Vec<PackageHistoryEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct PackageHistoryEntry {
+    pub id: i64,
+    pub username: Option<String>,
+    pub date: Option<String>,
+    pub note: Option<String>,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct Package {
@@ -34,6 +54,12 @@ pub struct Package {
     pub suppress_from_dock: bool,
     pub suppress_eula: bool,
     pub suppress_registration: bool,
+    /// Free-text "Info" field shown in Jamf Pro.
+    #[serde(default)]
+    pub info: Option<String>,
+    /// Free-text "Notes" field shown in Jamf Pro.
+    #[serde(default)]
+    pub notes: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -51,14 +77,56 @@ pub struct PackageCreateRequest {
     pub suppress_from_dock: bool,
     pub suppress_eula: bool,
     pub suppress_registration: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Package flags set explicitly on the command line. `None` leaves the
+/// default (new records) or the existing value (updates) in place.
+#[derive(Debug, Clone, Default)]
+pub struct PackageFlagOverrides {
+    pub fill_user_template: Option<bool>,
+    pub fill_existing_users: Option<bool>,
+    pub reboot_required: Option<bool>,
+    pub os_install: Option<bool>,
+    pub suppress_updates: Option<bool>,
+    pub suppress_from_dock: Option<bool>,
+    pub suppress_eula: Option<bool>,
+    pub suppress_registration: Option<bool>,
+}
+
+impl PackageFlagOverrides {
+    pub fn is_empty(&self) -> bool {
+        [
+            self.fill_user_template,
+            self.fill_existing_users,
+            self.reboot_required,
+            self.os_install,
+            self.suppress_updates,
+            self.suppress_from_dock,
+            self.suppress_eula,
+            self.suppress_registration,
+        ]
+        .iter()
+        .all(Option::is_none)
+    }
 }
 
 impl PackageCreateRequest {
-    pub fn new_default(package_name: &str, file_name: &str, priority: Option<i32>) -> Self {
+    /// A new record with default flags. Without a category it lands in
+    /// Unknown (`-1`).
+    pub fn new_default(
+        package_name: &str,
+        file_name: &str,
+        priority: Option<i32>,
+        category_id: Option<&str>,
+    ) -> Self {
         Self {
             package_name: package_name.to_string(),
             file_name: file_name.to_string(),
-            category_id: "-1".to_string(),
+            category_id: category_id.unwrap_or("-1").to_string(),
             priority: priority.unwrap_or(3),
             fill_user_template: false,
             fill_existing_users: false,
@@ -68,14 +136,126 @@ impl PackageCreateRequest {
             suppress_from_dock: false,
             suppress_eula: false,
             suppress_registration: false,
+            info: None,
+            notes: None,
+        }
+    }
+
+    /// Reset every boolean flag to the `new_default` value, leaving name,
+    /// file name, category and priority alone.
+    pub fn with_default_flags(self) -> Self {
+        let defaults = Self::new_default(&self.package_name, &self.file_name, None, None);
+        Self {
+            fill_user_template: defaults.fill_user_template,
+            fill_existing_users: defaults.fill_existing_users,
+            reboot_required: defaults.reboot_required,
+            os_install: defaults.os_install,
+            suppress_updates: defaults.suppress_updates,
+            suppress_from_dock: defaults.suppress_from_dock,
+            suppress_eula: defaults.suppress_eula,
+            suppress_registration: defaults.suppress_registration,
+            ..self
         }
     }
 
-    pub fn from_old(old: &Package, new_file_name: &str, priority: Option<i32>) -> Self {
+    /// Apply the flags that were set explicitly; unset ones keep their value.
+    pub fn with_flag_overrides(self, overrides: &PackageFlagOverrides) -> Self {
+        Self {
+            fill_user_template: overrides
+                .fill_user_template
+                .unwrap_or(self.fill_user_template),
+            fill_existing_users: overrides
+                .fill_existing_users
+                .unwrap_or(self.fill_existing_users),
+            reboot_required: overrides.reboot_required.unwrap_or(self.reboot_required),
+            os_install: overrides.os_install.unwrap_or(self.os_install),
+            suppress_updates: overrides.suppress_updates.unwrap_or(self.suppress_updates),
+            suppress_from_dock: overrides
+                .suppress_from_dock
+                .unwrap_or(self.suppress_from_dock),
+            suppress_eula: overrides.suppress_eula.unwrap_or(self.suppress_eula),
+            suppress_registration: overrides
+                .suppress_registration
+                .unwrap_or(self.suppress_registration),
+            ..self
+        }
+    }
+
+    /// Each field this request would change on `old`, as `field: old -> new`.
+    pub fn changes_from(&self, old: &Package) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut compare = |field: &str, before: String, after: String| {
+            if before != after {
+                changes.push(format!("{}: {} -> {}", field, before, after));
+            }
+        };
+        compare(
+            "packageName",
+            old.package_name.clone(),
+            self.package_name.clone(),
+        );
+        compare("fileName", old.file_name.clone(), self.file_name.clone());
+        compare(
+            "categoryId",
+            old.category_id.clone(),
+            self.category_id.clone(),
+        );
+        compare(
+            "priority",
+            old.priority.to_string(),
+            self.priority.to_string(),
+        );
+        let flags = [
+            (
+                "fillUserTemplate",
+                old.fill_user_template,
+                self.fill_user_template,
+            ),
+            (
+                "fillExistingUsers",
+                old.fill_existing_users,
+                self.fill_existing_users,
+            ),
+            ("rebootRequired", old.reboot_required, self.reboot_required),
+            ("osInstall", old.os_install, self.os_install),
+            (
+                "suppressUpdates",
+                old.suppress_updates,
+                self.suppress_updates,
+            ),
+            (
+                "suppressFromDock",
+                old.suppress_from_dock,
+                self.suppress_from_dock,
+            ),
+            ("suppressEula", old.suppress_eula, self.suppress_eula),
+            (
+                "suppressRegistration",
+                old.suppress_registration,
+                self.suppress_registration,
+            ),
+        ];
+        for (field, before, after) in flags {
+            compare(field, before.to_string(), after.to_string());
+        }
+        let text = |value: &Option<String>| format!("{:?}", value.as_deref().unwrap_or_default());
+        compare("info", text(&old.info), text(&self.info));
+        compare("notes", text(&old.notes), text(&self.notes));
+        changes
+    }
+
+    pub fn from_old(
+        old: &Package,
+        new_file_name: &str,
+        priority: Option<i32>,
+        category_id: Option<&str>,
+    ) -> Self {
         Self {
             package_name: old.package_name.clone(),
             file_name: new_file_name.to_string(),
-            category_id: old.category_id.clone(),
+            category_id: category_id
+                .map(str::to_string)
+                .unwrap_or_else(|| old.category_id.clone()),
             priority: priority.unwrap_or(old.priority),
             fill_user_template: old.fill_user_template,
             fill_existing_users: old.fill_existing_users,
@@ -85,6 +265,121 @@ impl PackageCreateRequest {
             suppress_from_dock: old.suppress_from_dock,
             suppress_eula: old.suppress_eula,
             suppress_registration: old.suppress_registration,
+            info: old.info.clone(),
+            notes: old.notes.clone(),
         }
     }
 }
+
+/// Prefix of the line `--record-version` keeps in a package's info or notes.
+const VERSION_LINE_PREFIX: &str = "Version: ";
+
+/// `text` with its `Version: ...` line replaced by `line`, or `line` added
+/// first when there is none. Everything else in the field is kept.
+pub fn with_version_line(text: Option<&str>, line: &str) -> String {
+    let mut lines: Vec<&str> = text.unwrap_or_default().lines().collect();
+    match lines
+        .iter()
+        .position(|l| l.starts_with(VERSION_LINE_PREFIX))
+    {
+        Some(i) => lines[i] = line,
+        None => lines.insert(0, line),
+    }
+    lines.join("\n")
+}
+
+/// The line `--record-version` writes for an installer `version` uploaded as
+/// `file_name`.
+pub fn version_line(version: &str, file_name: &str) -> String {
+    format!("{}{} ({})", VERSION_LINE_PREFIX, version, file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_from_lists_only_differing_fields() {
+        let old = Package {
+            id: "7".to_string(),
+            package_name: "App".to_string(),
+            file_name: "App-1.0.pkg".to_string(),
+            category_id: "-1".to_string(),
+            priority: 10,
+            fill_user_template: false,
+            fill_existing_users: false,
+            reboot_required: false,
+            os_install: false,
+            suppress_updates: false,
+            suppress_from_dock: false,
+            suppress_eula: false,
+            suppress_registration: false,
+            info: None,
+            notes: Some("Vendor build".to_string()),
+        };
+        assert!(
+            PackageCreateRequest::from_old(&old, "App-1.0.pkg", None, None)
+                .changes_from(&old)
+                .is_empty()
+        );
+
+        let mut req = PackageCreateRequest::from_old(&old, "App-2.0.pkg", Some(5), None);
+        req.reboot_required = true;
+        req.notes = Some(with_version_line(req.notes.as_deref(), "Version: 2.0"));
+        assert_eq!(
+            req.changes_from(&old),
+            [
+                "fileName: App-1.0.pkg -> App-2.0.pkg",
+                "priority: 10 -> 5",
+                "rebootRequired: false -> true",
+                r#"notes: "Vendor build" -> "Version: 2.0\nVendor build""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn category_defaults_to_unknown_or_the_old_record() {
+        let created = PackageCreateRequest::new_default("App", "App.pkg", None, None);
+        assert_eq!(created.category_id, "-1");
+        let created = PackageCreateRequest::new_default("App", "App.pkg", None, Some("4"));
+        assert_eq!(created.category_id, "4");
+
+        let old = Package {
+            id: "1".to_string(),
+            package_name: "App".to_string(),
+            file_name: "App.pkg".to_string(),
+            category_id: "7".to_string(),
+            priority: 3,
+            fill_user_template: false,
+            fill_existing_users: false,
+            reboot_required: false,
+            os_install: false,
+            suppress_updates: false,
+            suppress_from_dock: false,
+            suppress_eula: false,
+            suppress_registration: false,
+            info: None,
+            notes: None,
+        };
+        let kept = PackageCreateRequest::from_old(&old, "App-2.pkg", None, None);
+        assert_eq!(kept.category_id, "7");
+        let moved = PackageCreateRequest::from_old(&old, "App-2.pkg", None, Some("4"));
+        assert_eq!(
+            moved.changes_from(&old),
+            ["fileName: App.pkg -> App-2.pkg", "categoryId: 7 -> 4"]
+        );
+    }
+
+    #[test]
+    fn version_line_replaces_only_the_previous_version() {
+        let line = version_line("2.3.0", "App-2.3.0.pkg");
+        assert_eq!(
+            with_version_line(None, &line),
+            "Version: 2.3.0 (App-2.3.0.pkg)"
+        );
+        assert_eq!(
+            with_version_line(Some("Built by CI\nVersion: 2.2.0 (App-2.2.0.pkg)"), &line),
+            "Built by CI\nVersion: 2.3.0 (App-2.3.0.pkg)"
+        );
+    }
+}
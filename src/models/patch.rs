@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// A Patch Management software title configuration, as listed by
+/// `GET /api/v2/patch-software-title-configurations`. Only the fields needed
+/// to find package references are read.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchTitleConfiguration {
+    pub id: String,
+    pub display_name: String,
+    /// The title's version-to-package definitions.
+    #[serde(default)]
+    pub packages: Vec<PatchTitlePackage>,
+}
+
+/// One version of a patch title and the package that installs it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchTitlePackage {
+    pub package_id: String,
+    pub version: String,
+}
+
+/// A patch policy as listed by `GET /api/v2/patch-policies`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchPolicySummary {
+    pub id: String,
+    pub policy_name: String,
+    pub policy_target_version: Option<String>,
+    pub policy_software_title_configuration_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchPolicySearchResponse {
+    pub total_count: i64,
+    pub results: Vec<PatchPolicySummary>,
+}
+
+/// A patch title version that installs the package.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchTitleReference {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+}
+
+/// A patch policy deploying a title version that installs the package.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchPolicyReference {
+    pub id: String,
+    pub name: String,
+    pub title_id: String,
+    pub version: String,
+}
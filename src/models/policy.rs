@@ -1,18 +1,18 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
 pub struct PolicyListResponse {
     pub policies: Option<Vec<PolicySummary>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct PolicySummary {
     pub id: i64,
     pub name: String,
 }
 
 /// A policy that references the package we're updating.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AffectedPolicy {
     pub id: i64,
     pub name: String,
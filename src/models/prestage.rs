@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// A computer prestage enrollment, as listed by
+/// `GET /api/v3/computer-prestages`. Only the fields needed to find package
+/// references are read.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputerPrestage {
+    pub id: String,
+    pub display_name: String,
+    /// Packages installed during enrollment.
+    #[serde(default)]
+    pub custom_package_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputerPrestageSearchResponse {
+    pub total_count: i64,
+    pub results: Vec<ComputerPrestage>,
+}
+
+/// A prestage enrollment that installs the package.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrestageReference {
+    pub id: String,
+    pub name: String,
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+
+/// Default `--name-pattern`: splits `MyApp-1.2.3` / `MyApp_v1.2.3` into
+/// `app` and `version`.
+pub const DEFAULT_NAME_PATTERN: &str = r"^(?P<app>.+?)[-_ ]v?(?P<version>\d[\w.]*)$";
+
+/// Maps a file stem to a stable package name by matching it against a regex
+/// with named capture groups and substituting those groups into a template.
+///
+/// Template placeholders are written `{group}`; `{{` and `}}` produce literal
+/// braces.
+pub struct NameTemplate {
+    pattern: Regex,
+    template: String,
+}
+
+impl NameTemplate {
+    pub fn new(template: &str, pattern: Option<&str>) -> Result<Self> {
+        let pattern_src = pattern.unwrap_or(DEFAULT_NAME_PATTERN);
+        let pattern = Regex::new(pattern_src)
+            .with_context(|| format!("Invalid --name-pattern: {}", pattern_src))?;
+
+        let groups: Vec<&str> = pattern.capture_names().flatten().collect();
+        for placeholder in placeholders(template)? {
+            if !groups.contains(&placeholder.as_str()) {
+                bail!(
+                    "--name-template references {{{}}}, but --name-pattern has no group named '{}' (available: {})",
+                    placeholder,
+                    placeholder,
+                    if groups.is_empty() {
+                        "none".to_string()
+                    } else {
+                        groups.join(", ")
+                    }
+                );
+            }
+        }
+
+        Ok(Self {
+            pattern,
+            template: template.to_string(),
+        })
+    }
+
+    /// Render the package name for a file stem.
+    pub fn render(&self, file_stem: &str) -> Result<String> {
+        let caps = self.pattern.captures(file_stem).with_context(|| {
+            format!(
+                "File name '{}' does not match --name-pattern {}",
+                file_stem,
+                self.pattern.as_str()
+            )
+        })?;
+
+        let mut out = String::new();
+        let mut chars = self.template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    out.push_str(caps.name(&name).map(|m| m.as_str()).unwrap_or_default());
+                }
+                _ => out.push(c),
+            }
+        }
+
+        let rendered = out.trim().to_string();
+        if rendered.is_empty() {
+            bail!("--name-template produced an empty name for '{}'", file_stem);
+        }
+        Ok(rendered)
+    }
+}
+
+/// Resolve the Jamf package name for a file: an explicit `--name` wins, then a
+/// name template, then the file stem.
+pub fn resolve_package_name(
+    path: &Path,
+    name: Option<&str>,
+    template: Option<&NameTemplate>,
+) -> Result<String> {
+    if let Some(n) = name {
+        return Ok(n.to_string());
+    }
+
+    let stem = path
+        .file_stem()
+        .context("Cannot determine package name from file path")?
+        .to_string_lossy()
+        .to_string();
+
+    match template {
+        Some(t) => t.render(&stem),
+        None => Ok(stem),
+    }
+}
+
+fn placeholders(template: &str) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed || name.is_empty() {
+                    bail!("Invalid --name-template: {}", template);
+                }
+                found.push(name);
+            }
+            '}' => bail!("Invalid --name-template: {}", template),
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NameTemplate;
+
+    #[test]
+    fn renders_app_from_default_pattern() {
+        let t = NameTemplate::new("{app}", None).unwrap();
+        assert_eq!(t.render("MyApp-1.2.3").unwrap(), "MyApp");
+        assert_eq!(t.render("My App_v2.0b1").unwrap(), "My App");
+    }
+
+    #[test]
+    fn custom_pattern_and_literal_braces() {
+        let t = NameTemplate::new(
+            "{{{vendor}}} {app}",
+            Some(r"^(?P<vendor>\w+)\.(?P<app>\w+)-\d+$"),
+        )
+        .unwrap();
+        assert_eq!(t.render("acme.tool-7").unwrap(), "{acme} tool");
+    }
+
+    #[test]
+    fn rejects_unknown_group_and_bad_regex() {
+        assert!(NameTemplate::new("{product}", None).is_err());
+        assert!(NameTemplate::new("{app}", Some("(")).is_err());
+        assert!(NameTemplate::new("{app", None).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Notifications after `update`: a Slack message and/or a JSON document
+//! posted to a generic webhook, configured under `[notify]` in the config
+//! file (or `JAMF_NOTIFY_SLACK_WEBHOOK` / `JAMF_NOTIFY_GENERIC_WEBHOOK`).
+//!
+//! A notification that cannot be delivered is a warning; it never changes
+//! the outcome of the run.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::api::client::{ConnectOptions, JamfClient};
+use crate::api::packages::PackageDigestSnapshot;
+use crate::cli::UpdateArgs;
+use crate::commands::update::{UpdateAction, UpdateOutcome};
+use crate::config::Notify;
+use crate::output::{outcome, status};
+use crate::redact;
+
+const SLACK_ENV: &str = "JAMF_NOTIFY_SLACK_WEBHOOK";
+const GENERIC_ENV: &str = "JAMF_NOTIFY_GENERIC_WEBHOOK";
+/// Webhook endpoints answer quickly; don't hold up the exit for a slow one.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What the generic webhook receives for one `update` run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunReport<'a> {
+    event: &'static str,
+    version: &'static str,
+    success: bool,
+    duration_ms: u64,
+    /// Account behind the API token, when the run got as far as logging in.
+    identity: Option<&'a str>,
+    /// The paths given on the command line.
+    paths: &'a [PathBuf],
+    /// One entry per file; empty when the run failed before any file.
+    packages: &'a [UpdateOutcome],
+    /// Why the whole run failed; per-file errors are in `packages`.
+    error: Option<String>,
+}
+
+/// Post the result of an `update` run to every configured webhook, through
+/// the same proxy and trusted certificates as the Jamf connection (`options`).
+/// Dry runs are not reported.
+pub async fn after_update(
+    settings: &Notify,
+    args: &UpdateArgs,
+    options: &ConnectOptions,
+    result: &Result<Vec<UpdateOutcome>>,
+    elapsed: Duration,
+) {
+    let slack = webhook(SLACK_ENV, settings.slack_webhook.as_deref());
+    let generic = webhook(GENERIC_ENV, settings.generic_webhook.as_deref());
+    if (slack.is_none() && generic.is_none()) || args.dry_run {
+        return;
+    }
+
+    let packages: &[UpdateOutcome] = result.as_deref().unwrap_or_default();
+    let report = RunReport {
+        event: "update",
+        version: env!("CARGO_PKG_VERSION"),
+        success: result.is_ok() && packages.iter().all(|o| o.action != UpdateAction::Failed),
+        duration_ms: elapsed.as_millis() as u64,
+        identity: packages.iter().find_map(|o| o.identity.as_deref()),
+        paths: &args.paths,
+        packages,
+        error: result
+            .as_ref()
+            .err()
+            .map(|e| redact::scrub(&format!("{:#}", e))),
+    };
+
+    let http = match JamfClient::build_http(options) {
+        Ok(http) => http,
+        Err(e) => {
+            outcome!("Warning: notifications not sent: {:#}", e);
+            return;
+        }
+    };
+    if let Some(url) = slack {
+        let message = serde_json::json!({ "text": slack_text(&report) });
+        warn_on_failure("Slack", post(&http, &url, &message).await);
+    }
+    if let Some(url) = generic {
+        warn_on_failure("webhook", post(&http, &url, &report).await);
+    }
+}
+
+/// The environment variable, else the config value; blank disables it.
+fn webhook(env: &str, configured: Option<&str>) -> Option<String> {
+    std::env::var(env)
+        .ok()
+        .or_else(|| configured.map(str::to_string))
+        .filter(|url| !url.trim().is_empty())
+}
+
+async fn post<T: Serialize + ?Sized>(http: &reqwest::Client, url: &str, body: &T) -> Result<()> {
+    let resp = http
+        .post(url)
+        .timeout(TIMEOUT)
+        .json(body)
+        .send()
+        .await
+        // The webhook URL is a secret; keep it out of the error.
+        .map_err(reqwest::Error::without_url)
+        .context("Failed to reach the endpoint")?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        bail!("HTTP {}: {}", status, body.trim());
+    }
+    Ok(())
+}
+
+fn warn_on_failure(target: &str, result: Result<()>) {
+    match result {
+        Ok(()) => status!("Sent {} notification.", target),
+        Err(e) => outcome!(
+            "Warning: {} notification failed: {}",
+            target,
+            redact::scrub(&format!("{:#}", e))
+        ),
+    }
+}
+
+/// A Slack `mrkdwn` summary: one line for the run, then one per package.
+fn slack_text(report: &RunReport<'_>) -> String {
+    let count = report.packages.len();
+    let mut text = format!(
+        "{} *jamf-package-updater update {}*: {} {} in {:.1}s",
+        if report.success {
+            ":white_check_mark:"
+        } else {
+            ":x:"
+        },
+        if report.success {
+            "succeeded"
+        } else {
+            "failed"
+        },
+        count,
+        if count == 1 { "package" } else { "packages" },
+        report.duration_ms as f64 / 1000.0
+    );
+    if let Some(identity) = report.identity {
+        text.push_str(&format!(" (by {})", escape(identity)));
+    }
+    if let Some(error) = &report.error {
+        text.push_str(&format!("\nError: {}", escape(error)));
+    }
+
+    for package in report.packages {
+        let action = match package.action {
+            UpdateAction::Created => "created",
+            UpdateAction::Updated => "updated",
+            UpdateAction::Skipped => "unchanged",
+            UpdateAction::Failed => "FAILED",
+        };
+        text.push_str(&format!("\n• *{}*", escape(&package.package_name)));
+        if let Some(id) = &package.package_id {
+            text.push_str(&format!(" (ID {})", id));
+        }
+        text.push_str(&format!(": {}", action));
+        let old = package.previous_digest.as_ref().and_then(short_digest);
+        let new = package.new_digest.as_ref().and_then(short_digest);
+        match (old, new) {
+            (Some(old), Some(new)) if old != new => {
+                text.push_str(&format!(", `{}` → `{}`", old, new))
+            }
+            (None, Some(new)) => text.push_str(&format!(", `{}`", new)),
+            (Some(old), _) => text.push_str(&format!(", `{}`", old)),
+            (None, None) => {}
+        }
+        if !package.affected_policies.is_empty() {
+            let names: Vec<String> = package
+                .affected_policies
+                .iter()
+                .map(|p| escape(&p.name))
+                .collect();
+            text.push_str(&format!("\n    Policies: {}", names.join(", ")));
+        }
+        if let Some(error) = &package.error {
+            text.push_str(&format!("\n    Error: {}", escape(error)));
+        }
+    }
+    text
+}
+
+/// The strongest digest Jamf reported, shortened for display.
+fn short_digest(snapshot: &PackageDigestSnapshot) -> Option<String> {
+    let (kind, value) = match (&snapshot.hash_type, &snapshot.hash_value) {
+        (Some(kind), Some(value)) if !value.is_empty() => (kind.as_str(), value.as_str()),
+        _ => (
+            "MD5",
+            snapshot.md5_hash.as_deref().filter(|v| !v.is_empty())?,
+        ),
+    };
+    let value = value.get(..12).unwrap_or(value);
+    Some(format!("{} {}", kind, value))
+}
+
+/// Slack treats `&`, `<` and `>` as markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::policy::AffectedPolicy;
+
+    #[test]
+    fn slack_text_summarises_each_package() {
+        let mut outcome = UpdateOutcome::new("Chrome.pkg".as_ref(), "Chrome <beta>");
+        outcome.package_id = Some("12".to_string());
+        outcome.action = UpdateAction::Updated;
+        outcome.previous_digest = Some(PackageDigestSnapshot {
+            md5_hash: Some("0123456789abcdef".to_string()),
+            ..Default::default()
+        });
+        outcome.new_digest = Some(PackageDigestSnapshot {
+            md5_hash: Some("fedcba9876543210".to_string()),
+            ..Default::default()
+        });
+        outcome.affected_policies = vec![AffectedPolicy {
+            id: 7,
+            name: "Install Chrome".to_string(),
+        }];
+        let packages = [outcome];
+        let report = RunReport {
+            event: "update",
+            version: "0",
+            success: true,
+            duration_ms: 1500,
+            identity: Some("ci-client"),
+            paths: &[],
+            packages: &packages,
+            error: None,
+        };
+
+        assert_eq!(
+            slack_text(&report),
+            ":white_check_mark: *jamf-package-updater update succeeded*: 1 package in 1.5s (by ci-client)\n\
+             • *Chrome &lt;beta&gt;* (ID 12): updated, `MD5 0123456789ab` → `MD5 fedcba987654`\n    \
+             Policies: Install Chrome"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Where progress and status lines go.
+//!
+//! Normally they are the tool's stdout. When a command renders a
+//! machine-readable document (`--output json`), status lines move to stderr
+//! so stdout carries nothing but that document. `--quiet` drops progress
+//! lines but keeps final results. With `--log-file`, every line (quiet or
+//! not) is also written to the log, between the `tracing` events.
+//!
+//! The library prints nothing itself; its progress lines and warnings arrive
+//! as `tracing` events (see [`jamf_package_updater::STATUS_TARGET`]) and are
+//! printed here like the tool's own lines.
+//!
+//! Files a batch processes in parallel run as labeled workers: their lines
+//! are prefixed with the file they belong to, and spinners, progress bars
+//! and prompts are left out so workers don't draw over each other.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use jamf_package_updater::STATUS_TARGET;
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{Directive, filter_fn};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{self, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::redact;
+
+/// `tracing` target of the status and result lines, so they reach the log
+/// file without being printed a second time on stderr.
+pub const TRANSCRIPT: &str = "jamf_package_updater::transcript";
+
+tokio::task_local! {
+    static WORKER: String;
+}
+
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static JSON_PRINTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_status_to_stderr(enabled: bool) {
+    STATUS_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Run `work` as the batch worker `label`; see the module docs.
+pub(crate) async fn as_worker<F: Future>(label: String, work: F) -> F::Output {
+    WORKER.scope(label, work).await
+}
+
+/// The label of the batch worker being polled, if any.
+pub(crate) fn worker_label() -> Option<String> {
+    WORKER.try_with(Clone::clone).ok()
+}
+
+/// Whether live progress (spinners, bars, `\r` counters) may be drawn: not
+/// with `--quiet`, and not inside a batch worker.
+pub(crate) fn live_progress() -> bool {
+    !quiet() && worker_label().is_none()
+}
+
+/// Print `value` to stdout as a pretty JSON document. `what` names it in the
+/// error if serialization fails.
+pub fn print_json<T: Serialize + ?Sized>(value: &T, what: &str) -> Result<()> {
+    let rendered = serde_json::to_string_pretty(value)
+        .with_context(|| format!("Failed to serialize {}", what))?;
+    println!("{}", rendered);
+    JSON_PRINTED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Whether a command already printed its JSON document, so a failure after it
+/// must not print a second one.
+pub fn json_printed() -> bool {
+    JSON_PRINTED.load(Ordering::Relaxed)
+}
+
+/// Send `tracing` events to stderr: warnings by default, debug with `-v`,
+/// trace with `-vv`. `RUST_LOG` takes precedence when set.
+///
+/// With `log_file`, the same events (at least at debug level, regardless of
+/// `RUST_LOG`) and every status line are appended to that file, with
+/// timestamps, for attaching to a support case. Both outputs are scrubbed of
+/// secrets, since request logs carry tokens and presigned URLs.
+pub fn init_logging(verbose: u8, log_file: Option<&Path>) -> Result<()> {
+    let default = match verbose {
+        0 => "warn",
+        1 => "warn,jamf_package_updater=debug",
+        _ => "info,jamf_package_updater=trace,reqwest=debug",
+    };
+    let hide = |target: &str| -> Result<Directive> {
+        format!("{}=off", target)
+            .parse()
+            .context("Invalid log filter")
+    };
+    let stderr_filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default))
+        .add_directive(hide(TRANSCRIPT)?)
+        .add_directive(hide(STATUS_TARGET)?);
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(Scrubbed(std::io::stderr))
+        .with_target(false)
+        .with_filter(stderr_filter);
+
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            let level = match verbose {
+                0 | 1 => "info,jamf_package_updater=debug",
+                _ => "info,jamf_package_updater=trace,reqwest=debug",
+            };
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Scrubbed(Mutex::new(file)))
+                    .with_ansi(false)
+                    .with_target(false)
+                    .with_filter(EnvFilter::new(level)),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(LibraryLines.with_filter(filter_fn(|meta| meta.target() == STATUS_TARGET)))
+        .with(stderr)
+        .with(file)
+        .try_init()
+        .context("Failed to set up logging")
+}
+
+/// A log destination whose events pass through [`redact::scrub`].
+struct Scrubbed<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Scrubbed<M> {
+    type Writer = ScrubbingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ScrubbingWriter(self.0.make_writer())
+    }
+}
+
+/// Each formatted event arrives in a single `write`, so a secret is never
+/// split across two calls.
+struct ScrubbingWriter<W>(W);
+
+impl<W: Write> Write for ScrubbingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let scrubbed = redact::scrub(&String::from_utf8_lossy(buf));
+        self.0.write_all(scrubbed.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Prints the library's status events as status lines (info) or results
+/// (warn). The log file records the events themselves.
+struct LibraryLines;
+
+impl<S: Subscriber> Layer<S> for LibraryLines {
+    fn on_event(&self, event: &Event<'_>, _ctx: layer::Context<'_, S>) {
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        let result = *event.metadata().level() <= Level::WARN;
+        print_line(&labeled(worker_label().as_deref(), &message.0), result);
+    }
+}
+
+/// The formatted message of an event.
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+/// Print a status line (`result` false, hidden by `--quiet`) or a final
+/// result, and record it in the log file.
+pub(crate) fn emit(line: &str, result: bool) {
+    emit_as(worker_label().as_deref(), line, result);
+}
+
+/// [`emit`] on behalf of the batch worker `label`, for lines printed from
+/// outside its task (an upload body streamed by the HTTP client).
+pub(crate) fn emit_as(label: Option<&str>, line: &str, result: bool) {
+    let line = labeled(label, line);
+    tracing::info!(target: TRANSCRIPT, "{}", line);
+    print_line(&line, result);
+}
+
+/// `line` prefixed with the batch worker it belongs to.
+fn labeled(label: Option<&str>, line: &str) -> String {
+    match label {
+        Some(label) if !line.is_empty() => format!("[{}] {}", label, line),
+        _ => line.to_string(),
+    }
+}
+
+fn print_line(line: &str, result: bool) {
+    if !result && quiet() {
+        return;
+    }
+    if status_to_stderr() {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// `println!` for human-readable progress lines; see the module docs.
+macro_rules! status {
+    () => {
+        $crate::output::emit("", false)
+    };
+    ($($arg:tt)*) => {
+        $crate::output::emit(&format!($($arg)*), false)
+    };
+}
+
+/// Like [`status!`], but for final results, which `--quiet` keeps.
+macro_rules! outcome {
+    () => {
+        $crate::output::emit("", true)
+    };
+    ($($arg:tt)*) => {
+        $crate::output::emit(&format!($($arg)*), true)
+    };
+}
+
+pub(crate) use outcome;
+pub(crate) use status;
+
+#[cfg(test)]
+mod tests {
+    use super::{as_worker, live_progress, worker_label};
+
+    #[tokio::test]
+    async fn worker_label_is_scoped_to_the_worker() {
+        assert_eq!(worker_label(), None);
+        let (label, live) = as_worker("App.pkg".to_string(), async {
+            (worker_label(), live_progress())
+        })
+        .await;
+        assert_eq!(label.as_deref(), Some("App.pkg"));
+        assert!(!live);
+        assert_eq!(worker_label(), None);
+    }
+}
@@ -0,0 +1,384 @@
+//! Read the version embedded in an installer.
+//!
+//! Flat `.pkg` files are xar archives: a small binary header, a
+//! zlib-compressed XML table of contents, then a heap holding the member
+//! files. The version lives in the `Distribution` file (product installers)
+//! or a component's `PackageInfo`. For `.dmg` files the image is mounted
+//! (macOS only) and the first app bundle's `Info.plist` is read.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result, bail};
+use flate2::read::ZlibDecoder;
+use quick_xml::events::Event;
+use regex::Regex;
+use serde::Deserialize;
+
+const XAR_MAGIC: &[u8; 4] = b"xar!";
+/// Refuse to inflate metadata members larger than this; they are tiny XML files.
+const MAX_MEMBER_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct XarDocument {
+    toc: XarToc,
+}
+
+#[derive(Debug, Deserialize)]
+struct XarToc {
+    #[serde(rename = "file", default)]
+    files: Vec<XarFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XarFile {
+    name: String,
+    data: Option<XarData>,
+    #[serde(rename = "file", default)]
+    files: Vec<XarFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XarData {
+    offset: u64,
+    length: u64,
+    encoding: Option<XarEncoding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XarEncoding {
+    #[serde(rename = "@style")]
+    style: String,
+}
+
+/// Version declared inside an installer, or None when it can't be determined
+/// for this file type.
+pub fn read_installer_version(path: &Path) -> Result<Option<String>> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pkg" => read_pkg_version(path),
+        "dmg" => read_dmg_version(path),
+        _ => Ok(None),
+    }
+}
+
+/// First version number appearing in a package or file name: a dotted one
+/// (e.g. `128.0.3`), or a single number written as `v2`.
+pub fn version_in_name(name: &str) -> Option<String> {
+    static PATTERN: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)+|\b[vV](\d+)\b").expect("valid regex"));
+    let found = PATTERN.captures(name)?;
+    found
+        .get(1)
+        .or_else(|| found.get(0))
+        .map(|m| m.as_str().to_string())
+}
+
+/// Whether two version strings agree: their components are equal as numbers,
+/// ignoring trailing `.0` components, so `2.0` agrees with `2` and `2.01`
+/// with `2.1`, but `16.89` does not agree with `16.89.1`.
+pub fn versions_agree(a: &str, b: &str) -> bool {
+    let components = |v: &str| {
+        let mut parts: Vec<String> = v
+            .split('.')
+            .map(|p| match p.parse::<u64>() {
+                Ok(n) => n.to_string(),
+                Err(_) => p.to_string(),
+            })
+            .collect();
+        while parts.len() > 1 && parts.last().is_some_and(|p| p == "0") {
+            parts.pop();
+        }
+        parts
+    };
+    components(a) == components(b)
+}
+
+/// Version from a flat package's `Distribution` or `PackageInfo`.
+pub fn read_pkg_version(path: &Path) -> Result<Option<String>> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let (toc, heap_start) = read_xar_toc(&mut file)
+        .with_context(|| format!("{} is not a readable flat package", path.display()))?;
+
+    if let Some(entry) = toc.files.iter().find(|f| f.name == "Distribution") {
+        let xml = read_member(&mut file, heap_start, entry)?;
+        if let Some(version) = version_from_xml(&xml, &["product", "pkg-ref"]) {
+            return Ok(Some(version));
+        }
+    }
+
+    if let Some(entry) = find_member(&toc.files, "PackageInfo") {
+        let xml = read_member(&mut file, heap_start, entry)?;
+        return Ok(version_from_xml(&xml, &["pkg-info"]));
+    }
+
+    Ok(None)
+}
+
+/// Parse the xar header and table of contents, returning the TOC and the
+/// offset at which the heap starts.
+fn read_xar_toc(file: &mut File) -> Result<(XarToc, u64)> {
+    let mut header = [0_u8; 28];
+    file.read_exact(&mut header)
+        .context("File is too short for a xar header")?;
+    if &header[0..4] != XAR_MAGIC {
+        bail!("missing xar signature");
+    }
+
+    let header_size = u64::from(u16::from_be_bytes([header[4], header[5]]));
+    let toc_compressed = u64::from_be_bytes(header[8..16].try_into().expect("8 bytes"));
+    if toc_compressed > MAX_MEMBER_SIZE {
+        bail!("xar table of contents is implausibly large");
+    }
+
+    file.seek(SeekFrom::Start(header_size))
+        .context("Failed to seek to xar table of contents")?;
+    let mut compressed = vec![0_u8; toc_compressed as usize];
+    file.read_exact(&mut compressed)
+        .context("Failed to read xar table of contents")?;
+
+    let mut xml = String::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .read_to_string(&mut xml)
+        .context("Failed to inflate xar table of contents")?;
+
+    let document: XarDocument =
+        quick_xml::de::from_str(&xml).context("Failed to parse xar table of contents")?;
+    Ok((document.toc, header_size + toc_compressed))
+}
+
+fn find_member<'a>(files: &'a [XarFile], name: &str) -> Option<&'a XarFile> {
+    files.iter().find_map(|f| {
+        if f.name == name && f.data.is_some() {
+            Some(f)
+        } else {
+            find_member(&f.files, name)
+        }
+    })
+}
+
+fn read_member(file: &mut File, heap_start: u64, entry: &XarFile) -> Result<String> {
+    let data = entry
+        .data
+        .as_ref()
+        .with_context(|| format!("xar member {} has no data", entry.name))?;
+    if data.length > MAX_MEMBER_SIZE {
+        bail!("xar member {} is implausibly large", entry.name);
+    }
+
+    file.seek(SeekFrom::Start(heap_start + data.offset))
+        .with_context(|| format!("Failed to seek to {}", entry.name))?;
+    let mut raw = vec![0_u8; data.length as usize];
+    file.read_exact(&mut raw)
+        .with_context(|| format!("Failed to read {}", entry.name))?;
+
+    let style = data.encoding.as_ref().map(|e| e.style.as_str());
+    let bytes = match style {
+        None | Some("application/octet-stream") => raw,
+        // xar labels zlib streams as gzip.
+        Some("application/x-gzip") => {
+            let mut out = Vec::new();
+            ZlibDecoder::new(raw.as_slice())
+                .take(MAX_MEMBER_SIZE)
+                .read_to_end(&mut out)
+                .with_context(|| format!("Failed to inflate {}", entry.name))?;
+            out
+        }
+        Some(other) => bail!("Unsupported xar encoding {} for {}", other, entry.name),
+    };
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// First non-empty `version` attribute on any of `elements`, in the order the
+/// element names are given.
+fn version_from_xml(xml: &str, elements: &[&str]) -> Option<String> {
+    let mut found: Vec<Option<String>> = vec![None; elements.len()];
+    let mut reader = quick_xml::Reader::from_str(xml);
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = e.name();
+                let Some(index) = elements
+                    .iter()
+                    .position(|el| el.as_bytes() == name.as_ref())
+                else {
+                    continue;
+                };
+                if found[index].is_some() {
+                    continue;
+                }
+                let version = e
+                    .attributes()
+                    .flatten()
+                    .find(|a| a.key.as_ref() == b"version")
+                    .and_then(|a| a.unescape_value().ok())
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty());
+                found[index] = version;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    found.into_iter().flatten().next()
+}
+
+#[cfg(target_os = "macos")]
+fn read_dmg_version(path: &Path) -> Result<Option<String>> {
+    use std::process::Command;
+
+    let mount_point =
+        std::env::temp_dir().join(format!("jamf-package-updater-{}", std::process::id()));
+    std::fs::create_dir_all(&mount_point)
+        .with_context(|| format!("Failed to create {}", mount_point.display()))?;
+
+    let status = Command::new("hdiutil")
+        .args([
+            "attach",
+            "-nobrowse",
+            "-readonly",
+            "-noautoopen",
+            "-mountpoint",
+        ])
+        .arg(&mount_point)
+        .arg(path)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .context("Failed to run hdiutil attach")?;
+    if !status.success() {
+        bail!("hdiutil could not mount {}", path.display());
+    }
+
+    let version = version_from_mounted_image(&mount_point);
+
+    let _ = Command::new("hdiutil")
+        .args(["detach", "-quiet"])
+        .arg(&mount_point)
+        .status();
+    let _ = std::fs::remove_dir(&mount_point);
+
+    version
+}
+
+#[cfg(not(target_os = "macos"))]
+fn read_dmg_version(_path: &Path) -> Result<Option<String>> {
+    // Mounting disk images needs hdiutil.
+    Ok(None)
+}
+
+#[cfg(target_os = "macos")]
+fn version_from_mounted_image(root: &Path) -> Result<Option<String>> {
+    let mut entries: Vec<_> = std::fs::read_dir(root)
+        .with_context(|| format!("Failed to read {}", root.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for entry in &entries {
+        let ext = entry
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if ext == "app" {
+            let info = entry.join("Contents/Info.plist");
+            let plist = plist::Value::from_file(&info)
+                .with_context(|| format!("Failed to read {}", info.display()))?;
+            let version = plist.as_dictionary().and_then(|d| {
+                d.get("CFBundleShortVersionString")
+                    .or_else(|| d.get("CFBundleVersion"))
+                    .and_then(|v| v.as_string())
+                    .map(str::to_string)
+            });
+            if version.is_some() {
+                return Ok(version);
+            }
+        } else if ext == "pkg" && entry.is_file() {
+            if let Some(version) = read_pkg_version(entry)? {
+                return Ok(Some(version));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+
+    use super::{read_pkg_version, version_from_xml, version_in_name, versions_agree};
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_version_from_flat_package_distribution() {
+        let distribution = zlib(
+            br#"<?xml version="1.0"?><installer-gui-script><pkg-ref id="com.example.app" version="2.3.0"/></installer-gui-script>"#,
+        );
+        let toc = format!(
+            r#"<?xml version="1.0"?><xar><toc><creation-time>now</creation-time><file id="1"><name>Distribution</name><type>file</type><data><length>{}</length><offset>0</offset><size>0</size><encoding style="application/x-gzip"/></data></file></toc></xar>"#,
+            distribution.len()
+        );
+        let toc = zlib(toc.as_bytes());
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(b"xar!");
+        archive.extend_from_slice(&28_u16.to_be_bytes());
+        archive.extend_from_slice(&1_u16.to_be_bytes());
+        archive.extend_from_slice(&(toc.len() as u64).to_be_bytes());
+        archive.extend_from_slice(&0_u64.to_be_bytes());
+        archive.extend_from_slice(&0_u32.to_be_bytes());
+        archive.extend_from_slice(&toc);
+        archive.extend_from_slice(&distribution);
+
+        let path = std::env::temp_dir().join(format!("pkginfo-test-{}.pkg", std::process::id()));
+        std::fs::write(&path, &archive).unwrap();
+        let version = read_pkg_version(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(version.unwrap().as_deref(), Some("2.3.0"));
+    }
+
+    #[test]
+    fn prefers_product_version_over_pkg_ref() {
+        let xml = r#"<installer-gui-script><pkg-ref id="a" version="1.0"/><product version="4.5.6"/></installer-gui-script>"#;
+        assert_eq!(
+            version_from_xml(xml, &["product", "pkg-ref"]).as_deref(),
+            Some("4.5.6")
+        );
+    }
+
+    #[test]
+    fn extracts_and_compares_name_versions() {
+        assert_eq!(
+            version_in_name("Firefox 128.0.3").as_deref(),
+            Some("128.0.3")
+        );
+        assert_eq!(version_in_name("Tool v2").as_deref(), Some("2"));
+        assert_eq!(version_in_name("Tool-v10.pkg").as_deref(), Some("10"));
+        assert_eq!(version_in_name("Zoom-latest"), None);
+        assert_eq!(version_in_name("x64 build"), None);
+        assert!(versions_agree("2.0", "2"));
+        assert!(versions_agree("2.01", "2.1"));
+        assert!(!versions_agree("2.1", "2.10"));
+        assert!(!versions_agree("2", "2.5"));
+        assert!(!versions_agree("16.89", "16.89.24091630"));
+    }
+}
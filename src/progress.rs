@@ -0,0 +1,249 @@
+use std::io::{self, IsTerminal};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+use jamf_package_updater::ProgressCallback;
+
+use crate::output::{self, status};
+
+/// A spinner for network waits that have no measurable progress (auth,
+/// search, metadata writes).
+///
+/// On a terminal it animates on stderr and is cleared when finished; when
+/// stderr is not a terminal it falls back to printing the message once, so CI
+/// logs still show what the tool is doing. With `quiet`, nothing is shown.
+pub struct Spinner {
+    bar: Option<ProgressBar>,
+}
+
+impl Spinner {
+    pub fn start(message: &str, quiet: bool) -> Self {
+        if quiet {
+            return Self { bar: None };
+        }
+        if !io::stderr().is_terminal() || output::worker_label().is_some() {
+            status!("{}", message);
+            return Self { bar: None };
+        }
+
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(
+            ProgressStyle::with_template("{spinner} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        bar.set_message(message.to_string());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Self { bar: Some(bar) }
+    }
+
+    /// Stop and clear the spinner.
+    pub fn finish(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Spinner {
+    // Also clear when an early `?` return drops the spinner.
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// Bytes-transferred progress for an upload or download, drawn from the
+/// client's [`ProgressCallback`] reports.
+///
+/// On a terminal it draws a bar on stderr with the percentage, transfer rate
+/// and ETA; otherwise it prints a line with the same figures at every 10% so
+/// CI logs show the transfer is moving. A retried upload that starts again
+/// from 0 starts the display over.
+pub struct TransferProgress {
+    bar: Option<ProgressBar>,
+    /// Past-tense verb for progress lines: "Uploaded" or "Downloaded".
+    verb: &'static str,
+    /// The batch worker this transfer belongs to; its lines are printed from
+    /// the HTTP client's task, which doesn't know it.
+    worker: Option<String>,
+    started: Mutex<Instant>,
+    sent: AtomicU64,
+    reported_tenths: AtomicU64,
+}
+
+impl TransferProgress {
+    /// Progress for an upload; None with `quiet`.
+    pub fn upload(quiet: bool) -> Option<ProgressCallback> {
+        Self::callback(quiet, "Uploaded")
+    }
+
+    /// Progress for a download; None with `quiet`.
+    pub fn download(quiet: bool) -> Option<ProgressCallback> {
+        Self::callback(quiet, "Downloaded")
+    }
+
+    fn callback(quiet: bool, verb: &'static str) -> Option<ProgressCallback> {
+        if quiet {
+            return None;
+        }
+        let progress = Self::new(verb);
+        Some(ProgressCallback::new(move |sent, total| {
+            progress.update(sent, total)
+        }))
+    }
+
+    fn new(verb: &'static str) -> Self {
+        let worker = output::worker_label();
+        let bar = (worker.is_none() && io::stderr().is_terminal()).then(|| {
+            let bar = ProgressBar::new(0);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "  [{bar:30}] {percent:>3}% {bytes}/{total_bytes} ({bytes_per_sec}, {eta} left)",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+            );
+            bar
+        });
+        Self {
+            bar,
+            verb,
+            worker,
+            started: Mutex::new(Instant::now()),
+            sent: AtomicU64::new(0),
+            reported_tenths: AtomicU64::new(0),
+        }
+    }
+
+    /// Record that `sent` of `total` bytes have been transferred.
+    fn update(&self, sent: u64, total: u64) {
+        let previous = self.sent.swap(sent, Ordering::Relaxed);
+        let mut started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+        if sent < previous {
+            *started = Instant::now();
+            self.reported_tenths.store(0, Ordering::Relaxed);
+            if let Some(bar) = &self.bar {
+                bar.reset();
+            }
+        }
+        if let Some(bar) = &self.bar {
+            bar.set_length(total);
+            bar.set_position(sent);
+            if sent >= total {
+                bar.finish_and_clear();
+            }
+            return;
+        }
+        if total == 0 {
+            return;
+        }
+
+        let tenths = (sent.min(total) * 10) / total;
+        let previous = self.reported_tenths.fetch_max(tenths, Ordering::Relaxed);
+        if tenths > previous {
+            let line = progress_line(self.verb, sent.min(total), total, started.elapsed());
+            output::emit_as(self.worker.as_deref(), &line, false);
+        }
+    }
+}
+
+impl Drop for TransferProgress {
+    // Clear the bar of a transfer that failed part way.
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// The `Scanning policy <n>/<total>...` counter for a policy scan, redrawn
+/// on one stderr line; None when live progress is off.
+pub fn policy_scan() -> Option<ProgressCallback> {
+    if !output::live_progress() {
+        return None;
+    }
+    let line = ScanLine(AtomicBool::new(false));
+    Some(ProgressCallback::new(move |done, total| {
+        eprint!("\r  Scanning policy {}/{}...", done, total);
+        if done == total {
+            eprintln!();
+        }
+        line.0.store(done < total, Ordering::Relaxed);
+    }))
+}
+
+/// Whether a scan left its counter line unfinished.
+struct ScanLine(AtomicBool);
+
+impl Drop for ScanLine {
+    // Finish the counter line of a failed scan before the error is printed.
+    fn drop(&mut self) {
+        if self.0.load(Ordering::Relaxed) {
+            eprintln!();
+        }
+    }
+}
+
+/// The non-terminal progress line: percentage, bytes, average rate and ETA.
+fn progress_line(verb: &str, sent: u64, total: u64, elapsed: Duration) -> String {
+    let percent = sent * 100 / total.max(1);
+    let secs = elapsed.as_secs_f64();
+    if sent == 0 || secs <= 0.0 {
+        return format!(
+            "  {} {}% ({} of {})",
+            verb,
+            percent,
+            HumanBytes(sent),
+            HumanBytes(total)
+        );
+    }
+    let rate = sent as f64 / secs;
+    let remaining = Duration::from_secs_f64((total - sent) as f64 / rate);
+    format!(
+        "  {} {}% ({} of {}, {}/s, {} left)",
+        verb,
+        percent,
+        HumanBytes(sent),
+        HumanBytes(total),
+        HumanBytes(rate as u64),
+        HumanDuration(remaining)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_line_reports_rate_and_eta() {
+        assert_eq!(
+            progress_line("Uploaded", 40 << 20, 100 << 20, Duration::from_secs(4)),
+            "  Uploaded 40% (40.00 MiB of 100.00 MiB, 10.00 MiB/s, 6 seconds left)"
+        );
+        assert_eq!(
+            progress_line("Downloaded", 0, 100, Duration::ZERO),
+            "  Downloaded 0% (0 B of 100 B)"
+        );
+    }
+
+    #[test]
+    fn update_reports_each_tenth_once_and_restarts_with_a_retry() {
+        let mut progress = TransferProgress::new("Uploaded");
+        // Print lines even when the tests run on a terminal.
+        progress.bar = None;
+        let reported = |p: &TransferProgress| p.reported_tenths.load(Ordering::Relaxed);
+
+        progress.update(5, 100);
+        assert_eq!(reported(&progress), 0);
+        progress.update(35, 100);
+        assert_eq!(reported(&progress), 3);
+        progress.update(36, 100);
+        assert_eq!(reported(&progress), 3);
+        progress.update(236, 100);
+        assert_eq!(reported(&progress), 10);
+
+        progress.update(10, 100);
+        assert_eq!(reported(&progress), 1);
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result};
+
+use crate::output;
+
+/// Whether we can ask the user questions: stdin is attached to a terminal,
+/// and this is not one of several files a batch processes in parallel.
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && output::worker_label().is_none()
+}
+
+/// Ask a yes/no question alongside the other status output and read the
+/// answer from stdin. Anything other than `y`/`yes` counts as no.
+pub fn confirm(question: &str) -> Result<bool> {
+    let answer = ask(&format!("{} [y/N] ", question))?.to_ascii_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// List `options` numbered from 1 and ask which one is meant. None when the
+/// answer is empty or not one of the numbers.
+pub fn choose(question: &str, options: &[String]) -> Result<Option<usize>> {
+    let mut prompt = format!("{}\n", question);
+    for (i, option) in options.iter().enumerate() {
+        prompt.push_str(&format!("  {}) {}\n", i + 1, option));
+    }
+    prompt.push_str(&format!(
+        "Enter 1-{} (or nothing to cancel): ",
+        options.len()
+    ));
+    Ok(parse_choice(&ask(&prompt)?, options.len()))
+}
+
+fn parse_choice(answer: &str, count: usize) -> Option<usize> {
+    match answer.trim().parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Some(n - 1),
+        _ => None,
+    }
+}
+
+/// Print `prompt` where status lines go and read one trimmed line of answer.
+fn ask(prompt: &str) -> Result<String> {
+    if output::status_to_stderr() {
+        eprint!("{}", prompt);
+        io::stderr().flush().context("Failed to flush stderr")?;
+    } else {
+        print!("{}", prompt);
+        io::stdout().flush().context("Failed to flush stdout")?;
+    }
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read answer from stdin")?;
+    Ok(answer.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_choice;
+
+    #[test]
+    fn choices_are_one_based_and_bounded() {
+        assert_eq!(parse_choice("1", 3), Some(0));
+        assert_eq!(parse_choice(" 3 ", 3), Some(2));
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("", 3), None);
+        assert_eq!(parse_choice("two", 3), None);
+    }
+}
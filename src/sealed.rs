@@ -0,0 +1,184 @@
+//! Client secrets encrypted for the config file (`auth --store encrypted-file`).
+//!
+//! A sealed secret is `v1$<key>$<log_n>$<salt>$<nonce>$<ciphertext>`, hex
+//! encoded: ChaCha20-Poly1305 under a key derived with scrypt from either a
+//! passphrase or this machine's ID. The passphrase keeps the file useless
+//! without it; the machine key only keeps a copied file from working on
+//! another host, since anyone who can read the machine ID can derive it.
+
+use anyhow::{Context, Result, anyhow, bail};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+const VERSION: &str = "v1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// scrypt cost (N = 2^15, r = 8, p = 1): about 32 MiB and a fraction of a
+/// second per derivation.
+const LOG_N: u8 = 15;
+
+/// What the key is derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeySource {
+    Passphrase,
+    Machine,
+}
+
+impl KeySource {
+    fn tag(self) -> &'static str {
+        match self {
+            KeySource::Passphrase => "passphrase",
+            KeySource::Machine => "machine",
+        }
+    }
+}
+
+/// Encrypt `secret` with `passphrase`, or with the machine key when there is
+/// none.
+pub(crate) fn seal(secret: &str, passphrase: Option<&str>) -> Result<String> {
+    let (source, material) = key_material(passphrase)?;
+    seal_with(secret, source, &material, LOG_N)
+}
+
+/// Decrypt a secret from [`seal`]. A secret sealed with a passphrase needs
+/// the same passphrase; one sealed with the machine key, the same machine.
+pub(crate) fn open(sealed: &str, passphrase: Option<&str>) -> Result<String> {
+    let parts: Vec<&str> = sealed.split('$').collect();
+    let [VERSION, tag, log_n, salt, nonce, ciphertext] = parts.as_slice() else {
+        bail!("Unrecognised sealed secret format");
+    };
+    let material = match *tag {
+        "passphrase" => passphrase
+            .context("The client secret is encrypted with a passphrase, but none was given")?
+            .as_bytes()
+            .to_vec(),
+        "machine" => machine_id()?,
+        other => bail!("Unknown key source '{}'", other),
+    };
+    let log_n: u8 = log_n.parse().context("Invalid scrypt cost")?;
+    // Refuse costs that would exhaust memory before the MAC is even checked.
+    if log_n > 20 {
+        bail!("scrypt cost 2^{} is too high", log_n);
+    }
+    let key = derive_key(&material, &from_hex(salt)?, log_n)?;
+    let nonce = from_hex(nonce)?;
+    if nonce.len() != NONCE_LEN {
+        bail!("Invalid nonce length");
+    }
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), from_hex(ciphertext)?.as_slice())
+        .map_err(|_| match *tag {
+            "passphrase" => anyhow!("Wrong passphrase, or the secret was altered"),
+            _ => anyhow!("Sealed on a different machine, or the secret was altered"),
+        })?;
+    String::from_utf8(plaintext).context("Decrypted secret is not UTF-8")
+}
+
+fn seal_with(secret: &str, source: KeySource, material: &[u8], log_n: u8) -> Result<String> {
+    let salt: [u8; SALT_LEN] = rand_bytes()?;
+    let key = derive_key(material, &salt, log_n)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, secret.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt the client secret"))?;
+    Ok(format!(
+        "{}${}${}${}${}${}",
+        VERSION,
+        source.tag(),
+        log_n,
+        hex(&salt),
+        hex(&nonce),
+        hex(&ciphertext)
+    ))
+}
+
+fn key_material(passphrase: Option<&str>) -> Result<(KeySource, Vec<u8>)> {
+    match passphrase {
+        Some("") => bail!("The passphrase is empty"),
+        Some(passphrase) => Ok((KeySource::Passphrase, passphrase.as_bytes().to_vec())),
+        None => Ok((KeySource::Machine, machine_id()?)),
+    }
+}
+
+fn derive_key(material: &[u8], salt: &[u8], log_n: u8) -> Result<Key> {
+    let params = scrypt::Params::new(log_n, 8, 1, 32)
+        .map_err(|e| anyhow!("Invalid scrypt parameters: {}", e))?;
+    let mut key = Key::default();
+    scrypt::scrypt(material, salt, &params, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn rand_bytes<const N: usize>() -> Result<[u8; N]> {
+    use chacha20poly1305::aead::rand_core::RngCore;
+    let mut bytes = [0_u8; N];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| anyhow!("No system randomness: {}", e))?;
+    Ok(bytes)
+}
+
+/// This host's stable ID: systemd's machine ID on Linux, the hardware UUID on
+/// macOS.
+fn machine_id() -> Result<Vec<u8>> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .context("Failed to run ioreg for the machine ID")?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        if let Some(uuid) = text
+            .lines()
+            .find(|l| l.contains("IOPlatformUUID"))
+            .and_then(|l| l.split('"').nth(3))
+        {
+            return Ok(uuid.as_bytes().to_vec());
+        }
+    }
+    for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+        if let Ok(id) = std::fs::read_to_string(path)
+            && !id.trim().is_empty()
+        {
+            return Ok(id.trim().as_bytes().to_vec());
+        }
+    }
+    bail!("No machine ID is available on this host; set a passphrase instead")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(value: &str) -> Result<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        bail!("Invalid hex in sealed secret");
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(value.get(i..i + 2).unwrap_or_default(), 16)
+                .context("Invalid hex in sealed secret")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_secret_opens_only_with_its_passphrase() {
+        // A low cost keeps the test fast; `open` reads it from the string.
+        let sealed = seal_with("s3cret", KeySource::Passphrase, b"hunter2", 4).unwrap();
+        assert!(sealed.starts_with("v1$passphrase$4$"));
+        assert!(!sealed.contains("s3cret"));
+        assert_eq!(open(&sealed, Some("hunter2")).unwrap(), "s3cret");
+        assert!(open(&sealed, Some("wrong")).is_err());
+        assert!(open(&sealed, None).is_err());
+
+        let flipped = if sealed.ends_with('0') { "1" } else { "0" };
+        let tampered = format!("{}{}", &sealed[..sealed.len() - 1], flipped);
+        assert!(open(&tampered, Some("hunter2")).is_err());
+    }
+}